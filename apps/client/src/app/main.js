@@ -1,5 +1,5 @@
 import { Howler } from 'howler'
-import { Input, Settings, Sound, WeaponId, Console } from '../core/helpers'
+import { ArmorType, Input, Settings, Sound, WeaponId, Console } from '../core/helpers'
 import { Map } from '../game/map'
 import { Player } from '../game/player'
 import { Physics, PhysicsConstants } from '../game/physics'
@@ -10,6 +10,7 @@ import { loadAssets, ensureModelLoaded } from '../render/assets'
 import { Bot } from '../bot/bot'
 import { BotManager } from '../bot/manager'
 import { NetworkClient } from '../net/client'
+import { DEMO_CONTROL, roomTickMillis } from '../net/protocol'
 import { getBackendWsUrl } from '../net/wsEndpoint'
 import { getGameTicket } from '../lib/api'
 
@@ -17,32 +18,69 @@ const AIM_INPUT_SCALE = 0.5
 const PICKUP_RADIUS = PhysicsConstants.PICKUP_RADIUS
 const MAX_AIM_DELTA = 12
 const GAUNTLET_PLAYER_RADIUS = PhysicsConstants.GAUNTLET_PLAYER_RADIUS
-const GAUNTLET_SPARK_OFFSET = PhysicsConstants.GAUNTLET_RANGE
 
 const PROJECTILE_WEAPONS = new Set(['rocket', 'grenade', 'plasma', 'bfg'])
 const PROJECTILE_KIND = Object.freeze({ rocket: 0, grenade: 1, plasma: 2, bfg: 3 })
 const AUTOBOT_DIFFICULTIES = new Set(['easy', 'medium', 'hard'])
+const RAIL_IMPACT_RADIUS = 4
+const MULTI_KILL_NAMES = { 2: 'Double kill', 3: 'Triple kill' }
 
 await loadAssets()
 await Map.loadFromQuery()
 Physics.setMap(Map.getRows(), Map.getCols(), Map.getBricksFlat())
 
 const ITEM_DEFS = {
-    health5: { kind: 'health', amount: 5, max: PhysicsConstants.MAX_HEALTH, respawn: 300 },
-    health25: { kind: 'health', amount: 25, max: PhysicsConstants.MAX_HEALTH, respawn: 300 },
-    health50: { kind: 'health', amount: 50, max: PhysicsConstants.MAX_HEALTH, respawn: 600 },
-    health100: { kind: 'health', amount: 100, max: PhysicsConstants.MEGA_HEALTH, respawn: 900 },
-    armor50: { kind: 'armor', amount: 50, respawn: 600 },
-    armor100: { kind: 'armor', amount: 100, respawn: 900 },
+    health5: { kind: 'health', amount: 5, respawn: 300 },
+    health25: { kind: 'health', amount: 25, respawn: 300 },
+    health50: { kind: 'health', amount: 50, respawn: 600 },
+    health100: { kind: 'health', amount: 100, mega: true, respawn: 900 },
+    armor50: { kind: 'armor', armorType: ArmorType.GREEN, amount: 50, respawn: 600 },
+    armor100: { kind: 'armor', armorType: ArmorType.YELLOW, amount: 100, respawn: 900 },
+    armor_red: { kind: 'armor', armorType: ArmorType.RED, amount: 200, respawn: 900 },
     quad: { kind: 'quad', respawn: 1200 },
+    regeneration: {
+        kind: 'powerup',
+        timer: 'regenTimer',
+        duration: 'REGEN_DURATION',
+        respawn: 1200,
+    },
+    haste: { kind: 'powerup', timer: 'hasteTimer', duration: 'HASTE_DURATION', respawn: 1200 },
+    battle_suit: {
+        kind: 'powerup',
+        timer: 'battleSuitTimer',
+        duration: 'BATTLE_SUIT_DURATION',
+        respawn: 1200,
+    },
+    invisibility: {
+        kind: 'powerup',
+        timer: 'invisibilityTimer',
+        duration: 'INVISIBILITY_DURATION',
+        respawn: 1200,
+    },
+    weapon_gauntlet: { kind: 'weapon', weaponId: WeaponId.GAUNTLET, respawn: 600 },
     weapon_machine: { kind: 'weapon', weaponId: WeaponId.MACHINE, respawn: 600 },
     weapon_shotgun: { kind: 'weapon', weaponId: WeaponId.SHOTGUN, respawn: 600 },
     weapon_grenade: { kind: 'weapon', weaponId: WeaponId.GRENADE, respawn: 600 },
     weapon_rocket: { kind: 'weapon', weaponId: WeaponId.ROCKET, respawn: 600 },
+    weapon_rail: { kind: 'weapon', weaponId: WeaponId.RAIL, respawn: 600 },
+    weapon_plasma: { kind: 'weapon', weaponId: WeaponId.PLASMA, respawn: 600 },
+    weapon_shaft: { kind: 'weapon', weaponId: WeaponId.SHAFT, respawn: 600 },
+    weapon_bfg: { kind: 'weapon', weaponId: WeaponId.BFG, respawn: 600 },
+    ammo_machine: { kind: 'ammo', weaponId: WeaponId.MACHINE, respawn: 600 },
+    ammo_shotgun: { kind: 'ammo', weaponId: WeaponId.SHOTGUN, respawn: 600 },
+    ammo_grenade: { kind: 'ammo', weaponId: WeaponId.GRENADE, respawn: 600 },
+    ammo_rocket: { kind: 'ammo', weaponId: WeaponId.ROCKET, respawn: 600 },
+    ammo_rail: { kind: 'ammo', weaponId: WeaponId.RAIL, respawn: 600 },
+    ammo_plasma: { kind: 'ammo', weaponId: WeaponId.PLASMA, respawn: 600 },
+    ammo_shaft: { kind: 'ammo', weaponId: WeaponId.SHAFT, respawn: 600 },
+    ammo_bfg: { kind: 'ammo', weaponId: WeaponId.BFG, respawn: 600 },
 }
 
 const localPlayer = new Player()
 const network = new NetworkClient()
+// Chase camera when `followId` names a player in the room, free camera otherwise.
+const spectatorCamera = { followId: null, x: 0, y: 0, prevX: 0, prevY: 0 }
+const SPECTATOR_CAMERA_SPEED = 12
 let netDebugEnabled = false
 let lastNetDebugUpdateAt = 0
 let cachedNetDebugText = ''
@@ -136,6 +174,12 @@ function setupExplosionHandlers() {
 }
 
 function gameLoop(timestamp, player) {
+    if (network.isSpectator()) {
+        spectatorFrame(timestamp)
+        requestAnimationFrame((ts) => gameLoop(ts, player))
+        return
+    }
+
     if (network.isActive()) {
         network.flushSnapshots()
         player.prevAimAngle = player.aimAngle
@@ -146,12 +190,14 @@ function gameLoop(timestamp, player) {
         }
 
         let isFiring = Input.isFiring
+        let firePressed = Input.firePressed
         let weaponSwitch = Input.weaponSwitch
         let weaponScroll = Input.weaponScroll
 
         if (autoBot.enabled) {
             const botInput = updateAutoBotForNetworkPlayer(player, remotePlayers)
             isFiring = botInput.isFiring
+            firePressed = false
             weaponSwitch = botInput.weaponSwitch
             weaponScroll = botInput.weaponScroll
         } else {
@@ -159,9 +205,12 @@ function gameLoop(timestamp, player) {
             processAimInput(player)
         }
 
+        // Scrolls resolve to a weapon locally; the server gets the target as a latched switch.
+        const previousWeapon = player.pendingWeapon
         applyWeaponInputPrediction(player, weaponSwitch, weaponScroll)
+        const switchWeapon = player.pendingWeapon !== previousWeapon ? player.pendingWeapon : -1
 
-        const didSendInput = network.sendInput(
+        network.sendInput(
             {
                 tick: timestamp | 0,
                 key_up: player.keyUp,
@@ -169,19 +218,19 @@ function gameLoop(timestamp, player) {
                 key_left: player.keyLeft,
                 key_right: player.keyRight,
                 mouse_down: isFiring,
-                weapon_switch: weaponSwitch,
-                weapon_scroll: weaponScroll,
+                fire_pressed: firePressed,
+                weapon_switch: switchWeapon,
+                weapon_scroll: 0,
                 aim_angle: player.aimAngle,
                 facing_left: player.facingLeft,
             },
             timestamp,
         )
 
-        if (didSendInput) {
-            if (!autoBot.enabled) {
-                Input.weaponSwitch = -1
-                Input.weaponScroll = 0
-            }
+        if (!autoBot.enabled) {
+            Input.firePressed = false
+            Input.weaponSwitch = -1
+            Input.weaponScroll = 0
         }
 
         const steps = Physics.updateAllPlayers([player], timestamp)
@@ -253,6 +302,59 @@ function gameLoop(timestamp, player) {
     requestAnimationFrame((ts) => gameLoop(ts, player))
 }
 
+function spectatorFrame(timestamp) {
+    network.flushSnapshots()
+    const remotePlayers = network.getRemotePlayers()
+    for (const remote of remotePlayers) {
+        remote.prevAimAngle = remote.aimAngle
+    }
+
+    if (Input.firePressed) {
+        Input.firePressed = false
+        followNextPlayer(remotePlayers)
+    }
+    Input.weaponSwitch = -1
+    Input.weaponScroll = 0
+
+    const steps = Physics.consumeTicks(timestamp)
+    for (let i = 0; i < steps; i++) {
+        Projectiles.update(1)
+    }
+
+    network.updateInterpolation()
+    const followed = remotePlayers.find((p) => p.id === spectatorCamera.followId) ?? null
+    spectatorCamera.prevX = spectatorCamera.x
+    spectatorCamera.prevY = spectatorCamera.y
+    if (followed) {
+        spectatorCamera.x = followed.x
+        spectatorCamera.y = followed.y
+    } else {
+        const dx = (Input.keyRight ? 1 : 0) - (Input.keyLeft ? 1 : 0)
+        const dy = (Input.keyDown ? 1 : 0) - (Input.keyUp ? 1 : 0)
+        spectatorCamera.x += dx * SPECTATOR_CAMERA_SPEED
+        spectatorCamera.y += dy * SPECTATOR_CAMERA_SPEED
+    }
+
+    updateNetDebugOverlay(timestamp)
+    Render.renderSpectator(
+        followed ?? spectatorCamera,
+        followed,
+        getRemoteBotWrappers(remotePlayers),
+    )
+}
+
+// Cycles through the room's players, then back to the free camera.
+function followNextPlayer(remotePlayers) {
+    const ids = remotePlayers.map((p) => p.id).sort((a, b) => a - b)
+    const idx = ids.indexOf(spectatorCamera.followId)
+    spectatorCamera.followId = idx + 1 < ids.length ? ids[idx + 1] : null
+    Console.writeText(
+        spectatorCamera.followId === null
+            ? '[spec] Free camera'
+            : `[spec] Following player ${spectatorCamera.followId}`,
+    )
+}
+
 function getRemoteBotWrappers(remotePlayers) {
     if (remotePlayers !== remoteBotWrapperSource) {
         remoteBotWrapperSource = remotePlayers
@@ -307,7 +409,7 @@ function applyWeaponInputPrediction(player, weaponSwitch, weaponScroll) {
     const direction = weaponScroll < 0 ? -1 : 1
     const total = player.weapons.length
     for (let step = 1; step <= total; step++) {
-        const next = (player.currentWeapon + direction * step + total) % total
+        const next = (player.pendingWeapon + direction * step + total) % total
         if (player.weapons[next]) {
             player.switchWeapon(next)
             return
@@ -337,8 +439,17 @@ function initNetwork() {
         onClose: () => {
             lastAppliedWorldSnapshotTick = -1
         },
+        onReconnecting: (attempt) => {
+            Console.writeText(`[mp] Connection lost, reconnecting (attempt ${attempt})...`)
+        },
+        onReconnectFailed: () => {
+            Console.writeText('[mp] Could not reconnect')
+        },
+        onTransportFallback: (err) => {
+            Console.writeText(`[mp] WebRTC failed (${err.message}), using WebSocket`)
+        },
         onRoomState: async (room) => {
-            await syncTickRateFromRoom(room)
+            await syncTickFromRoom(room)
             if (room?.map) {
                 const loaded = await Map.loadFromName(room.map)
                 if (loaded) {
@@ -346,6 +457,10 @@ function initNetwork() {
                     Render.renderMap()
                 }
             }
+            if (network.isSpectator() && spectatorCamera.followId === null) {
+                spectatorCamera.x = (Map.getCols() * PhysicsConstants.TILE_W) / 2
+                spectatorCamera.y = (Map.getRows() * PhysicsConstants.TILE_H) / 2
+            }
             Render.setSceneReady(true)
         },
         onSnapshot: (snapshot) => {
@@ -355,11 +470,16 @@ function initNetwork() {
                 lastAppliedWorldSnapshotTick = tick
             }
             if (snapshot?.items) Map.setItemStates(snapshot.items)
+            if (snapshot?.dropped_items) Map.setDroppedItems(snapshot.dropped_items)
             if (snapshot?.events) applySnapshotEvents(snapshot.events)
         },
         onPlayerLeft: (playerId) => {
             Render.cleanupBotSprite(playerId)
         },
+        onDemoState: (state) => {
+            const status = state.paused ? 'paused' : `playing x${state.speed}`
+            Console.writeText(`[demo] tick ${state.tick}/${state.end_tick} ${status}`)
+        },
     })
     network.setPredictor((player, input) => {
         applyPredictedInput(player, input)
@@ -393,11 +513,11 @@ async function autoConnectFromLobby() {
     }
 }
 
-async function syncTickRateFromRoom(room) {
-    const hz = Number(room?.tick_rate ?? room?.tickRate)
-    if (!Number.isFinite(hz) || hz <= 0) return
-    Physics.setTickRateHz(hz)
-    network.setServerTickRateHz(hz)
+async function syncTickFromRoom(room) {
+    const ms = roomTickMillis(room)
+    if (!Number.isFinite(ms) || ms <= 0) return
+    Physics.setTickMillis(ms)
+    network.setServerTickMillis(ms)
 }
 
 function setupConsoleCommands() {
@@ -416,15 +536,16 @@ function setupConsoleCommands() {
             if (action === 'connect') {
                 const username = args[1]?.trim()
                 if (!username) {
-                    Console.writeText('Usage: mp connect <username> [room]')
+                    Console.writeText('Usage: mp connect <username> [room] [ruleset]')
                     return
                 }
                 const roomId = args[2]?.trim() || 'room-1'
+                const ruleset = args[3]?.trim() || null
                 const url = getBackendWsUrl()
 
                 try {
                     Console.writeText(`Connecting to ${roomId}...`)
-                    await network.connect({ url, username, roomId })
+                    await network.connect({ url, username, roomId, ruleset })
                     Console.writeText(`Connected as ${username}`)
                 } catch (err) {
                     Console.writeText(`Connection failed: ${err.message}`)
@@ -432,17 +553,91 @@ function setupConsoleCommands() {
                 return
             }
 
+            if (action === 'spectate') {
+                const username = args[1]?.trim()
+                if (!username) {
+                    Console.writeText('Usage: mp spectate <username> [room]')
+                    return
+                }
+                const roomId = args[2]?.trim() || 'room-1'
+                const url = getBackendWsUrl()
+                spectatorCamera.followId = null
+
+                try {
+                    Console.writeText(`Spectating ${roomId}... (fire cycles players)`)
+                    await network.connect({ url, username, roomId, spectator: true })
+                } catch (err) {
+                    Console.writeText(`Connection failed: ${err.message}`)
+                }
+                return
+            }
+
             if (action === 'disconnect') {
                 network.disconnect()
                 Console.writeText('Disconnected')
                 return
             }
 
-            Console.writeText('Usage: mp connect <username> [room] | mp disconnect')
+            Console.writeText(
+                'Usage: mp connect <username> [room] | mp spectate <username> [room] | mp disconnect',
+            )
         },
         'connect/disconnect multiplayer',
     )
 
+    Console.registerCommand(
+        'demo',
+        async (args) => {
+            const action = args[0]?.toLowerCase()
+
+            if (action === 'watch') {
+                const name = args[1]?.trim()
+                if (!name) {
+                    Console.writeText('Usage: demo watch <name> [username]')
+                    return
+                }
+                const username = args[2]?.trim() || 'viewer'
+                network.disconnect()
+                spectatorCamera.followId = null
+                try {
+                    Console.writeText(`Loading demo ${name}...`)
+                    await network.connect({ url: getBackendWsUrl(), username, demo: name })
+                } catch (err) {
+                    Console.writeText(`Connection failed: ${err.message}`)
+                }
+                return
+            }
+
+            if (action === 'pause') {
+                network.sendDemoControl(DEMO_CONTROL.PAUSE)
+                return
+            }
+            if (action === 'resume') {
+                network.sendDemoControl(DEMO_CONTROL.RESUME)
+                return
+            }
+            if (action === 'seek') {
+                const tick = Number.parseInt(args[1], 10)
+                if (Number.isFinite(tick) && tick >= 0) {
+                    network.sendDemoControl(DEMO_CONTROL.SEEK, tick)
+                    return
+                }
+            }
+            if (action === 'speed') {
+                const speed = Number.parseFloat(args[1])
+                if (Number.isFinite(speed) && speed > 0) {
+                    network.sendDemoControl(DEMO_CONTROL.SPEED, 0, speed)
+                    return
+                }
+            }
+
+            Console.writeText(
+                'Usage: demo watch <name> [username] | pause | resume | seek <tick> | speed <x>',
+            )
+        },
+        'watch a recorded demo',
+    )
+
     Console.registerCommand(
         'autobot',
         (args) => {
@@ -738,6 +933,12 @@ function applySnapshotEvents(events) {
                     startY: event.start_y,
                     trace: { x: event.end_x, y: event.end_y },
                 })
+                for (const impact of event.impacts ?? []) {
+                    Render.addBulletImpact(impact.x, impact.y, { radius: RAIL_IMPACT_RADIUS })
+                }
+                break
+            case 'multi_kill':
+                handleMultiKillEvent(event)
                 break
             case 'shaft':
                 Render.addShaftShot({
@@ -754,10 +955,10 @@ function applySnapshotEvents(events) {
                     const attackerId = pendingGauntletAttackers.shift()
                     const attacker = getPlayerById(attackerId)
                     if (attacker && !attacker.dead) {
-                        const { x, y } = getWeaponTip(attacker, GAUNTLET_SPARK_OFFSET)
+                        const { x, y } = getWeaponTip(attacker, PhysicsConstants.GAUNTLET_RANGE)
                         Render.addGauntletSpark(x, y, {
                             followPlayer: attacker,
-                            weaponTipOffset: GAUNTLET_SPARK_OFFSET,
+                            weaponTipOffset: PhysicsConstants.GAUNTLET_RANGE,
                         })
                     } else {
                         // Fallback if events arrive without matching attacker context.
@@ -778,12 +979,47 @@ function applySnapshotEvents(events) {
             case 'damage':
                 handleDamageEvent(event)
                 break
+            case 'item_pickup':
+                handleItemPickupEvent(event)
+                break
+            case 'item_respawn':
+                {
+                    const item = Map.getItems()[event.item_index]
+                    if (item) item.active = true
+                }
+                break
+            case 'quad_start':
+            case 'quad_expire':
+                {
+                    const player = getPlayerById(event.player_id)
+                    if (player) player.quadDamage = event.type === 'quad_start'
+                }
+                break
+            case 'player_respawn':
+                if (event.player_id === localPlayer.id) Sound.respawn()
+                break
             default:
                 break
         }
     }
 }
 
+function handleItemPickupEvent(event) {
+    const item = event.item_index == null ? null : Map.getItems()[event.item_index]
+    if (item) item.active = false
+    if (event.player_id !== localPlayer.id) return
+    playItemSound(ITEM_DEFS[item?.type ?? Map.getItemType(event.token)])
+}
+
+function handleMultiKillEvent(event) {
+    const name = MULTI_KILL_NAMES[event.count] ?? `${event.count}x kill`
+    if (event.attacker_id === localPlayer.id) {
+        Render.announce(`${name}!`)
+    } else {
+        Console.writeText(`Player ${event.attacker_id}: ${name.toLowerCase()}`)
+    }
+}
+
 function handleDamageEvent(event) {
     const targetId = event?.target_id
     if (!targetId) return
@@ -880,12 +1116,12 @@ function setAutoBotEnabled(enabled, difficulty = autoBot.difficulty) {
 }
 
 function updateAutoBotForNetworkPlayer(player, remotePlayers) {
-    const previousWeapon = player.currentWeapon
+    const previousWeapon = player.pendingWeapon
     autoBot.controller.update([player, ...remotePlayers])
 
     return {
         isFiring: autoBot.controller.wantsToFire,
-        weaponSwitch: player.currentWeapon !== previousWeapon ? player.currentWeapon : -1,
+        weaponSwitch: player.pendingWeapon !== previousWeapon ? player.pendingWeapon : -1,
         weaponScroll: 0,
     }
 }
@@ -940,7 +1176,9 @@ function updateFacingDirection(player) {
 }
 
 function processFiring(player) {
-    if (!Input.isFiring || player.dead) return
+    const firing = Input.isFiring || Input.firePressed
+    Input.firePressed = false
+    if (!firing || player.dead) return
 
     const otherPlayers = BotManager.getOtherPlayers(player)
     const couldFire = player.canFire()
@@ -972,10 +1210,10 @@ function processFireResult(player, result, otherPlayers) {
         }
     } else if (result?.type === 'gauntlet') {
         Sound.gauntlet('active')
-        const { x, y } = getWeaponTip(player, GAUNTLET_SPARK_OFFSET)
+        const { x, y } = getWeaponTip(player, PhysicsConstants.GAUNTLET_RANGE)
         Render.addGauntletSpark(x, y, {
             followPlayer: player,
-            weaponTipOffset: GAUNTLET_SPARK_OFFSET,
+            weaponTipOffset: PhysicsConstants.GAUNTLET_RANGE,
         })
         applyMeleeDamage(player, result, otherPlayers)
     }
@@ -1010,7 +1248,7 @@ function processItemPickups(player) {
 
         if (!isPlayerNearItem(player, item)) continue
 
-        applyItemEffect(player, item)
+        if (!applyItemEffect(player, item)) continue
         item.active = false
         item.respawnTimer = ITEM_DEFS[item.type]?.respawn ?? 300
     }
@@ -1028,29 +1266,58 @@ function isPlayerNearItem(player, item) {
     return Math.hypot(player.x - x, player.y - y) <= PICKUP_RADIUS
 }
 
+// Returns false when the player can't take the item and it should stay
 function applyItemEffect(player, item) {
     const def = ITEM_DEFS[item.type]
-    if (!def) return
+    if (!def) return true
 
     switch (def.kind) {
         case 'health':
-            player.giveHealth(def.amount, def.max)
+            player.giveHealth(
+                def.amount,
+                def.mega ? PhysicsConstants.MEGA_HEALTH : PhysicsConstants.MAX_HEALTH,
+            )
+            break
+        case 'armor':
+            if (!player.giveArmor(def.armorType, def.amount)) return false
+            break
+        case 'quad':
+            player.quadDamage = true
+            player.quadTimer = PhysicsConstants.QUAD_DURATION
+            break
+        case 'powerup':
+            player[def.timer] = PhysicsConstants[def.duration]
+            break
+        case 'weapon':
+            player.giveWeapon(def.weaponId, PhysicsConstants.PICKUP_AMMO[def.weaponId] ?? 0)
+            break
+        case 'ammo':
+            player.giveAmmo(def.weaponId, PhysicsConstants.AMMO_BOX[def.weaponId] ?? 0)
+            break
+    }
+    playItemSound(def)
+    return true
+}
+
+function playItemSound(def) {
+    switch (def?.kind) {
+        case 'health':
             Sound.health(def.amount)
             break
         case 'armor':
-            player.giveArmor(def.amount)
             if (def.amount < 50) Sound.shard()
             else Sound.armor()
             break
         case 'quad':
-            player.quadDamage = true
-            player.quadTimer = PhysicsConstants.QUAD_DURATION
+        case 'powerup':
             Sound.quad()
             break
         case 'weapon':
-            player.giveWeapon(def.weaponId, PhysicsConstants.PICKUP_AMMO[def.weaponId] ?? 0)
             Sound.wpPickup()
             break
+        case 'ammo':
+            Sound.ammoPickup()
+            break
     }
 }
 
@@ -66,14 +66,23 @@ const ITEM_PRIORITY = {
     health100: 90,
     armor50: 35,
     armor100: 70,
+    armor_red: 90,
     quad: 140,
     weapon_machine: 20,
     weapon_shotgun: 28,
     weapon_grenade: 32,
     weapon_rocket: 40,
+    weapon_rail: 38,
+    weapon_plasma: 30,
+    weapon_shaft: 34,
+    weapon_bfg: 45,
+    regeneration: 80,
+    haste: 60,
+    battle_suit: 90,
+    invisibility: 70,
 }
 
-const HIGH_VALUE_ITEMS = new Set(['health100', 'quad', 'armor100'])
+const HIGH_VALUE_ITEMS = new Set(['health100', 'quad', 'armor100', 'armor_red'])
 const PROJECTILE_WEAPONS = new Set([
     WeaponId.GRENADE,
     WeaponId.ROCKET,
@@ -1109,7 +1118,7 @@ export class Bot {
         }
 
         if (!currentHasAmmo) {
-            if (nextWeapon != null && nextWeapon !== this.player.currentWeapon) {
+            if (nextWeapon != null && nextWeapon !== this.player.pendingWeapon) {
                 this.player.switchWeapon(nextWeapon)
                 this.lastWeaponRange = rangeZone
                 this.weaponSwitchCooldown = WEAPON_SWITCH_COOLDOWN
@@ -1120,7 +1129,7 @@ export class Bot {
         if (this.weaponSwitchCooldown > 0) return
         if (rangeZone === this.lastWeaponRange) return
 
-        if (nextWeapon != null && nextWeapon !== this.player.currentWeapon) {
+        if (nextWeapon != null && nextWeapon !== this.player.pendingWeapon) {
             this.player.switchWeapon(nextWeapon)
             this.weaponSwitchCooldown = WEAPON_SWITCH_COOLDOWN
         }
@@ -2258,6 +2267,14 @@ function weaponIdFromItemType(type) {
             return WeaponId.GRENADE
         case 'weapon_rocket':
             return WeaponId.ROCKET
+        case 'weapon_rail':
+            return WeaponId.RAIL
+        case 'weapon_plasma':
+            return WeaponId.PLASMA
+        case 'weapon_shaft':
+            return WeaponId.SHAFT
+        case 'weapon_bfg':
+            return WeaponId.BFG
         default:
             return null
     }
@@ -374,6 +374,7 @@ class BotManagerClass {
             case 'quad':
             case 'health100':
             case 'armor100':
+            case 'armor_red':
                 return 1.6
             case 'health50':
             case 'armor50':
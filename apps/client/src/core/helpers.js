@@ -13,6 +13,20 @@ export const WeaponId = {
     BFG: 8,
 }
 
+export const ArmorType = {
+    NONE: 0,
+    GREEN: 1,
+    YELLOW: 2,
+    RED: 3,
+}
+
+export const WeaponState = {
+    READY: 0,
+    FIRING: 1,
+    LOWERING: 2,
+    RAISING: 3,
+}
+
 export const WeaponConstants = {
     NAMES: [
         'Gauntlet',
@@ -40,6 +54,8 @@ export const Input = {
     mouseDeltaY: 0,
     mouseDown: false,
     fireKeyDown: false,
+    // Set on every fire press and cleared once a tick has consumed it
+    firePressed: false,
     pointerLocked: false,
     weaponSwitch: -1,
     weaponScroll: 0,
@@ -166,6 +182,7 @@ function initInputHandlers() {
         const isFireKey = e.code === 'Space' || e.key === ' ' || e.key === 'Spacebar'
         if (isFireKey) {
             e.preventDefault()
+            if (pressed && !Input.fireKeyDown) Input.firePressed = true
             Input.fireKeyDown = pressed
             return
         }
@@ -197,7 +214,9 @@ function initInputHandlers() {
     })
 
     document.addEventListener('mousedown', (e) => {
-        if (e.button === 0) Input.mouseDown = true
+        if (e.button !== 0) return
+        Input.mouseDown = true
+        Input.firePressed = true
     })
     document.addEventListener('mouseup', (e) => {
         if (e.button === 0) Input.mouseDown = false
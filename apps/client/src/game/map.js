@@ -11,14 +11,41 @@ const ITEM_TOKENS = {
     6: 'health50',
     A: 'armor100',
     a: 'armor50',
+    V: 'armor_red',
     Q: 'quad',
+    E: 'regeneration',
+    F: 'haste',
+    U: 'battle_suit',
+    I: 'invisibility',
+    G: 'weapon_gauntlet',
     M: 'weapon_machine',
     T: 'weapon_shotgun',
     3: 'weapon_grenade',
     4: 'weapon_rocket',
+    L: 'weapon_rail',
+    P: 'weapon_plasma',
+    S: 'weapon_shaft',
+    B: 'weapon_bfg',
+    m: 'ammo_machine',
+    t: 'ammo_shotgun',
+    g: 'ammo_grenade',
+    k: 'ammo_rocket',
+    l: 'ammo_rail',
+    p: 'ammo_plasma',
+    s: 'ammo_shaft',
+    b: 'ammo_bfg',
 }
 
-const state = { rows: 0, cols: 0, bricks: [], bricksFlat: [], colors: [], respawns: [], items: [] }
+const state = {
+    rows: 0,
+    cols: 0,
+    bricks: [],
+    bricksFlat: [],
+    colors: [],
+    respawns: [],
+    items: [],
+    droppedItems: [],
+}
 
 export const Map = {
     async loadFromQuery() {
@@ -57,6 +84,8 @@ export const Map = {
     getCols: () => state.cols,
     getBricksFlat: () => state.bricksFlat,
     getItems: () => state.items,
+    getDroppedItems: () => state.droppedItems,
+    getItemType: (token) => ITEM_TOKENS[token],
 
     getRandomRespawn() {
         const { respawns } = state
@@ -72,6 +101,13 @@ export const Map = {
                 src.respawn_timer ?? src.respawnTimer ?? state.items[i].respawnTimer
         }
     },
+    // Weapons and powerups dropped on death, positioned in world pixels
+    setDroppedItems(droppedItems) {
+        if (!Array.isArray(droppedItems)) return
+        state.droppedItems = droppedItems
+            .map((src) => ({ id: src.id, type: ITEM_TOKENS[src.token], x: src.x, y: src.y }))
+            .filter((item) => item.type)
+    },
 }
 
 function loadFromUrl(mapText) {
@@ -102,6 +138,7 @@ function parseMapText(mapText) {
     state.colors = []
     state.respawns = []
     state.items = []
+    state.droppedItems = []
 
     for (let row = 0; row < state.rows; row++) {
         const line = lines[row] ?? ''
@@ -12,6 +12,7 @@ const runtime = {
     mapCols: 0,
     scratchInput: null,
     scratchOutput: null,
+    decayOut: new Int32Array(3),
     playerStates: new Map(),
     frameMs: 16,
 }
@@ -40,10 +41,22 @@ async function initKernel() {
         velocityY: 0,
     }
 
-    // Load all constants from WASM - Rust physics_core/src/constants.rs is the source of truth
-    const weaponCount = module.get_weapon_count()
     runtime.frameMs = module.get_tick_millis()
-    PhysicsConstants = {
+    PhysicsConstants = readConstants(module)
+}
+
+// Rebuilds the constants after `wasm_set_ruleset` so prediction and the HUD
+// use the room's ruleset. Keeps the object identity for existing importers.
+export function reloadPhysicsConstants() {
+    if (!runtime.module) return
+    Object.assign(PhysicsConstants, readConstants(runtime.module))
+}
+
+// Load all constants from WASM - Rust physics_core is the source of truth,
+// and ruleset-backed getters reflect the room's current ruleset.
+function readConstants(module) {
+    const weaponCount = module.get_weapon_count()
+    return {
         WEAPON_COUNT: weaponCount,
         // Projectile physics
         GRAVITY: module.get_projectile_gravity(),
@@ -85,6 +98,8 @@ async function initKernel() {
         // Weapon stats (indexed by WeaponId)
         getDamage: module.get_damage,
         getFireRate: module.get_fire_rate,
+        getWeaponLowerTime: module.get_weapon_lower_time,
+        getWeaponRaiseTime: module.get_weapon_raise_time,
         getProjectileSpeed: module.get_projectile_speed,
         getProjectileOffset: module.get_projectile_offset,
         getWeaponPush: module.get_weapon_push,
@@ -93,18 +108,23 @@ async function initKernel() {
         getPickupAmmo: module.get_pickup_ammo,
         DEFAULT_AMMO: Array.from({ length: weaponCount }, (_, i) => module.get_default_ammo(i)),
         PICKUP_AMMO: Array.from({ length: weaponCount }, (_, i) => module.get_pickup_ammo(i)),
+        AMMO_BOX: Array.from({ length: weaponCount }, (_, i) => module.get_ammo_box(i)),
         PROJECTILE_OFFSET: Array.from({ length: weaponCount }, (_, i) =>
             module.get_projectile_offset(i),
         ),
 
         // Game constants
         MAX_HEALTH: module.get_max_health(),
-        MAX_ARMOR: module.get_max_armor(),
+        ARMOR_CAP: Array.from({ length: 4 }, (_, i) => module.get_armor_cap(i)),
         MEGA_HEALTH: module.get_mega_health(),
-        ARMOR_ABSORPTION: module.get_armor_absorption(),
+        ARMOR_ABSORPTION: Array.from({ length: 4 }, (_, i) => module.get_armor_absorption(i)),
         SELF_DAMAGE_REDUCTION: module.get_self_damage_reduction(),
         QUAD_MULTIPLIER: module.get_quad_multiplier(),
         QUAD_DURATION: module.get_quad_duration(),
+        REGEN_DURATION: module.get_regen_duration(),
+        HASTE_DURATION: module.get_haste_duration(),
+        BATTLE_SUIT_DURATION: module.get_battle_suit_duration(),
+        INVISIBILITY_DURATION: module.get_invisibility_duration(),
         GIB_THRESHOLD: -40,
         RESPAWN_TIME: module.get_respawn_time(),
         SPAWN_PROTECTION: module.get_spawn_protection(),
@@ -124,7 +144,6 @@ async function initKernel() {
         TILE_W: module.get_tile_w(),
         TILE_H: module.get_tile_h(),
     }
-
 }
 
 await initKernel()
@@ -168,10 +187,26 @@ export const Physics = {
         return frames
     },
 
-    setTickRateHz(tickRateHz) {
-        const hz = Number(tickRateHz)
-        if (!Number.isFinite(hz) || hz <= 0) return
-        runtime.frameMs = 1000 / hz
+    // PlayerState::decay, run in wasm against the room's ruleset.
+    decayPlayer(player) {
+        const out = runtime.decayOut
+        runtime.module.wasm_decay_player(
+            player.health,
+            player.armor,
+            player.regenTimer,
+            player.decayTimer,
+            player.dead,
+            out,
+        )
+        player.health = out[0]
+        player.armor = out[1]
+        player.decayTimer = out[2]
+    },
+
+    setTickMillis(tickMillis) {
+        const ms = Number(tickMillis)
+        if (!Number.isFinite(ms) || ms <= 0) return
+        runtime.frameMs = ms
     },
 
     getFrameMs() {
@@ -317,6 +352,7 @@ function stepPlayer(player) {
         entry.mirror.dead = player.dead
     }
 
+    entry.state.set_haste(player.hasteTimer > 0)
     runtime.scratchInput.set(player.keyUp, player.keyDown, player.keyLeft, player.keyRight)
     runtime.kernel.step_player(entry.state, runtime.scratchInput, runtime.map)
 
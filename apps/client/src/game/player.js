@@ -1,5 +1,5 @@
-import { Sound, WeaponId } from '../core/helpers'
-import { PhysicsConstants } from './physics'
+import { ArmorType, Sound, WeaponId, WeaponState } from '../core/helpers'
+import { Physics, PhysicsConstants } from './physics'
 import { Map } from './map'
 import { DEFAULT_MODEL, DEFAULT_SKIN } from '../core/models'
 import { Weapons } from './weapons'
@@ -38,6 +38,7 @@ export class Player {
 
     health = PhysicsConstants.MAX_HEALTH
     armor = 0
+    armorType = ArmorType.NONE
     dead = false
     respawnTimer = 0
     spawnProtection = 0
@@ -48,10 +49,18 @@ export class Player {
     weapons = Array(PhysicsConstants.WEAPON_COUNT).fill(true)
     ammo = createAmmoArray()
     currentWeapon = WeaponId.ROCKET
+    pendingWeapon = WeaponId.ROCKET
+    weaponState = WeaponState.READY
+    weaponTimer = 0
     fireCooldown = 0
 
     quadDamage = false
     quadTimer = 0
+    regenTimer = 0
+    decayTimer = 0
+    hasteTimer = 0
+    battleSuitTimer = 0
+    invisibilityTimer = 0
 
     constructor(options = {}) {
         if (options.model) this.model = options.model
@@ -92,6 +101,30 @@ export class Player {
         if (this.quadDamage && --this.quadTimer <= 0) {
             this.quadDamage = false
         }
+        if (this.regenTimer > 0) this.regenTimer--
+        if (this.hasteTimer > 0) this.hasteTimer--
+        if (this.battleSuitTimer > 0) this.battleSuitTimer--
+        if (this.invisibilityTimer > 0) this.invisibilityTimer--
+        this.updateWeapon()
+        Physics.decayPlayer(this)
+    }
+
+    // Mirrors PlayerState::update_weapon: lower the held weapon, then raise the pending one
+    updateWeapon() {
+        if (this.weaponTimer > 0) this.weaponTimer--
+        if (this.weaponTimer > 0) return
+        if (this.weaponState === WeaponState.FIRING && this.fireCooldown > 0) return
+        if (this.weaponState === WeaponState.LOWERING) {
+            this.currentWeapon = this.pendingWeapon
+            this.weaponState = WeaponState.RAISING
+            this.weaponTimer = PhysicsConstants.getWeaponRaiseTime(this.currentWeapon)
+            if (this.weaponTimer > 0) return
+        }
+        this.weaponState = WeaponState.READY
+        if (this.pendingWeapon !== this.currentWeapon) {
+            this.weaponState = WeaponState.LOWERING
+            this.weaponTimer = PhysicsConstants.getWeaponLowerTime(this.currentWeapon)
+        }
     }
 
     decayVisualCorrection(factor = 0.85) {
@@ -112,6 +145,7 @@ export class Player {
 
     canFire() {
         if (this.dead || this.fireCooldown > 0) return false
+        if (this.weaponState !== WeaponState.READY) return false
         const ammo = this.ammo[this.currentWeapon]
         return ammo === -1 || ammo > 0
     }
@@ -124,17 +158,22 @@ export class Player {
         }
 
         this.fireCooldown = Weapons.getFireRate(this.currentWeapon)
+        this.weaponState = WeaponState.FIRING
         return Weapons.fire(this, this.currentWeapon)
     }
 
     switchWeapon(weaponId) {
         if (weaponId >= 0 && weaponId < PhysicsConstants.WEAPON_COUNT && this.weapons[weaponId]) {
-            this.currentWeapon = weaponId
+            this.pendingWeapon = weaponId
         }
     }
 
     giveWeapon(weaponId, ammo) {
         this.weapons[weaponId] = true
+        this.giveAmmo(weaponId, ammo)
+    }
+
+    giveAmmo(weaponId, ammo) {
         if (this.ammo[weaponId] !== -1) {
             this.ammo[weaponId] += ammo
         }
@@ -144,8 +183,16 @@ export class Player {
         this.health = Math.min(this.health + amount, max)
     }
 
-    giveArmor(amount) {
-        this.armor = Math.min(this.armor + amount, PhysicsConstants.MAX_ARMOR)
+    // Mirrors PlayerState::give_armor; returns false when the pickup is refused
+    giveArmor(type, amount) {
+        const armor = Math.min(this.armor + amount, PhysicsConstants.ARMOR_CAP[type])
+        const protection = armor * PhysicsConstants.ARMOR_ABSORPTION[type]
+        if (protection <= this.armor * PhysicsConstants.ARMOR_ABSORPTION[this.armorType]) {
+            return false
+        }
+        this.armor = armor
+        this.armorType = type
+        return true
     }
 
     takeDamage(damage, attackerId) {
@@ -156,11 +203,12 @@ export class Player {
 
         if (this.armor > 0) {
             const armorDamage = Math.min(
-                Math.floor(actual * PhysicsConstants.ARMOR_ABSORPTION),
+                Math.floor(actual * PhysicsConstants.ARMOR_ABSORPTION[this.armorType]),
                 this.armor,
             )
             this.armor -= armorDamage
             actual -= armorDamage
+            if (this.armor <= 0) this.armorType = ArmorType.NONE
         }
 
         const rounded = Math.floor(actual)
@@ -193,14 +241,22 @@ export class Player {
 
         this.health = PhysicsConstants.MAX_HEALTH
         this.armor = 0
+        this.armorType = ArmorType.NONE
         this.dead = false
         this.velocityX = 0
         this.velocityY = 0
         this.weapons = Array(PhysicsConstants.WEAPON_COUNT).fill(true)
         this.ammo = createAmmoArray()
         this.currentWeapon = WeaponId.ROCKET
+        this.pendingWeapon = WeaponId.ROCKET
+        this.weaponState = WeaponState.READY
+        this.weaponTimer = 0
         this.quadDamage = false
         this.quadTimer = 0
+        this.regenTimer = 0
+        this.hasteTimer = 0
+        this.battleSuitTimer = 0
+        this.invisibilityTimer = 0
         this.spawnProtection = PhysicsConstants.SPAWN_PROTECTION // ~2 seconds of spawn protection
     }
 
@@ -2,12 +2,16 @@ import { ensureModelLoaded } from '../render/assets'
 import { Player } from '../game/player'
 import { pickMultiplayerSkin } from '../core/models'
 import {
+    applyRoomRuleset,
     decodeServerMessage,
+    encodeDemoControl,
     encodeHello,
     encodeInput,
     encodeJoinRoom,
     encodePing,
+    encodeWatchDemo,
     initProtocolWasm,
+    roomTickMillis,
 } from './protocol'
 import { getBackendWsUrl } from './wsEndpoint'
 
@@ -21,6 +25,7 @@ const SNAPSHOT_INTERVAL_MS = 1000 / SNAPSHOT_SEND_RATE_HZ
 const SNAPSHOT_BUFFER_MAX = 90
 const SNAPSHOT_INTERVAL_MIN_MS = 12
 const SNAPSHOT_INTERVAL_MAX_MS = 80
+const POWERUP_FLAG_HOLD_TICKS = 8
 const PING_INTERVAL_MS = 1000
 const DEFAULT_CLOCK_OFFSET_MS = 0
 const DEFAULT_RTT_MS = 80
@@ -32,6 +37,8 @@ const REMOTE_FACING_CONFIRM_FRAMES = 3
 const INPUT_MIN_SEND_HZ = 30
 const INPUT_MAX_SEND_HZ = 90
 const TELEPORT_THRESHOLD_UNITS = 40
+const RECONNECT_ATTEMPTS = 5
+const RECONNECT_BASE_DELAY_MS = 500
 const DEFAULT_TUNING = Object.freeze({
     interpBaseSnapshots: 2.25,
     interpRttFactor: 0.25,
@@ -92,6 +99,7 @@ const AUTO_TUNE_INTERVAL_MS = 1000
 export class NetworkClient {
     constructor() {
         this.signalSocket = null
+        this.gameSocket = null
         this.peerConnection = null
         this.controlDataChannel = null
         this.gameDataChannel = null
@@ -99,6 +107,10 @@ export class NetworkClient {
         this.roomId = null
         this.inputSeq = 0
         this.connected = false
+        this.spectator = false
+        this.connectOptions = null
+        this.resumeToken = null
+        this.reconnectTimer = null
         this.handlers = {}
         this.remotePlayers = new Map()
         this._remotePlayerCache = []
@@ -124,6 +136,7 @@ export class NetworkClient {
         this.lastAckedInputSeq = 0
         this.lastAckProgressAtMs = 0
         this.lastSentInputSignature = ''
+        this.resetLatchedInput()
         this.currentInputSendHz = INPUT_SEND_RATE_HZ
         this.serverTickMillis = DEFAULT_SERVER_TICK_MILLIS
         this.interpDelayMs = MIN_INTERP_DELAY_MS
@@ -155,10 +168,15 @@ export class NetworkClient {
         return this.connected
     }
 
-    setServerTickRateHz(tickRateHz) {
-        const hz = Number(tickRateHz)
-        if (!Number.isFinite(hz) || hz <= 0) return
-        this.serverTickMillis = 1000 / hz
+    // Spectators join without a body: no input goes out and the local player stays hidden.
+    isSpectator() {
+        return this.connected && this.spectator
+    }
+
+    setServerTickMillis(tickMillis) {
+        const ms = Number(tickMillis)
+        if (!Number.isFinite(ms) || ms <= 0) return
+        this.serverTickMillis = ms
     }
 
     getRemotePlayers() {
@@ -303,30 +321,95 @@ export class NetworkClient {
         return true
     }
 
-    connect({ url = DEFAULT_SERVER_URL, username, roomId, map = DEFAULT_MAP, ticket = null } = {}) {
+    connect(options = {}) {
+        const {
+            url = DEFAULT_SERVER_URL,
+            username,
+            roomId,
+            map = DEFAULT_MAP,
+            ruleset = null,
+            ticket = null,
+            demo = null,
+            spectator = false,
+            transport = 'auto',
+        } = options
         if (this.connected) return Promise.resolve()
         if (!username) return Promise.reject(new Error('Username required'))
+        this.connectOptions = options
 
         return initProtocolWasm().then(async () => {
             this.resetConnectionState()
+            this.spectator = spectator || !!demo
             if (this.localPlayer) {
                 const skin = pickMultiplayerSkin(username)
                 await ensureModelLoaded(this.localPlayer.model, skin)
                 this.localPlayer.skin = skin
             }
-            await this.connectWebRtc({ url, username, roomId, map, ticket })
+            const session = { url, username, roomId, map, ruleset, ticket, demo, spectator }
+            if (transport !== 'ws') {
+                try {
+                    await this.connectWebRtc(session)
+                    return
+                } catch (err) {
+                    if (transport === 'rtc') throw err
+                    this.handleTransportClosed()
+                    this.handlers.onTransportFallback?.(err)
+                }
+            }
+            await this.connectWebSocket(session)
         })
     }
 
     disconnect() {
+        this.connectOptions = null
+        this.resumeToken = null
+        clearTimeout(this.reconnectTimer)
+        this.reconnectTimer = null
         this.handleTransportClosed()
     }
 
+    // The server holds our slot for a grace period after the connection drops;
+    // reconnecting with the resume token from the welcome gets the same player back.
+    scheduleReconnect(attempt) {
+        const options = this.connectOptions
+        if (!options) return
+        if (attempt >= RECONNECT_ATTEMPTS) {
+            this.connectOptions = null
+            this.resumeToken = null
+            this.handlers.onReconnectFailed?.()
+            return
+        }
+        this.reconnectTimer = setTimeout(() => {
+            this.reconnectTimer = null
+            if (this.connected || this.connectOptions !== options) return
+            this.handlers.onReconnecting?.(attempt + 1)
+            this.connect(options).catch(() => {
+                if (this.connectOptions === options) this.scheduleReconnect(attempt + 1)
+            })
+        }, RECONNECT_BASE_DELAY_MS * 2 ** attempt)
+    }
+
+    // Fire presses and weapon switches are latched until the next packet goes out.
+    // Every packet repeats the latest edge seqs so the server applies each once.
+    resetLatchedInput() {
+        this.firePending = false
+        this.switchPending = -1
+        this.fireSeq = 0
+        this.switchSeq = 0
+        this.switchWeapon = -1
+    }
+
     sendInput(input, now = performance.now()) {
+        if (this.spectator) return
         if (!this.connected) return false
-        if (!this.gameDataChannel) return false
+        if (!this.gameDataChannel && !this.gameSocket) return false
+        if (input?.fire_pressed) this.firePending = true
+        if ((input?.weapon_switch ?? -1) >= 0) this.switchPending = input.weapon_switch
         const signature = buildInputSignature(input)
-        const inputChanged = signature !== this.lastSentInputSignature
+        const inputChanged =
+            signature !== this.lastSentInputSignature ||
+            this.firePending ||
+            this.switchPending >= 0
 
         this.updateInputSendInterval(input, now)
         const ackStalled = this.isAckProgressStalled(now)
@@ -336,6 +419,15 @@ export class NetworkClient {
 
         this.lastInputSentAt = now
         this.inputSeq++
+        if (this.firePending) {
+            this.fireSeq = this.inputSeq
+            this.firePending = false
+        }
+        if (this.switchPending >= 0) {
+            this.switchSeq = this.inputSeq
+            this.switchWeapon = this.switchPending
+            this.switchPending = -1
+        }
         this.pendingInputs.push({
             seq: this.inputSeq,
             input,
@@ -344,21 +436,93 @@ export class NetworkClient {
         if (this.pendingInputs.length > PENDING_INPUT_MAX) {
             this.pendingInputs.splice(0, this.pendingInputs.length - PENDING_INPUT_MAX)
         }
-        this.sendGame(encodeInput(this.inputSeq, input))
+        this.sendGame(
+            encodeInput(this.inputSeq, {
+                ...input,
+                fire_seq: this.fireSeq,
+                switch_seq: this.switchSeq,
+                switch_weapon: this.switchWeapon,
+            }),
+        )
         return true
     }
 
+    sendDemoControl(action, tick, speed) {
+        if (!this.connected) return
+        this.sendControl(encodeDemoControl(action, tick, speed))
+    }
+
     sendControl(payload) {
+        if (this.gameSocket) {
+            this.sendSocket(payload)
+            return
+        }
         if (!this.controlDataChannel || this.controlDataChannel.readyState !== 'open') return
         this.controlDataChannel.send(payload)
     }
 
     sendGame(payload) {
+        if (this.gameSocket) {
+            this.sendSocket(payload)
+            return
+        }
         if (!this.gameDataChannel || this.gameDataChannel.readyState !== 'open') return
         this.gameDataChannel.send(payload)
     }
 
-    async connectWebRtc({ url, username, roomId, map, ticket }) {
+    sendSocket(payload) {
+        if (this.gameSocket.readyState !== WebSocket.OPEN) return
+        this.gameSocket.send(payload)
+    }
+
+    // Fallback for networks where ICE can't connect: the same binary messages go
+    // over a plain WebSocket, at the cost of head-of-line blocking on snapshots.
+    async connectWebSocket({ url, username, roomId, map, ruleset, ticket, demo, spectator }) {
+        const params = new URLSearchParams()
+        if (ticket) params.set('ticket', ticket)
+        if (this.resumeToken) params.set('resume', this.resumeToken)
+        const query = params.toString()
+        const socketUrl = toGameSocketUrl(url)
+        const socket = new WebSocket(query ? `${socketUrl}?${query}` : socketUrl)
+        socket.binaryType = 'arraybuffer'
+        this.gameSocket = socket
+
+        socket.addEventListener('message', (event) => {
+            if (!(event.data instanceof ArrayBuffer)) return
+            const msg = decodeServerMessage(event.data)
+            if (msg) this.handleMessage(msg)
+        })
+        socket.addEventListener('close', () => {
+            if (this.gameSocket === socket) this.handleTransportClosed()
+        })
+
+        try {
+            await waitForWebSocketOpen(socket)
+        } catch (err) {
+            this.handleTransportClosed()
+            throw err
+        }
+        this.startSession({ username, roomId, map, ruleset, demo, spectator })
+    }
+
+    startSession({ username, roomId, map, ruleset, demo, spectator }) {
+        this.connected = true
+        this.lastInputSentAt = -Infinity
+        this.lastPingSentAt = -Infinity
+        this.lastAckProgressAtMs = performance.now()
+        this.lastAckedInputSeq = 0
+        this.lastSentInputSignature = ''
+        this.currentInputSendHz = INPUT_SEND_RATE_HZ
+        this.sendControl(encodeHello(username))
+        if (demo) {
+            this.sendControl(encodeWatchDemo(demo))
+        } else {
+            this.sendControl(encodeJoinRoom(roomId ?? '', map, ruleset, spectator))
+        }
+        this.handlers.onOpen?.()
+    }
+
+    async connectWebRtc({ url, username, roomId, map, ruleset, ticket, demo, spectator }) {
         try {
             if (typeof RTCPeerConnection === 'undefined') {
                 throw new Error('WebRTC not supported by this browser')
@@ -419,6 +583,7 @@ export class NetworkClient {
                 JSON.stringify({
                     type: 'offer',
                     sdp: this.peerConnection.localDescription?.sdp ?? offer.sdp,
+                    resume: this.resumeToken ?? undefined,
                 }),
             )
 
@@ -432,16 +597,7 @@ export class NetworkClient {
             this.signalSocket = null
 
             await channelsOpenPromise
-            this.connected = true
-            this.lastInputSentAt = -Infinity
-            this.lastPingSentAt = -Infinity
-            this.lastAckProgressAtMs = performance.now()
-            this.lastAckedInputSeq = 0
-            this.lastSentInputSignature = ''
-            this.currentInputSendHz = INPUT_SEND_RATE_HZ
-            this.sendControl(encodeHello(username))
-            this.sendControl(encodeJoinRoom(roomId ?? '', map))
-            this.handlers.onOpen?.()
+            this.startSession({ username, roomId, map, ruleset, demo, spectator })
         } catch (err) {
             if (this.signalSocket) {
                 try {
@@ -457,6 +613,7 @@ export class NetworkClient {
         this.connected = false
         this.playerId = null
         this.roomId = null
+        applyRoomRuleset(null)
         this.lastInputSentAt = -Infinity
         this.lastPingSentAt = -Infinity
         this.clockOffsetMs = DEFAULT_CLOCK_OFFSET_MS
@@ -478,6 +635,7 @@ export class NetworkClient {
         this.lastAckedInputSeq = 0
         this.lastAckProgressAtMs = 0
         this.lastSentInputSignature = ''
+        this.resetLatchedInput()
         this.currentInputSendHz = INPUT_SEND_RATE_HZ
         this.remotePlayers.clear()
         this._remotePlayerCache = []
@@ -488,6 +646,13 @@ export class NetworkClient {
 
     handleTransportClosed() {
         const wasConnected = this.connected
+        if (this.gameSocket) {
+            const socket = this.gameSocket
+            this.gameSocket = null
+            try {
+                socket.close()
+            } catch {}
+        }
         if (this.controlDataChannel) {
             try {
                 this.controlDataChannel.close()
@@ -509,6 +674,7 @@ export class NetworkClient {
         this.resetConnectionState()
         if (wasConnected) {
             this.handlers.onClose?.()
+            this.scheduleReconnect(0)
         }
     }
 
@@ -516,6 +682,7 @@ export class NetworkClient {
         switch (msg.type) {
             case 'welcome':
                 this.playerId = msg.player_id
+                if (msg.resume_token) this.resumeToken = msg.resume_token
                 if (this.localPlayer) {
                     this.localPlayer.id = msg.player_id
                 }
@@ -523,7 +690,8 @@ export class NetworkClient {
                 break
             case 'room_state':
                 this.roomId = msg.room_id
-                this.setServerTickRateHz(msg.tick_rate)
+                this.setServerTickMillis(roomTickMillis(msg))
+                applyRoomRuleset(msg.ruleset)
                 this.hydrateRoom(msg)
                 this.handlers.onRoomState?.(msg)
                 break
@@ -543,6 +711,9 @@ export class NetworkClient {
             case 'pong':
                 this.handlePong(msg)
                 break
+            case 'demo_state':
+                this.handlers.onDemoState?.(msg)
+                break
             default:
                 break
         }
@@ -993,6 +1164,7 @@ function applyPlayerState(player, state, isRemote) {
     player.dead = state.dead ?? player.dead
     player.health = state.health ?? player.health
     player.armor = state.armor ?? player.armor
+    player.armorType = state.armor_type ?? player.armorType
     player.currentWeapon = state.current_weapon ?? player.currentWeapon
     player.fireCooldown = state.fire_cooldown ?? player.fireCooldown
     if (Array.isArray(state.weapons)) {
@@ -1001,6 +1173,8 @@ function applyPlayerState(player, state, isRemote) {
     if (Array.isArray(state.ammo)) {
         player.ammo = state.ammo
     }
+    applyPowerupFlags(player, state)
+    applyWeaponState(player, state)
 }
 
 function applyInterpolatedState(player, a, b, t) {
@@ -1018,9 +1192,12 @@ function applyInterpolatedState(player, a, b, t) {
     player.dead = b.dead ?? player.dead
     player.health = b.health ?? player.health
     player.armor = b.armor ?? player.armor
+    player.armorType = b.armor_type ?? player.armorType
     player.currentWeapon = b.current_weapon ?? player.currentWeapon
     if (Array.isArray(b.weapons)) player.weapons = b.weapons
     if (Array.isArray(b.ammo)) player.ammo = b.ammo
+    applyPowerupFlags(player, b)
+    applyWeaponState(player, b)
 }
 
 function applyExtrapolatedState(player, state, extrapolationMs, serverTickMillis) {
@@ -1040,9 +1217,34 @@ function applyExtrapolatedState(player, state, extrapolationMs, serverTickMillis
     player.dead = state.dead ?? player.dead
     player.health = state.health ?? player.health
     player.armor = state.armor ?? player.armor
+    player.armorType = state.armor_type ?? player.armorType
     player.currentWeapon = state.current_weapon ?? player.currentWeapon
     if (Array.isArray(state.weapons)) player.weapons = state.weapons
     if (Array.isArray(state.ammo)) player.ammo = state.ammo
+    applyPowerupFlags(player, state)
+    applyWeaponState(player, state)
+}
+
+// The server owns the switch cycle; a locally queued switch stays pending.
+function applyWeaponState(player, state) {
+    if (!Number.isInteger(state.weapon_state)) return
+    player.weaponState = state.weapon_state
+    player.weaponTimer = state.weapon_timer ?? 0
+    if (!player.weapons[player.pendingWeapon]) {
+        player.pendingWeapon = player.currentWeapon
+    }
+}
+
+// Snapshots only carry powerup flags. Hold active ones for a few ticks so the
+// local per-tick countdown does not switch them off between snapshots.
+function applyPowerupFlags(player, state) {
+    if (typeof state.quad !== 'boolean') return
+    player.quadDamage = state.quad
+    player.quadTimer = state.quad ? POWERUP_FLAG_HOLD_TICKS : 0
+    player.regenTimer = state.regeneration ? POWERUP_FLAG_HOLD_TICKS : 0
+    player.hasteTimer = state.haste ? POWERUP_FLAG_HOLD_TICKS : 0
+    player.battleSuitTimer = state.battle_suit ? POWERUP_FLAG_HOLD_TICKS : 0
+    player.invisibilityTimer = state.invisibility ? POWERUP_FLAG_HOLD_TICKS : 0
 }
 
 // Binary search: returns the first index i where buffer[i].tick >= tick.
@@ -1152,17 +1354,25 @@ function buildIceServers() {
 }
 
 function toRtcSignalingUrl(baseUrl) {
+    return withServerPath(baseUrl, '/rtc')
+}
+
+function toGameSocketUrl(baseUrl) {
+    return withServerPath(baseUrl, '/ws')
+}
+
+function withServerPath(baseUrl, path) {
     try {
         const url = new URL(baseUrl, window.location.href)
-        url.pathname = '/rtc'
+        url.pathname = path
         url.search = ''
         url.hash = ''
         return url.toString()
     } catch {
         if (baseUrl.endsWith('/ws')) {
-            return `${baseUrl.slice(0, -3)}/rtc`
+            return `${baseUrl.slice(0, -3)}${path}`
         }
-        return `${baseUrl}/rtc`
+        return `${baseUrl}${path}`
     }
 }
 
@@ -1172,7 +1382,7 @@ function waitForWebSocketOpen(socket) {
     }
     return new Promise((resolve, reject) => {
         const onOpen = () => resolve()
-        const onError = () => reject(new Error('RTC signaling socket error'))
+        const onError = () => reject(new Error('WebSocket connection error'))
         socket.addEventListener('open', onOpen, { once: true })
         socket.addEventListener('error', onError, { once: true })
     })
@@ -1,16 +1,27 @@
 import { getWasmModuleSync, initWasm } from '../wasm/client'
+import { reloadPhysicsConstants } from '../game/physics'
 
 export const MSG = {
     HELLO: 0x01,
     JOIN_ROOM: 0x02,
     INPUT: 0x03,
     PING: 0x04,
+    WATCH_DEMO: 0x05,
+    DEMO_CONTROL: 0x06,
     WELCOME: 0x81,
     ROOM_STATE: 0x82,
     PLAYER_JOINED: 0x83,
     PLAYER_LEFT: 0x84,
     SNAPSHOT: 0x85,
     PONG: 0x86,
+    DEMO_STATE: 0x8a,
+}
+
+export const DEMO_CONTROL = {
+    PAUSE: 0,
+    RESUME: 1,
+    SEEK: 2,
+    SPEED: 3,
 }
 
 export async function initProtocolWasm() {
@@ -29,8 +40,36 @@ export function encodeHello(username) {
     return getProtocolModule().wasm_encode_hello(username ?? '')
 }
 
-export function encodeJoinRoom(roomId, map) {
-    return getProtocolModule().wasm_encode_join_room(roomId ?? '', map ?? '')
+export function encodeJoinRoom(roomId, map, ruleset, spectator = false) {
+    return getProtocolModule().wasm_encode_join_room(
+        roomId ?? '',
+        map ?? '',
+        ruleset ?? '',
+        spectator,
+    )
+}
+
+export function encodeWatchDemo(name) {
+    return getProtocolModule().wasm_encode_watch_demo(name ?? '')
+}
+
+export function encodeDemoControl(action, tick = 0, speed = 1) {
+    return getProtocolModule().wasm_encode_demo_control(action, BigInt(tick), speed)
+}
+
+export function applyRoomRuleset(ruleset) {
+    const module = getProtocolModule()
+    if (!ruleset?.length || !module.wasm_set_ruleset(ruleset)) {
+        module.wasm_reset_ruleset()
+    }
+    reloadPhysicsConstants()
+}
+
+// Servers that predate `tick_millis` only send the tick rate in Hz.
+export function roomTickMillis(room) {
+    const ms = Number(room?.tick_millis)
+    if (Number.isFinite(ms) && ms > 0) return ms
+    return 1000 / Number(room?.tick_rate)
 }
 
 export function encodeInput(seq, input) {
@@ -45,6 +84,9 @@ export function encodeInput(seq, input) {
         input.facing_left ?? false,
         input.weapon_switch ?? -1,
         input.weapon_scroll ?? 0,
+        BigInt(input.fire_seq ?? 0),
+        BigInt(input.switch_seq ?? 0),
+        input.switch_weapon ?? -1,
     )
 }
 
@@ -49,6 +49,8 @@ export function recalcCamera() {
     hudRef.ammo.y = innerHeight - 80
     hudRef.netDebug.x = innerWidth - 20
     hudRef.netDebug.y = 20
+    hudRef.announce.x = innerWidth / 2
+    hudRef.announce.y = 100
 }
 
 export function updateCamera(player) {
@@ -26,8 +26,13 @@ export const GRENADE_SMOKE_INTERVAL = 6
 export const SMOKE_MAX_AGE = 32
 
 export const WEAPON_ITEM_MAP = {
+    weapon_gauntlet: WeaponId.GAUNTLET,
     weapon_machine: WeaponId.MACHINE,
     weapon_shotgun: WeaponId.SHOTGUN,
     weapon_grenade: WeaponId.GRENADE,
     weapon_rocket: WeaponId.ROCKET,
+    weapon_rail: WeaponId.RAIL,
+    weapon_plasma: WeaponId.PLASMA,
+    weapon_shaft: WeaponId.SHAFT,
+    weapon_bfg: WeaponId.BFG,
 }
@@ -8,6 +8,11 @@ const HEALTH_THRESHOLDS = [
     { min: 0, color: 0xff0000 },
 ]
 
+// Indexed by armor type: none, green, yellow, red
+const ARMOR_COLORS = [0xffffff, 0x00ff00, 0xffff00, 0xff0000]
+
+const ANNOUNCE_DURATION_MS = 2000
+
 const STYLES = {
     health: {
         fontFamily: 'Arial',
@@ -36,6 +41,13 @@ const STYLES = {
         fill: 0xffffff,
         stroke: { color: 0x000000, width: 2 },
     },
+    announce: {
+        fontFamily: 'Arial',
+        fontSize: 36,
+        fontWeight: 'bold',
+        fill: 0xffcc33,
+        stroke: { color: 0x000000, width: 4 },
+    },
     netDebug: {
         fontFamily: 'monospace',
         fontSize: 12,
@@ -53,9 +65,16 @@ export function createHUD() {
     const ammo = createText('100', STYLES.ammo, { anchorX: 1 })
     const netDebug = createText('', STYLES.netDebug, { anchorX: 1 })
     netDebug.visible = false
+    const announce = createText('', STYLES.announce, { anchorX: 0.5 })
+    announce.visible = false
+
+    container.addChild(health, armor, weapon, ammo, netDebug, announce)
+    return { container, health, armor, weapon, ammo, netDebug, announce, announceUntil: 0 }
+}
 
-    container.addChild(health, armor, weapon, ammo, netDebug)
-    return { container, health, armor, weapon, ammo, netDebug }
+export function showAnnouncement(hud, text) {
+    hud.announce.text = text
+    hud.announceUntil = performance.now() + ANNOUNCE_DURATION_MS
 }
 
 export function updateHUD(player, hud) {
@@ -65,11 +84,14 @@ export function updateHUD(player, hud) {
 
     hud.armor.text = player.armor.toString()
     hud.armor.visible = player.armor > 0
+    hud.armor.style.fill = ARMOR_COLORS[player.armorType] ?? ARMOR_COLORS[0]
 
     hud.weapon.text = WeaponConstants.NAMES[player.currentWeapon]
 
     const ammo = player.ammo[player.currentWeapon]
     hud.ammo.text = ammo === -1 ? '∞' : ammo.toString()
+
+    hud.announce.visible = performance.now() < hud.announceUntil
 }
 
 function createText(text, style, { x = 0, y = 0, anchorX = 0, anchorY = 0 } = {}) {
@@ -2,9 +2,10 @@ import * as PIXI from 'pixi.js'
 import { getTexture } from './assets'
 import { app, renderer, stage, world } from './app'
 import { BG_TILE_SCALE } from './constants'
-import { createHUD, updateHUD } from './hud'
+import { createHUD, showAnnouncement, updateHUD } from './hud'
 import {
     cleanupBotSprite,
+    hidePlayerSprite,
     initPlayerSprites,
     setPlayerColor,
     updateBotSprites,
@@ -40,6 +41,7 @@ export const Render = {
     initSprites,
     setSceneReady,
     renderGame,
+    renderSpectator,
     setNetDebugOverlay,
     renderMap,
     setPlayerColor,
@@ -49,6 +51,7 @@ export const Render = {
     addBulletImpact,
     addGauntletSpark,
     addExplosion,
+    announce,
 }
 
 function initSprites(player) {
@@ -63,6 +66,7 @@ function setSceneReady(visible) {
 }
 
 function renderGame(player, bots = []) {
+    hud.container.visible = stage.visible
     updateCamera(player)
     renderPlayers(player, bots)
     renderEffects(player)
@@ -70,6 +74,22 @@ function renderGame(player, bots = []) {
     app.render()
 }
 
+// `target` is what the camera centers on; `followed` is the player shown in the HUD,
+// or null for a free camera.
+function renderSpectator(target, followed, bots = []) {
+    updateCamera(target)
+    hidePlayerSprite()
+    updateBotSprites(bots)
+    renderEffects(followed)
+    hud.container.visible = stage.visible && !!followed
+    if (followed) updateHUD(followed, hud)
+    app.render()
+}
+
+function announce(text) {
+    showAnnouncement(hud, text)
+}
+
 function setNetDebugOverlay(text, visible) {
     hud.netDebug.visible = !!visible
     hud.netDebug.text = text ?? ''
@@ -8,11 +8,13 @@ import { PhysicsConstants } from '../game/physics'
 const { isBrick } = Map
 
 const itemSprites = []
+const droppedSprites = new globalThis.Map()
 
 export function renderMap() {
     tiles.removeChildren()
     items.removeChildren()
     itemSprites.length = 0
+    droppedSprites.clear()
 
     const rows = Map.getRows()
     const cols = Map.getCols()
@@ -31,15 +33,9 @@ export function renderMap() {
     }
 
     for (const item of Map.getItems()) {
-        const tex = item.type.startsWith('weapon_')
-            ? getWeaponIcon(WEAPON_ITEM_MAP[item.type])
-            : getItemIcon(item.type)
-        if (!tex) continue
-
-        const sprite = new PIXI.Sprite(tex)
-        const scale = (PhysicsConstants.TILE_H * 1.2) / Math.max(tex.width, tex.height)
-        sprite.anchor.set(0.5)
-        sprite.scale.set(scale)
+        const sprite = createItemSprite(item.type)
+        if (!sprite) continue
+
         sprite.x = item.col * PhysicsConstants.TILE_W + PhysicsConstants.TILE_W / 2
         sprite.y = item.row * PhysicsConstants.TILE_H + PhysicsConstants.TILE_H / 2
         sprite.visible = item.active
@@ -55,4 +51,40 @@ export function updateItemSprites() {
     for (const { item, sprite } of itemSprites) {
         sprite.visible = item.active
     }
+    updateDroppedSprites()
+}
+
+function updateDroppedSprites() {
+    const dropped = Map.getDroppedItems()
+    const liveIds = new Set()
+    for (const item of dropped) {
+        liveIds.add(item.id)
+        let sprite = droppedSprites.get(item.id)
+        if (!sprite) {
+            sprite = createItemSprite(item.type)
+            if (!sprite) continue
+            droppedSprites.set(item.id, sprite)
+            items.addChild(sprite)
+        }
+        sprite.x = item.x
+        sprite.y = item.y
+    }
+    for (const [id, sprite] of droppedSprites) {
+        if (liveIds.has(id)) continue
+        sprite.destroy()
+        droppedSprites.delete(id)
+    }
+}
+
+function createItemSprite(type) {
+    const tex = type.startsWith('weapon_')
+        ? getWeaponIcon(WEAPON_ITEM_MAP[type])
+        : getItemIcon(type)
+    if (!tex) return null
+
+    const sprite = new PIXI.Sprite(tex)
+    const scale = (PhysicsConstants.TILE_H * 1.2) / Math.max(tex.width, tex.height)
+    sprite.anchor.set(0.5)
+    sprite.scale.set(scale)
+    return sprite
 }
@@ -1,6 +1,8 @@
 import * as PIXI from 'pixi.js'
 import { getModelAnimationFrames, getWeaponIcon } from './assets'
+import { WeaponState } from '../core/helpers'
 import { DEFAULT_MODEL, DEFAULT_SKIN } from '../core/models'
+import { PhysicsConstants } from '../game/physics'
 import { getRenderPosition } from './camera'
 import { world } from './app'
 import { ANIMATION, PLAYER_SCALE_X, PLAYER_SCALE_Y, WEAPON_SCALE } from './constants'
@@ -8,6 +10,7 @@ import { ANIMATION, PLAYER_SCALE_X, PLAYER_SCALE_Y, WEAPON_SCALE } from './const
 const CROUCH_SCALE_FACTOR = 0.83
 const CROUCH_Y_OFFSET = 8
 const CROUCH_WEAPON_Y_OFFSET = 4
+const WEAPON_SWAP_DROP = 10
 
 let localPlayer = { sprite: null, center: null, weapon: null, anim: 'walk', frame: 0, timer: 0 }
 const botSprites = new Map()
@@ -47,6 +50,12 @@ export function updatePlayerSprite(player) {
     }
 }
 
+export function hidePlayerSprite() {
+    if (localPlayer.sprite) localPlayer.sprite.visible = false
+    if (localPlayer.center) localPlayer.center.visible = false
+    if (localPlayer.weapon) localPlayer.weapon.visible = false
+}
+
 export function updateWeaponSprite(player) {
     if (!localPlayer.weapon) return
 
@@ -202,11 +211,26 @@ function positionWeapon(weapon, player, x, y, aimAngle) {
         return
     }
 
+    const drop = weaponDrop(player)
     weapon.texture = icon
     weapon.x = x
-    weapon.y = y
-    weapon.rotation = aimAngle
+    weapon.y = y + drop * WEAPON_SWAP_DROP
+    weapon.rotation = aimAngle + drop * (player.facingLeft ? -1 : 1)
     weapon.scale.x = WEAPON_SCALE
     weapon.scale.y = (player.facingLeft ? -1 : 1) * WEAPON_SCALE
     weapon.visible = true
 }
+
+// 0 while the weapon is up, 1 when fully lowered during a switch
+function weaponDrop(player) {
+    const { weaponState, weaponTimer, currentWeapon } = player
+    if (weaponState === WeaponState.LOWERING) {
+        const total = PhysicsConstants.getWeaponLowerTime(currentWeapon)
+        return total > 0 ? 1 - weaponTimer / total : 1
+    }
+    if (weaponState === WeaponState.RAISING) {
+        const total = PhysicsConstants.getWeaponRaiseTime(currentWeapon)
+        return total > 0 ? weaponTimer / total : 0
+    }
+    return 0
+}
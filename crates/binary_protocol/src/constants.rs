@@ -3,6 +3,8 @@ pub const MSG_HELLO: u8 = 0x01;
 pub const MSG_JOIN_ROOM: u8 = 0x02;
 pub const MSG_INPUT: u8 = 0x03;
 pub const MSG_PING: u8 = 0x04;
+pub const MSG_WATCH_DEMO: u8 = 0x05;
+pub const MSG_DEMO_CONTROL: u8 = 0x06;
 pub const MSG_WELCOME: u8 = 0x81;
 pub const MSG_ROOM_STATE: u8 = 0x82;
 pub const MSG_PLAYER_JOINED: u8 = 0x83;
@@ -12,6 +14,13 @@ pub const MSG_PONG: u8 = 0x86;
 pub const MSG_JOIN_REJECTED: u8 = 0x87;
 pub const MSG_ROOM_CLOSED: u8 = 0x88;
 pub const MSG_KICKED: u8 = 0x89;
+pub const MSG_DEMO_STATE: u8 = 0x8A;
+
+// Demo control actions
+pub const DEMO_PAUSE: u8 = 0x00;
+pub const DEMO_RESUME: u8 = 0x01;
+pub const DEMO_SEEK: u8 = 0x02;
+pub const DEMO_SPEED: u8 = 0x03;
 
 // Event type constants
 pub const EVENT_WEAPON_FIRED: u8 = 0x01;
@@ -23,10 +32,33 @@ pub const EVENT_GAUNTLET: u8 = 0x06;
 pub const EVENT_EXPLOSION: u8 = 0x07;
 pub const EVENT_DAMAGE: u8 = 0x08;
 pub const EVENT_PROJECTILE_REMOVE: u8 = 0x09;
+pub const EVENT_MULTI_KILL: u8 = 0x0A;
+pub const EVENT_ITEM_PICKUP: u8 = 0x0B;
+pub const EVENT_ITEM_RESPAWN: u8 = 0x0C;
+pub const EVENT_QUAD_START: u8 = 0x0D;
+pub const EVENT_QUAD_EXPIRE: u8 = 0x0E;
+pub const EVENT_PLAYER_RESPAWN: u8 = 0x0F;
+/// Item index sent for pickups of dropped items, which have no map slot.
+pub const NO_ITEM_INDEX: u16 = u16::MAX;
+
+// Join roles, sent after the ruleset name in JoinRoom
+pub const JOIN_ROLE_PLAYER: u8 = 0;
+pub const JOIN_ROLE_SPECTATOR: u8 = 1;
 
 // Protocol limits
 pub const MAX_USERNAME_LEN: usize = 32;
 pub const WEAPON_COUNT: usize = 9;
+pub const PLAYER_RECORD_LEN: usize = 67;
+/// Snapshot header: type, tick, server time, then player, item, projectile,
+/// event and dropped item counts.
+pub const SNAPSHOT_HEADER_LEN: usize = 23;
+pub const DROPPED_ITEM_RECORD_LEN: usize = 17;
+/// Input packet length including the latched fire / weapon switch edges.
+pub const INPUT_LATCHED_LEN: usize = 33;
+/// Type, action, then a u64 tick or f32 speed padded to 8 bytes.
+pub const DEMO_CONTROL_LEN: usize = 10;
+/// Type, tick, end tick, paused flag, speed.
+pub const DEMO_STATE_LEN: usize = 22;
 
 // Projectile kind constants for JS interop
 pub const PROJ_ROCKET: u8 = 0;
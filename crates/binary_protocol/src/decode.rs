@@ -1,5 +1,5 @@
 use crate::constants::*;
-use crate::types::{ClientMsg, DecodeError};
+use crate::types::{ClientMsg, DecodeError, DemoControl, JoinRole};
 
 pub fn decode_client_message(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
     let first = *bytes.first().ok_or(DecodeError::Empty)?;
@@ -8,6 +8,8 @@ pub fn decode_client_message(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
         MSG_JOIN_ROOM => decode_join_room(bytes),
         MSG_INPUT => decode_input(bytes),
         MSG_PING => decode_ping(bytes),
+        MSG_WATCH_DEMO => decode_watch_demo(bytes),
+        MSG_DEMO_CONTROL => decode_demo_control(bytes),
         _ => Err(DecodeError::UnknownType(first)),
     }
 }
@@ -42,11 +44,29 @@ fn decode_join_room(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
         None
     };
     let map = if map_len > 0 {
-        Some(read_string(bytes, offset, map_len)?)
+        let map = read_string(bytes, offset, map_len)?;
+        offset += map_len;
+        Some(map)
     } else {
         None
     };
-    Ok(ClientMsg::JoinRoom { room_id, map })
+    // Older clients stop after the map name.
+    let ruleset_len = bytes.get(offset).copied().unwrap_or(0) as usize;
+    let ruleset = if ruleset_len > 0 {
+        Some(read_string(bytes, offset + 1, ruleset_len)?)
+    } else {
+        None
+    };
+    let role = match bytes.get(offset + 1 + ruleset_len).copied() {
+        Some(JOIN_ROLE_SPECTATOR) => JoinRole::Spectator,
+        _ => JoinRole::Player,
+    };
+    Ok(ClientMsg::JoinRoom {
+        room_id,
+        map,
+        ruleset,
+        role,
+    })
 }
 
 fn decode_input(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
@@ -58,6 +78,17 @@ fn decode_input(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
     let flags = bytes[13];
     let weapon_switch = bytes[14] as i8 as i32;
     let weapon_scroll = bytes[15] as i8 as i32;
+    // Latched edges trail the original 16-byte packet; older clients omit them.
+    let latched = bytes.len() >= INPUT_LATCHED_LEN;
+    let (fire_seq, switch_seq, switch_weapon) = if latched {
+        (
+            read_u64(bytes, 16)?,
+            read_u64(bytes, 24)?,
+            bytes[32] as i8 as i32,
+        )
+    } else {
+        (0, 0, -1)
+    };
     Ok(ClientMsg::Input {
         seq,
         key_up: flags & 0x01 != 0,
@@ -69,6 +100,10 @@ fn decode_input(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
         weapon_scroll,
         aim_angle,
         facing_left: flags & 0x20 != 0,
+        fire_seq,
+        switch_seq,
+        switch_weapon,
+        latched,
     })
 }
 
@@ -81,6 +116,33 @@ fn decode_ping(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
     })
 }
 
+fn decode_watch_demo(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
+    if bytes.len() < 2 {
+        return Err(DecodeError::OutOfBounds);
+    }
+    let name_len = bytes[1] as usize;
+    let name = read_string(bytes, 2, name_len)?;
+    Ok(ClientMsg::WatchDemo { name })
+}
+
+fn decode_demo_control(bytes: &[u8]) -> Result<ClientMsg, DecodeError> {
+    if bytes.len() < DEMO_CONTROL_LEN {
+        return Err(DecodeError::OutOfBounds);
+    }
+    let control = match bytes[1] {
+        DEMO_PAUSE => DemoControl::Pause,
+        DEMO_RESUME => DemoControl::Resume,
+        DEMO_SEEK => DemoControl::Seek {
+            tick: read_u64(bytes, 2)?,
+        },
+        DEMO_SPEED => DemoControl::Speed {
+            speed: read_f32(bytes, 2)?,
+        },
+        action => return Err(DecodeError::UnknownType(action)),
+    };
+    Ok(ClientMsg::DemoControl { control })
+}
+
 fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, DecodeError> {
     if bytes.len() < offset + 8 {
         return Err(DecodeError::OutOfBounds);
@@ -109,7 +171,9 @@ fn read_string(bytes: &[u8], offset: usize, len: usize) -> Result<String, Decode
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::encode::{encode_hello, encode_input, encode_join_room};
+    use crate::encode::{
+        encode_demo_control, encode_hello, encode_input, encode_join_room, encode_watch_demo,
+    };
 
     #[test]
     fn hello_roundtrip() {
@@ -123,12 +187,35 @@ mod tests {
 
     #[test]
     fn join_room_roundtrip() {
-        let encoded = encode_join_room("room-1", "dm2");
+        let encoded = encode_join_room("room-1", "dm2", "instagib", JoinRole::Spectator);
         let decoded = decode_client_message(&encoded).unwrap();
         match decoded {
-            ClientMsg::JoinRoom { room_id, map } => {
+            ClientMsg::JoinRoom {
+                room_id,
+                map,
+                ruleset,
+                role,
+            } => {
                 assert_eq!(room_id, Some("room-1".to_string()));
                 assert_eq!(map, Some("dm2".to_string()));
+                assert_eq!(ruleset, Some("instagib".to_string()));
+                assert_eq!(role, JoinRole::Spectator);
+            }
+            _ => panic!("expected JoinRoom"),
+        }
+    }
+
+    #[test]
+    fn join_room_without_ruleset_field() {
+        let mut encoded = encode_join_room("", "dm2", "", JoinRole::Player);
+        encoded.truncate(encoded.len() - 2);
+        match decode_client_message(&encoded).unwrap() {
+            ClientMsg::JoinRoom {
+                map, ruleset, role, ..
+            } => {
+                assert_eq!(map, Some("dm2".to_string()));
+                assert_eq!(ruleset, None);
+                assert_eq!(role, JoinRole::Player);
             }
             _ => panic!("expected JoinRoom"),
         }
@@ -136,7 +223,9 @@ mod tests {
 
     #[test]
     fn input_roundtrip() {
-        let encoded = encode_input(42, 1.25, true, false, true, false, true, true, 3, -1);
+        let encoded = encode_input(
+            42, 1.25, true, false, true, false, true, true, 3, -1, 40, 41, 5,
+        );
         let decoded = decode_client_message(&encoded).unwrap();
         match decoded {
             ClientMsg::Input {
@@ -150,6 +239,10 @@ mod tests {
                 weapon_scroll,
                 aim_angle,
                 facing_left,
+                fire_seq,
+                switch_seq,
+                switch_weapon,
+                latched,
             } => {
                 assert_eq!(seq, 42);
                 assert!(key_up);
@@ -161,8 +254,53 @@ mod tests {
                 assert_eq!(weapon_scroll, -1);
                 assert!((aim_angle - 1.25).abs() < f32::EPSILON);
                 assert!(facing_left);
+                assert_eq!((fire_seq, switch_seq, switch_weapon), (40, 41, 5));
+                assert!(latched);
+            }
+            _ => panic!("expected Input"),
+        }
+    }
+
+    #[test]
+    fn input_without_latched_edges() {
+        let encoded = encode_input(
+            7, 0.0, false, false, false, false, true, false, -1, 0, 3, 4, 2,
+        );
+        match decode_client_message(&encoded[..16]).unwrap() {
+            ClientMsg::Input {
+                seq,
+                mouse_down,
+                fire_seq,
+                switch_seq,
+                switch_weapon,
+                latched,
+                ..
+            } => {
+                assert_eq!(seq, 7);
+                assert!(mouse_down);
+                assert_eq!((fire_seq, switch_seq, switch_weapon), (0, 0, -1));
+                assert!(!latched);
             }
             _ => panic!("expected Input"),
         }
     }
+
+    #[test]
+    fn demo_messages_roundtrip() {
+        match decode_client_message(&encode_watch_demo("final-2024")).unwrap() {
+            ClientMsg::WatchDemo { name } => assert_eq!(name, "final-2024"),
+            _ => panic!("expected WatchDemo"),
+        }
+        for control in [
+            DemoControl::Pause,
+            DemoControl::Resume,
+            DemoControl::Seek { tick: 12_345 },
+            DemoControl::Speed { speed: 0.5 },
+        ] {
+            match decode_client_message(&encode_demo_control(control)).unwrap() {
+                ClientMsg::DemoControl { control: decoded } => assert_eq!(decoded, control),
+                _ => panic!("expected DemoControl"),
+            }
+        }
+    }
 }
@@ -1,5 +1,8 @@
 use crate::constants::*;
-use crate::types::{EffectEvent, ItemSnapshot, PlayerSnapshot, ProjectileSnapshot};
+use crate::types::{
+    DemoControl, DroppedItemSnapshot, EffectEvent, ItemSnapshot, JoinRole, PlayerSnapshot,
+    ProjectileSnapshot,
+};
 
 pub trait BinaryWriter {
     fn put_u8(&mut self, v: u8);
@@ -35,20 +38,29 @@ pub fn encode_hello(username: &str) -> Vec<u8> {
     out
 }
 
-pub fn encode_join_room(room_id: &str, map: &str) -> Vec<u8> {
+pub fn encode_join_room(room_id: &str, map: &str, ruleset: &str, role: JoinRole) -> Vec<u8> {
     let room_bytes = room_id.as_bytes();
     let map_bytes = map.as_bytes();
+    let ruleset_bytes = ruleset.as_bytes();
     let room_len = room_bytes.len().min(255);
     let map_len = map_bytes.len().min(255);
-    let mut out = Vec::with_capacity(3 + room_len + map_len);
+    let ruleset_len = ruleset_bytes.len().min(255);
+    let mut out = Vec::with_capacity(5 + room_len + map_len + ruleset_len);
     out.push(MSG_JOIN_ROOM);
     out.push(room_len as u8);
     out.push(map_len as u8);
     out.extend_from_slice(&room_bytes[..room_len]);
     out.extend_from_slice(&map_bytes[..map_len]);
+    out.push(ruleset_len as u8);
+    out.extend_from_slice(&ruleset_bytes[..ruleset_len]);
+    out.push(match role {
+        JoinRole::Player => JOIN_ROLE_PLAYER,
+        JoinRole::Spectator => JOIN_ROLE_SPECTATOR,
+    });
     out
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn encode_input(
     seq: u64,
     aim_angle: f32,
@@ -60,8 +72,11 @@ pub fn encode_input(
     facing_left: bool,
     weapon_switch: i8,
     weapon_scroll: i8,
+    fire_seq: u64,
+    switch_seq: u64,
+    switch_weapon: i8,
 ) -> Vec<u8> {
-    let mut out = Vec::with_capacity(16);
+    let mut out = Vec::with_capacity(INPUT_LATCHED_LEN);
     out.push(MSG_INPUT);
     push_u64(&mut out, seq);
     push_f32(&mut out, aim_angle);
@@ -87,6 +102,9 @@ pub fn encode_input(
     out.push(flags);
     out.push(weapon_switch as u8);
     out.push(weapon_scroll as u8);
+    push_u64(&mut out, fire_seq);
+    push_u64(&mut out, switch_seq);
+    out.push(switch_weapon as u8);
     out
 }
 
@@ -97,10 +115,65 @@ pub fn encode_ping(client_time_ms: u64) -> Vec<u8> {
     out
 }
 
-pub fn encode_welcome(player_id: u64) -> Vec<u8> {
-    let mut out = Vec::with_capacity(9);
+pub fn encode_watch_demo(name: &str) -> Vec<u8> {
+    let name_bytes = name.as_bytes();
+    let len = name_bytes.len().min(255);
+    let mut out = Vec::with_capacity(2 + len);
+    out.push(MSG_WATCH_DEMO);
+    out.push(len as u8);
+    out.extend_from_slice(&name_bytes[..len]);
+    out
+}
+
+pub fn encode_demo_control(control: DemoControl) -> Vec<u8> {
+    let mut out = Vec::with_capacity(DEMO_CONTROL_LEN);
+    out.push(MSG_DEMO_CONTROL);
+    match control {
+        DemoControl::Pause => out.push(DEMO_PAUSE),
+        DemoControl::Resume => out.push(DEMO_RESUME),
+        DemoControl::Seek { tick } => {
+            out.push(DEMO_SEEK);
+            push_u64(&mut out, tick);
+        }
+        DemoControl::Speed { speed } => {
+            out.push(DEMO_SPEED);
+            push_f32(&mut out, speed);
+        }
+    }
+    out.resize(DEMO_CONTROL_LEN, 0);
+    out
+}
+
+pub fn encode_demo_state(tick: u64, end_tick: u64, paused: bool, speed: f32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(DEMO_STATE_LEN);
+    out.push(MSG_DEMO_STATE);
+    push_u64(&mut out, tick);
+    push_u64(&mut out, end_tick);
+    out.push(u8::from(paused));
+    push_f32(&mut out, speed);
+    out
+}
+
+/// Overwrite the tick and server time of an encoded snapshot, so demo
+/// playback can keep both moving forward across seeks and speed changes.
+pub fn restamp_snapshot(payload: &mut [u8], tick: u64, server_time_ms: u64) {
+    if payload.len() < SNAPSHOT_HEADER_LEN || payload[0] != MSG_SNAPSHOT {
+        return;
+    }
+    payload[1..9].copy_from_slice(&tick.to_le_bytes());
+    payload[9..17].copy_from_slice(&server_time_ms.to_le_bytes());
+}
+
+/// `resume_token` lets the client reclaim this player id after a dropped
+/// connection; older clients stop reading after the id.
+pub fn encode_welcome(player_id: u64, resume_token: &str) -> Vec<u8> {
+    let token = resume_token.as_bytes();
+    let token_len = token.len().min(255);
+    let mut out = Vec::with_capacity(10 + token_len);
     out.push(MSG_WELCOME);
     push_u64(&mut out, player_id);
+    out.push(token_len as u8);
+    out.extend_from_slice(&token[..token_len]);
     out
 }
 
@@ -155,18 +228,24 @@ pub fn encode_player_left(id: u64) -> Vec<u8> {
     out
 }
 
+/// `tick_rate` is the tick length rounded to Hz, the field older clients read;
+/// the exact `tick_millis` is appended after the players, where they stop.
 pub fn encode_room_state(
     room_id: &str,
     map_name: &str,
     tick_rate: u16,
+    tick_millis: u16,
+    ruleset: &[u8],
     players: &[(String, PlayerSnapshot)],
 ) -> Vec<u8> {
     let room_id_bytes = room_id.as_bytes();
     let map_bytes = map_name.as_bytes();
     let room_len = room_id_bytes.len().min(255);
     let map_len = map_bytes.len().min(255);
+    let ruleset_len = ruleset.len().min(u16::MAX as usize);
     let player_count = players.len().min(255) as u8;
-    let mut out = Vec::with_capacity(6 + room_len + map_len + player_count as usize * 96);
+    let mut out =
+        Vec::with_capacity(10 + room_len + map_len + ruleset_len + player_count as usize * 96);
     out.push(MSG_ROOM_STATE);
     out.push(room_len as u8);
     out.push(map_len as u8);
@@ -174,13 +253,16 @@ pub fn encode_room_state(
     push_u16(&mut out, tick_rate);
     out.extend_from_slice(&room_id_bytes[..room_len]);
     out.extend_from_slice(&map_bytes[..map_len]);
-    for (username, snapshot) in players {
+    push_u16(&mut out, ruleset_len as u16);
+    out.extend_from_slice(&ruleset[..ruleset_len]);
+    for (username, snapshot) in players.iter().take(player_count as usize) {
         let name_bytes = username.as_bytes();
         let len = name_bytes.len().min(255);
         out.push(len as u8);
         out.extend_from_slice(&name_bytes[..len]);
         write_player_record(&mut out, snapshot);
     }
+    push_u16(&mut out, tick_millis);
     out
 }
 
@@ -189,6 +271,7 @@ pub fn encode_snapshot(
     server_time_ms: u64,
     players: &[PlayerSnapshot],
     items: &[ItemSnapshot],
+    dropped: &[DroppedItemSnapshot],
     projectiles: &[ProjectileSnapshot],
     events: &[EffectEvent],
 ) -> Vec<u8> {
@@ -196,9 +279,12 @@ pub fn encode_snapshot(
     let item_count = items.len().min(255) as u8;
     let projectile_count = projectiles.len().min(u16::MAX as usize) as u16;
     let event_count = events.len().min(255) as u8;
+    let dropped_count = dropped.len().min(255) as u8;
     let mut out = Vec::with_capacity(
-        22 + (player_count as usize * 63)
+        SNAPSHOT_HEADER_LEN
+            + (player_count as usize * PLAYER_RECORD_LEN)
             + (item_count as usize * 3)
+            + (dropped_count as usize * DROPPED_ITEM_RECORD_LEN)
             + (projectile_count as usize * 33)
             + (event_count as usize * 40),
     );
@@ -209,6 +295,7 @@ pub fn encode_snapshot(
     out.push(item_count);
     push_u16(&mut out, projectile_count);
     out.push(event_count);
+    out.push(dropped_count);
     for snapshot in players {
         write_player_record(&mut out, snapshot);
     }
@@ -220,6 +307,9 @@ pub fn encode_snapshot(
         out.push(flags);
         push_i16(&mut out, item.respawn_timer);
     }
+    for item in dropped.iter().take(dropped_count as usize) {
+        write_dropped_item_record(&mut out, item);
+    }
     for proj in projectiles {
         push_u64(&mut out, proj.id);
         push_f32(&mut out, proj.x);
@@ -235,6 +325,13 @@ pub fn encode_snapshot(
     out
 }
 
+pub fn write_dropped_item_record<W: BinaryWriter>(out: &mut W, item: &DroppedItemSnapshot) {
+    write_u64(out, item.id);
+    out.put_u8(item.kind);
+    write_f32(out, item.x);
+    write_f32(out, item.y);
+}
+
 pub fn write_player_record<W: BinaryWriter>(out: &mut W, snap: &PlayerSnapshot) {
     write_u64(out, snap.id);
     write_f32(out, snap.x);
@@ -283,6 +380,26 @@ pub fn write_player_record<W: BinaryWriter>(out: &mut W, snap: &PlayerSnapshot)
         flags |= 0x40;
     }
     out.put_u8(flags);
+    let mut powerups = 0u8;
+    if snap.quad {
+        powerups |= 0x01;
+    }
+    if snap.regeneration {
+        powerups |= 0x02;
+    }
+    if snap.haste {
+        powerups |= 0x04;
+    }
+    if snap.battle_suit {
+        powerups |= 0x08;
+    }
+    if snap.invisibility {
+        powerups |= 0x10;
+    }
+    out.put_u8(powerups);
+    out.put_u8(snap.armor_type);
+    out.put_u8(snap.weapon_state);
+    out.put_u8(snap.weapon_timer.clamp(0, 255) as u8);
 }
 
 pub fn write_event<W: BinaryWriter>(out: &mut W, event: &EffectEvent) {
@@ -318,12 +435,19 @@ pub fn write_event<W: BinaryWriter>(out: &mut W, event: &EffectEvent) {
             start_y,
             end_x,
             end_y,
+            impacts,
         } => {
             out.put_u8(EVENT_RAIL);
             write_f32(out, *start_x);
             write_f32(out, *start_y);
             write_f32(out, *end_x);
             write_f32(out, *end_y);
+            let count = impacts.len().min(255);
+            out.put_u8(count as u8);
+            for (x, y) in &impacts[..count] {
+                write_f32(out, *x);
+                write_f32(out, *y);
+            }
         }
         EffectEvent::Shaft {
             start_x,
@@ -359,6 +483,8 @@ pub fn write_event<W: BinaryWriter>(out: &mut W, event: &EffectEvent) {
             target_id,
             amount,
             killed,
+            hit_zone,
+            weapon_id,
         } => {
             out.put_u8(EVENT_DAMAGE);
             write_u64(out, *attacker_id);
@@ -367,7 +493,13 @@ pub fn write_event<W: BinaryWriter>(out: &mut W, event: &EffectEvent) {
                 out,
                 (*amount).clamp(i16::MIN as i32, i16::MAX as i32) as i16,
             );
-            out.put_u8(if *killed { 0x01 } else { 0x00 });
+            // Bit 0: killed, bits 1-2: hit zone.
+            let mut flags = (*hit_zone & 0x03) << 1;
+            if *killed {
+                flags |= 0x01;
+            }
+            out.put_u8(flags);
+            out.put_u8(*weapon_id as u8);
         }
         EffectEvent::ProjectileRemove { id, x, y, kind } => {
             out.put_u8(EVENT_PROJECTILE_REMOVE);
@@ -376,6 +508,39 @@ pub fn write_event<W: BinaryWriter>(out: &mut W, event: &EffectEvent) {
             write_f32(out, *y);
             out.put_u8(*kind);
         }
+        EffectEvent::MultiKill { attacker_id, count } => {
+            out.put_u8(EVENT_MULTI_KILL);
+            write_u64(out, *attacker_id);
+            out.put_u8(*count);
+        }
+        EffectEvent::ItemPickup {
+            player_id,
+            index,
+            kind,
+        } => {
+            out.put_u8(EVENT_ITEM_PICKUP);
+            write_u64(out, *player_id);
+            write_u16(out, index.unwrap_or(NO_ITEM_INDEX));
+            out.put_u8(*kind);
+        }
+        EffectEvent::ItemRespawn { index } => {
+            out.put_u8(EVENT_ITEM_RESPAWN);
+            write_u16(out, *index);
+        }
+        EffectEvent::QuadStart { player_id } => {
+            out.put_u8(EVENT_QUAD_START);
+            write_u64(out, *player_id);
+        }
+        EffectEvent::QuadExpire { player_id } => {
+            out.put_u8(EVENT_QUAD_EXPIRE);
+            write_u64(out, *player_id);
+        }
+        EffectEvent::PlayerRespawn { player_id, x, y } => {
+            out.put_u8(EVENT_PLAYER_RESPAWN);
+            write_u64(out, *player_id);
+            write_f32(out, *x);
+            write_f32(out, *y);
+        }
     }
 }
 
@@ -12,11 +12,13 @@ pub mod wasm;
 pub use constants::*;
 pub use decode::decode_client_message;
 pub use encode::{
-    encode_hello, encode_input, encode_join_rejected, encode_join_room, encode_kicked, encode_ping,
-    encode_player_joined, encode_player_left, encode_pong, encode_room_closed, encode_room_state,
-    encode_snapshot, encode_welcome, kind_u8_to_str, write_event, write_player_record,
-    BinaryWriter,
+    encode_demo_control, encode_demo_state, encode_hello, encode_input, encode_join_rejected,
+    encode_join_room, encode_kicked, encode_ping, encode_player_joined, encode_player_left,
+    encode_pong, encode_room_closed, encode_room_state, encode_snapshot, encode_watch_demo,
+    encode_welcome, kind_u8_to_str, restamp_snapshot, write_dropped_item_record, write_event,
+    write_player_record, BinaryWriter,
 };
 pub use types::{
-    ClientMsg, DecodeError, EffectEvent, ItemSnapshot, PlayerSnapshot, ProjectileSnapshot,
+    ClientMsg, DecodeError, DemoControl, DroppedItemSnapshot, EffectEvent, ItemSnapshot, JoinRole,
+    PlayerSnapshot, ProjectileSnapshot,
 };
@@ -10,6 +10,9 @@ pub enum ClientMsg {
     JoinRoom {
         room_id: Option<String>,
         map: Option<String>,
+        /// Ruleset file name; only used when the join creates the room.
+        ruleset: Option<String>,
+        role: JoinRole,
     },
     Input {
         seq: u64,
@@ -22,10 +25,48 @@ pub enum ClientMsg {
         weapon_scroll: i32,
         aim_angle: f32,
         facing_left: bool,
+        /// Seq of the latest input where fire was pressed; 0 if none.
+        fire_seq: u64,
+        /// Seq of the latest weapon switch request and its target; 0 if none.
+        switch_seq: u64,
+        switch_weapon: i32,
+        /// The packet carried the edges above; older 16-byte inputs don't.
+        latched: bool,
     },
     Ping {
         client_time_ms: u64,
     },
+    /// Watch a recorded demo instead of joining a room.
+    WatchDemo {
+        name: String,
+    },
+    DemoControl {
+        control: DemoControl,
+    },
+}
+
+/// Spectators get every broadcast but have no body in the simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JoinRole {
+    #[default]
+    Player,
+    Spectator,
+}
+
+/// Playback command from a demo viewer. Viewers share one playback, so any
+/// of them can steer it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DemoControl {
+    Pause,
+    Resume,
+    /// Jump to a tick counted from the start of the demo.
+    Seek {
+        tick: u64,
+    },
+    /// Playback rate relative to real time.
+    Speed {
+        speed: f32,
+    },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -41,8 +82,13 @@ pub struct PlayerSnapshot {
     pub dead: bool,
     pub health: i32,
     pub armor: i32,
+    /// 0 none, 1 green, 2 yellow, 3 red.
+    pub armor_type: u8,
     pub current_weapon: i32,
     pub fire_cooldown: i32,
+    /// 0 ready, 1 firing, 2 lowering, 3 raising.
+    pub weapon_state: u8,
+    pub weapon_timer: i32,
     pub weapons: [bool; WEAPON_COUNT],
     pub ammo: [i32; WEAPON_COUNT],
     pub last_input_seq: u64,
@@ -50,6 +96,11 @@ pub struct PlayerSnapshot {
     pub key_right: bool,
     pub key_up: bool,
     pub key_down: bool,
+    pub quad: bool,
+    pub regeneration: bool,
+    pub haste: bool,
+    pub battle_suit: bool,
+    pub invisibility: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -58,6 +109,15 @@ pub struct ItemSnapshot {
     pub respawn_timer: i16,
 }
 
+/// Weapon or powerup dropped on death; `kind` is its map token.
+#[derive(Debug, Clone, Copy)]
+pub struct DroppedItemSnapshot {
+    pub id: u64,
+    pub kind: u8,
+    pub x: f32,
+    pub y: f32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ProjectileSnapshot {
     pub id: u64,
@@ -89,6 +149,7 @@ pub enum EffectEvent {
         start_y: f32,
         end_x: f32,
         end_y: f32,
+        impacts: Vec<(f32, f32)>,
     },
     Shaft {
         start_x: f32,
@@ -110,11 +171,14 @@ pub enum EffectEvent {
         y: f32,
         kind: u8,
     },
+    /// `weapon_id` is -1 for damage no weapon dealt, such as telefrags.
     Damage {
         attacker_id: u64,
         target_id: u64,
         amount: i32,
         killed: bool,
+        hit_zone: u8,
+        weapon_id: i32,
     },
     ProjectileRemove {
         id: u64,
@@ -122,6 +186,31 @@ pub enum EffectEvent {
         y: f32,
         kind: u8,
     },
+    MultiKill {
+        attacker_id: u64,
+        count: u8,
+    },
+    /// `index` is the map item slot, `None` for dropped weapons and powerups;
+    /// `kind` is the item's map token.
+    ItemPickup {
+        player_id: u64,
+        index: Option<u16>,
+        kind: u8,
+    },
+    ItemRespawn {
+        index: u16,
+    },
+    QuadStart {
+        player_id: u64,
+    },
+    QuadExpire {
+        player_id: u64,
+    },
+    PlayerRespawn {
+        player_id: u64,
+        x: f32,
+        y: f32,
+    },
 }
 
 #[derive(Debug)]
@@ -1,10 +1,14 @@
 #![cfg(feature = "wasm")]
 
-use js_sys::{Array, Object, Reflect};
+use js_sys::{Array, Object, Reflect, Uint8Array};
 use wasm_bindgen::prelude::*;
 
 use crate::constants::*;
-use crate::encode::{encode_hello, encode_input, encode_join_room, encode_ping, kind_u8_to_str};
+use crate::encode::{
+    encode_demo_control, encode_hello, encode_input, encode_join_room, encode_ping,
+    encode_watch_demo, kind_u8_to_str,
+};
+use crate::types::{DemoControl, JoinRole};
 
 #[wasm_bindgen]
 pub fn wasm_encode_hello(username: &str) -> Vec<u8> {
@@ -12,11 +16,17 @@ pub fn wasm_encode_hello(username: &str) -> Vec<u8> {
 }
 
 #[wasm_bindgen]
-pub fn wasm_encode_join_room(room_id: &str, map: &str) -> Vec<u8> {
-    encode_join_room(room_id, map)
+pub fn wasm_encode_join_room(room_id: &str, map: &str, ruleset: &str, spectator: bool) -> Vec<u8> {
+    let role = if spectator {
+        JoinRole::Spectator
+    } else {
+        JoinRole::Player
+    };
+    encode_join_room(room_id, map, ruleset, role)
 }
 
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn wasm_encode_input(
     seq: u64,
     aim_angle: f32,
@@ -28,6 +38,9 @@ pub fn wasm_encode_input(
     facing_left: bool,
     weapon_switch: i8,
     weapon_scroll: i8,
+    fire_seq: u64,
+    switch_seq: u64,
+    switch_weapon: i8,
 ) -> Vec<u8> {
     encode_input(
         seq,
@@ -40,6 +53,9 @@ pub fn wasm_encode_input(
         facing_left,
         weapon_switch,
         weapon_scroll,
+        fire_seq,
+        switch_seq,
+        switch_weapon,
     )
 }
 
@@ -48,6 +64,25 @@ pub fn wasm_encode_ping(client_time_ms: u64) -> Vec<u8> {
     encode_ping(client_time_ms)
 }
 
+#[wasm_bindgen]
+pub fn wasm_encode_watch_demo(name: &str) -> Vec<u8> {
+    encode_watch_demo(name)
+}
+
+/// `tick` is only read for seeks and `speed` only for speed changes; unknown
+/// actions encode to an empty message.
+#[wasm_bindgen]
+pub fn wasm_encode_demo_control(action: u8, tick: u64, speed: f32) -> Vec<u8> {
+    let control = match action {
+        DEMO_PAUSE => DemoControl::Pause,
+        DEMO_RESUME => DemoControl::Resume,
+        DEMO_SEEK => DemoControl::Seek { tick },
+        DEMO_SPEED => DemoControl::Speed { speed },
+        _ => return Vec::new(),
+    };
+    encode_demo_control(control)
+}
+
 #[wasm_bindgen]
 pub fn wasm_decode_server_message(buffer: &[u8]) -> JsValue {
     if buffer.is_empty() {
@@ -60,6 +95,7 @@ pub fn wasm_decode_server_message(buffer: &[u8]) -> JsValue {
         MSG_PLAYER_LEFT => decode_player_left_js(buffer),
         MSG_SNAPSHOT => decode_snapshot_js(buffer),
         MSG_PONG => decode_pong_js(buffer),
+        MSG_DEMO_STATE => decode_demo_state_js(buffer),
         _ => JsValue::NULL,
     }
 }
@@ -72,6 +108,10 @@ fn decode_welcome_js(bytes: &[u8]) -> JsValue {
     let obj = Object::new();
     set_str(&obj, "type", "welcome");
     set_f64(&obj, "player_id", player_id as f64);
+    let token_len = bytes.get(9).copied().unwrap_or(0) as usize;
+    if token_len > 0 && bytes.len() >= 10 + token_len {
+        set_str(&obj, "resume_token", &read_str(bytes, 10, token_len));
+    }
     obj.into()
 }
 
@@ -91,6 +131,16 @@ fn decode_room_state_js(bytes: &[u8]) -> JsValue {
     offset += room_len;
     let map = read_str(bytes, offset, map_len);
     offset += map_len;
+    if bytes.len() < offset + 2 {
+        return JsValue::NULL;
+    }
+    let ruleset_len = read_u16(bytes, offset) as usize;
+    offset += 2;
+    if bytes.len() < offset + ruleset_len {
+        return JsValue::NULL;
+    }
+    let ruleset = Uint8Array::from(&bytes[offset..offset + ruleset_len]);
+    offset += ruleset_len;
     let players = Array::new();
     for _ in 0..player_count {
         if offset >= bytes.len() {
@@ -100,12 +150,12 @@ fn decode_room_state_js(bytes: &[u8]) -> JsValue {
         offset += 1;
         let username = read_str(bytes, offset, name_len);
         offset += name_len;
-        if bytes.len() < offset + 63 {
+        if bytes.len() < offset + PLAYER_RECORD_LEN {
             break;
         }
         let state = decode_player_record_js(bytes, offset);
-        offset += 63;
-        let player_id = read_u64(bytes, offset - 63);
+        let player_id = read_u64(bytes, offset);
+        offset += PLAYER_RECORD_LEN;
         let info = Object::new();
         set_f64(&info, "id", player_id as f64);
         set_jsval(&info, "username", &JsValue::from_str(&username));
@@ -117,6 +167,11 @@ fn decode_room_state_js(bytes: &[u8]) -> JsValue {
     set_jsval(&obj, "room_id", &JsValue::from_str(&room_id));
     set_jsval(&obj, "map", &JsValue::from_str(&map));
     set_f64(&obj, "tick_rate", tick_rate);
+    // Older servers end the message after the players.
+    if bytes.len() >= offset + 2 {
+        set_f64(&obj, "tick_millis", read_u16(bytes, offset) as f64);
+    }
+    set_jsval(&obj, "ruleset", &ruleset);
     set_jsval(&obj, "players", &players);
     obj.into()
 }
@@ -149,7 +204,7 @@ fn decode_player_left_js(bytes: &[u8]) -> JsValue {
 }
 
 fn decode_snapshot_js(bytes: &[u8]) -> JsValue {
-    if bytes.len() < 22 {
+    if bytes.len() < SNAPSHOT_HEADER_LEN {
         return JsValue::NULL;
     }
     let tick = read_u64(bytes, 1);
@@ -158,15 +213,16 @@ fn decode_snapshot_js(bytes: &[u8]) -> JsValue {
     let item_count = bytes[18] as usize;
     let projectile_count = read_u16(bytes, 19) as usize;
     let event_count = bytes[21] as usize;
-    let mut offset = 22;
+    let dropped_count = bytes[22] as usize;
+    let mut offset = SNAPSHOT_HEADER_LEN;
 
     let players = Array::new();
     for _ in 0..player_count {
-        if bytes.len() < offset + 63 {
+        if bytes.len() < offset + PLAYER_RECORD_LEN {
             return JsValue::NULL;
         }
         players.push(&decode_player_record_js(bytes, offset));
-        offset += 63;
+        offset += PLAYER_RECORD_LEN;
     }
 
     let items = Array::new();
@@ -183,6 +239,20 @@ fn decode_snapshot_js(bytes: &[u8]) -> JsValue {
         items.push(&item);
     }
 
+    let dropped_items = Array::new();
+    for _ in 0..dropped_count {
+        if bytes.len() < offset + DROPPED_ITEM_RECORD_LEN {
+            return JsValue::NULL;
+        }
+        let item = Object::new();
+        set_f64(&item, "id", read_u64(bytes, offset) as f64);
+        set_str(&item, "token", &char::from(bytes[offset + 8]).to_string());
+        set_f64(&item, "x", read_f32(bytes, offset + 9) as f64);
+        set_f64(&item, "y", read_f32(bytes, offset + 13) as f64);
+        offset += DROPPED_ITEM_RECORD_LEN;
+        dropped_items.push(&item);
+    }
+
     let projectiles = Array::new();
     for _ in 0..projectile_count {
         if bytes.len() < offset + 33 {
@@ -218,6 +288,7 @@ fn decode_snapshot_js(bytes: &[u8]) -> JsValue {
     set_f64(&obj, "server_time_ms", server_time_ms as f64);
     set_jsval(&obj, "players", &players);
     set_jsval(&obj, "items", &items);
+    set_jsval(&obj, "dropped_items", &dropped_items);
     set_jsval(&obj, "projectiles", &projectiles);
     set_jsval(&obj, "events", &events);
     obj.into()
@@ -234,6 +305,19 @@ fn decode_pong_js(bytes: &[u8]) -> JsValue {
     obj.into()
 }
 
+fn decode_demo_state_js(bytes: &[u8]) -> JsValue {
+    if bytes.len() < DEMO_STATE_LEN {
+        return JsValue::NULL;
+    }
+    let obj = Object::new();
+    set_str(&obj, "type", "demo_state");
+    set_f64(&obj, "tick", read_u64(bytes, 1) as f64);
+    set_f64(&obj, "end_tick", read_u64(bytes, 9) as f64);
+    set_bool(&obj, "paused", bytes[17] != 0);
+    set_f64(&obj, "speed", read_f32(bytes, 18) as f64);
+    obj.into()
+}
+
 fn decode_player_record_js(bytes: &[u8], offset: usize) -> JsValue {
     let id = read_u64(bytes, offset);
     let x = read_f32(bytes, offset + 8);
@@ -254,6 +338,10 @@ fn decode_player_record_js(bytes: &[u8], offset: usize) -> JsValue {
     }
     let last_input_seq = read_u64(bytes, offset + 54);
     let flags = bytes[offset + 62];
+    let powerups = bytes[offset + 63];
+    let armor_type = bytes[offset + 64];
+    let weapon_state = bytes[offset + 65];
+    let weapon_timer = bytes[offset + 66];
     let weapons = Array::new();
     for i in 0..WEAPON_COUNT {
         weapons.push(&JsValue::from_bool((weapon_bits & (1 << i)) != 0));
@@ -270,8 +358,11 @@ fn decode_player_record_js(bytes: &[u8], offset: usize) -> JsValue {
     set_bool(&obj, "dead", (flags & 0x04) != 0);
     set_f64(&obj, "health", health as f64);
     set_f64(&obj, "armor", armor as f64);
+    set_f64(&obj, "armor_type", armor_type as f64);
     set_f64(&obj, "current_weapon", current_weapon as f64);
     set_f64(&obj, "fire_cooldown", fire_cooldown as f64);
+    set_f64(&obj, "weapon_state", weapon_state as f64);
+    set_f64(&obj, "weapon_timer", weapon_timer as f64);
     set_jsval(&obj, "weapons", &weapons);
     set_jsval(&obj, "ammo", &ammo);
     set_f64(&obj, "last_input_seq", last_input_seq as f64);
@@ -279,6 +370,11 @@ fn decode_player_record_js(bytes: &[u8], offset: usize) -> JsValue {
     set_bool(&obj, "key_right", (flags & 0x10) != 0);
     set_bool(&obj, "key_up", (flags & 0x20) != 0);
     set_bool(&obj, "key_down", (flags & 0x40) != 0);
+    set_bool(&obj, "quad", (powerups & 0x01) != 0);
+    set_bool(&obj, "regeneration", (powerups & 0x02) != 0);
+    set_bool(&obj, "haste", (powerups & 0x04) != 0);
+    set_bool(&obj, "battle_suit", (powerups & 0x08) != 0);
+    set_bool(&obj, "invisibility", (powerups & 0x10) != 0);
     obj.into()
 }
 
@@ -314,16 +410,30 @@ fn decode_event_js(bytes: &[u8], offset: usize) -> (JsValue, usize) {
             (obj.into(), 34)
         }
         EVENT_RAIL => {
-            if bytes.len() < offset + 17 {
+            if bytes.len() < offset + 18 {
+                return (JsValue::NULL, 0);
+            }
+            let impact_count = bytes[offset + 17] as usize;
+            let size = 18 + impact_count * 8;
+            if bytes.len() < offset + size {
                 return (JsValue::NULL, 0);
             }
+            let impacts = Array::new();
+            for idx in 0..impact_count {
+                let impact_offset = offset + 18 + idx * 8;
+                let impact = Object::new();
+                set_f64(&impact, "x", read_f32(bytes, impact_offset) as f64);
+                set_f64(&impact, "y", read_f32(bytes, impact_offset + 4) as f64);
+                impacts.push(&impact);
+            }
             let obj = Object::new();
             set_str(&obj, "type", "rail");
             set_f64(&obj, "start_x", read_f32(bytes, offset + 1) as f64);
             set_f64(&obj, "start_y", read_f32(bytes, offset + 5) as f64);
             set_f64(&obj, "end_x", read_f32(bytes, offset + 9) as f64);
             set_f64(&obj, "end_y", read_f32(bytes, offset + 13) as f64);
-            (obj.into(), 17)
+            set_jsval(&obj, "impacts", &impacts);
+            (obj.into(), size)
         }
         EVENT_SHAFT => {
             if bytes.len() < offset + 17 {
@@ -370,7 +480,7 @@ fn decode_event_js(bytes: &[u8], offset: usize) -> (JsValue, usize) {
             (obj.into(), 10)
         }
         EVENT_DAMAGE => {
-            if bytes.len() < offset + 20 {
+            if bytes.len() < offset + 21 {
                 return (JsValue::NULL, 0);
             }
             let obj = Object::new();
@@ -379,7 +489,14 @@ fn decode_event_js(bytes: &[u8], offset: usize) -> (JsValue, usize) {
             set_f64(&obj, "target_id", read_u64(bytes, offset + 9) as f64);
             set_f64(&obj, "amount", read_i16(bytes, offset + 17) as f64);
             set_bool(&obj, "killed", (bytes[offset + 19] & 0x01) != 0);
-            (obj.into(), 20)
+            set_f64(&obj, "hit_zone", ((bytes[offset + 19] >> 1) & 0x03) as f64);
+            let weapon_id = bytes[offset + 20] as i8;
+            if weapon_id < 0 {
+                set_jsval(&obj, "weapon_id", &JsValue::NULL);
+            } else {
+                set_f64(&obj, "weapon_id", weapon_id as f64);
+            }
+            (obj.into(), 21)
         }
         EVENT_PROJECTILE_REMOVE => {
             if bytes.len() < offset + 18 {
@@ -393,6 +510,66 @@ fn decode_event_js(bytes: &[u8], offset: usize) -> (JsValue, usize) {
             set_str(&obj, "kind", kind_u8_to_str(bytes[offset + 17]));
             (obj.into(), 18)
         }
+        EVENT_MULTI_KILL => {
+            if bytes.len() < offset + 10 {
+                return (JsValue::NULL, 0);
+            }
+            let obj = Object::new();
+            set_str(&obj, "type", "multi_kill");
+            set_f64(&obj, "attacker_id", read_u64(bytes, offset + 1) as f64);
+            set_f64(&obj, "count", bytes[offset + 9] as f64);
+            (obj.into(), 10)
+        }
+        EVENT_ITEM_PICKUP => {
+            if bytes.len() < offset + 12 {
+                return (JsValue::NULL, 0);
+            }
+            let obj = Object::new();
+            set_str(&obj, "type", "item_pickup");
+            set_f64(&obj, "player_id", read_u64(bytes, offset + 1) as f64);
+            let index = read_u16(bytes, offset + 9);
+            if index == NO_ITEM_INDEX {
+                set_jsval(&obj, "item_index", &JsValue::NULL);
+            } else {
+                set_f64(&obj, "item_index", index as f64);
+            }
+            set_str(&obj, "token", &char::from(bytes[offset + 11]).to_string());
+            (obj.into(), 12)
+        }
+        EVENT_ITEM_RESPAWN => {
+            if bytes.len() < offset + 3 {
+                return (JsValue::NULL, 0);
+            }
+            let obj = Object::new();
+            set_str(&obj, "type", "item_respawn");
+            set_f64(&obj, "item_index", read_u16(bytes, offset + 1) as f64);
+            (obj.into(), 3)
+        }
+        EVENT_QUAD_START | EVENT_QUAD_EXPIRE => {
+            if bytes.len() < offset + 9 {
+                return (JsValue::NULL, 0);
+            }
+            let obj = Object::new();
+            let kind = if event_type == EVENT_QUAD_START {
+                "quad_start"
+            } else {
+                "quad_expire"
+            };
+            set_str(&obj, "type", kind);
+            set_f64(&obj, "player_id", read_u64(bytes, offset + 1) as f64);
+            (obj.into(), 9)
+        }
+        EVENT_PLAYER_RESPAWN => {
+            if bytes.len() < offset + 17 {
+                return (JsValue::NULL, 0);
+            }
+            let obj = Object::new();
+            set_str(&obj, "type", "player_respawn");
+            set_f64(&obj, "player_id", read_u64(bytes, offset + 1) as f64);
+            set_f64(&obj, "x", read_f32(bytes, offset + 9) as f64);
+            set_f64(&obj, "y", read_f32(bytes, offset + 13) as f64);
+            (obj.into(), 17)
+        }
         _ => (JsValue::NULL, 1),
     }
 }
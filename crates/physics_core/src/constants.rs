@@ -15,6 +15,10 @@ pub const PLAYER_HITBOX_TOP_STAND: f32 = 24.0;
 pub const PLAYER_HITBOX_TOP_CROUCH: f32 = 8.0;
 pub const PLAYER_HITBOX_BOTTOM: f32 = 22.0;
 pub const HITSCAN_AABB_PADDING: f32 = 2.0;
+// Hit zone boundaries as offsets from the player center (y grows downward).
+pub const HIT_ZONE_HEAD_BOTTOM_STAND: f32 = -12.0;
+pub const HIT_ZONE_HEAD_BOTTOM_CROUCH: f32 = -2.0;
+pub const HIT_ZONE_LEGS_TOP: f32 = 6.0;
 
 pub const PLAYER_MAX_VELOCITY_X: f32 = 3.0;
 pub const PLAYER_VELOCITY_CLAMP: f32 = 5.0;
@@ -34,16 +38,30 @@ pub const SPEED_JUMP_X: [f32; 7] = [0.0, 0.33, 0.8, 1.1, 1.4, 1.8, 2.2];
 
 pub const DEFAULT_AMMO: [i32; WEAPON_COUNT] = [-1, 100, 10, 5, 20, 10, 30, 50, 10];
 pub const PICKUP_AMMO: [i32; WEAPON_COUNT] = [-1, 50, 10, 5, 5, 10, 30, 50, 10];
+pub const AMMO_BOX: [i32; WEAPON_COUNT] = [0, 50, 10, 5, 5, 10, 30, 60, 15];
 
 pub const MAX_HEALTH: i32 = 100;
 pub const MAX_ARMOR: i32 = 200;
 pub const MEGA_HEALTH: i32 = 200;
-pub const ARMOR_ABSORPTION: f32 = 0.67;
+/// Green, yellow and red armor.
+pub const ARMOR_TIERS: usize = 3;
+pub const ARMOR_ABSORPTION: [f32; ARMOR_TIERS] = [0.5, 0.67, 0.75];
+pub const ARMOR_CAP: [i32; ARMOR_TIERS] = [100, 150, MAX_ARMOR];
 pub const SELF_DAMAGE_REDUCTION: f32 = 0.5;
 pub const QUAD_MULTIPLIER: f32 = 3.0;
 pub const QUAD_DURATION: i32 = 900;
+pub const POWERUP_DURATION: i32 = 1800;
+pub const REGEN_INTERVAL: i32 = 60;
+pub const REGEN_HEALTH: i32 = 15;
+pub const HASTE_FACTOR: f32 = 1.3;
+pub const BATTLE_SUIT_FACTOR: f32 = 0.5;
+pub const DECAY_INTERVAL: i32 = 60;
+pub const HEALTH_DECAY: i32 = 1;
+pub const ARMOR_DECAY: i32 = 1;
+pub const ARMOR_DECAY_FLOOR: i32 = 100;
 pub const RESPAWN_TIME: i32 = 180;
 pub const SPAWN_PROTECTION: i32 = 120;
+pub const DROPPED_ITEM_LIFETIME: i32 = 1800;
 
 // Projectile physics
 pub const PROJECTILE_GRAVITY: f32 = 0.05;
@@ -69,6 +87,11 @@ pub const PLASMA_SPLASH_DMG: f32 = 10.0;
 pub const PLASMA_SPLASH_RADIUS: f32 = 10.0;
 pub const PLASMA_SPLASH_PUSH: f32 = 1.5;
 
+// Radial knockback model
+pub const PLAYER_MASS: f32 = 1.0;
+pub const RADIAL_KNOCKBACK_SCALE: f32 = 1.4;
+pub const KNOCKBACK_MIN_DISTANCE: f32 = 0.01;
+
 // Weapon constants
 pub const GRENADE_LOFT: f32 = 2.0;
 pub const SHOTGUN_PELLETS: usize = 11;
@@ -80,6 +103,8 @@ pub const GAUNTLET_RANGE: f32 = TILE_W * 0.42;
 pub const SHAFT_RANGE: f32 = TILE_W * 3.0;
 pub const MACHINE_RANGE: f32 = 1000.0;
 pub const RAIL_RANGE: f32 = 2000.0;
+// Damage multiplier applied for each body a rail has already passed through.
+pub const RAIL_PIERCE_FALLOFF: f32 = 1.0;
 pub const HITSCAN_PLAYER_RADIUS: f32 = 14.0;
 pub const GAUNTLET_PLAYER_RADIUS: f32 = 10.0;
 pub const PICKUP_RADIUS: f32 = 16.0;
@@ -89,12 +114,30 @@ pub const HIT_RADIUS_ROCKET: f32 = 28.0;
 pub const HIT_RADIUS_BFG: f32 = 28.0;
 pub const HIT_RADIUS_GRENADE: f32 = 16.0;
 pub const HIT_RADIUS_PLASMA: f32 = 20.0;
+// Half-extents of projectile bodies for hitscan traces and projectile-vs-projectile checks
+pub const PROJECTILE_HITBOX_ROCKET: f32 = 6.0;
+pub const PROJECTILE_HITBOX_GRENADE: f32 = 6.0;
+pub const PROJECTILE_HITBOX_PLASMA: f32 = 5.0;
+pub const PROJECTILE_HITBOX_BFG: f32 = 12.0;
 // Scale circle hit radii when expanding AABB for point-vs-expanded-box checks.
 pub const PROJECTILE_AABB_RADIUS_SCALE: f32 = 0.70710677;
 
 // Weapon damage values (Gauntlet, Machine, Shotgun, Grenade, Rocket, Rail, Plasma, Shaft, Bfg)
 pub const DAMAGE: [f32; WEAPON_COUNT] = [50.0, 7.0, 7.0, 100.0, 100.0, 100.0, 20.0, 8.0, 100.0];
 
+// Damage multiplier per hit zone (head, torso, legs) for each weapon
+pub const HIT_ZONE_DAMAGE: [[f32; 3]; WEAPON_COUNT] = [
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.5, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+];
+
 // Splash radius per weapon (Gauntlet, Machine, Shotgun, Grenade, Rocket, Rail, Plasma, Shaft, Bfg)
 pub const SPLASH_RADIUS: [f32; WEAPON_COUNT] = [0.0, 0.0, 0.0, 60.0, 60.0, 0.0, 0.0, 0.0, 50.0];
 
@@ -110,3 +153,7 @@ pub const PROJECTILE_OFFSET: [f32; WEAPON_COUNT] =
 
 // Fire rate in ticks
 pub const FIRE_RATE: [i32; WEAPON_COUNT] = [20, 5, 50, 40, 40, 75, 5, 3, 10];
+/// Ticks spent lowering a weapon before the next one comes up.
+pub const WEAPON_LOWER_TIME: [i32; WEAPON_COUNT] = [6, 6, 6, 6, 6, 9, 6, 6, 9];
+/// Ticks spent raising a weapon before it can fire.
+pub const WEAPON_RAISE_TIME: [i32; WEAPON_COUNT] = [9, 9, 9, 9, 9, 12, 9, 9, 12];
@@ -1,10 +1,51 @@
 use crate::constants::{
-    DAMAGE, EXPLOSION_FAR_BIAS, EXPLOSION_FAR_SCALE, EXPLOSION_MID_BIAS, EXPLOSION_MID_SCALE,
-    PLASMA_SPLASH_DMG, PLASMA_SPLASH_PUSH, PLASMA_SPLASH_RADIUS, SPLASH_RADIUS, WEAPON_PUSH,
+    EXPLOSION_FAR_BIAS, EXPLOSION_FAR_SCALE, EXPLOSION_MID_BIAS, EXPLOSION_MID_SCALE,
+    KNOCKBACK_MIN_DISTANCE, PLAYER_MASS, RADIAL_KNOCKBACK_SCALE,
 };
 use crate::projectile::{Explosion, ProjectileKind};
+use crate::ruleset::Ruleset;
 use crate::types::PlayerState;
 
+/// How explosion and hit impulses are turned into player velocity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KnockbackModel {
+    /// Fixed push per axis based on the sign of the offset; upward kick only.
+    #[default]
+    Classic = 0,
+    /// Impulse along the normalized source-to-player direction, scaled by falloff and mass.
+    Radial = 1,
+}
+
+impl KnockbackModel {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Classic),
+            1 => Some(Self::Radial),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::Classic),
+            "radial" => Some(Self::Radial),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Radial => "radial",
+        }
+    }
+}
+
 /// Apply knockback from an explosion to a player.
 /// Returns the damage falloff (0.0-1.0) if player was in radius, None otherwise.
 pub fn apply_knockback(player: &mut PlayerState, explosion: &Explosion) -> Option<f32> {
@@ -16,11 +57,22 @@ pub fn apply_knockback_with_scale(
     explosion: &Explosion,
     push_scale: f32,
 ) -> Option<f32> {
-    let (radius, push) = match explosion.kind {
-        ProjectileKind::Rocket => (SPLASH_RADIUS[4], WEAPON_PUSH[4]),
-        ProjectileKind::Grenade => (SPLASH_RADIUS[3], WEAPON_PUSH[3]),
-        ProjectileKind::Plasma => (PLASMA_SPLASH_RADIUS, PLASMA_SPLASH_PUSH),
-        ProjectileKind::Bfg => (SPLASH_RADIUS[8], WEAPON_PUSH[8]),
+    apply_knockback_with_rules(player, explosion, push_scale, &Ruleset::DEFAULT)
+}
+
+/// Knockback using the ruleset's splash radii, push strengths and knockback model.
+pub fn apply_knockback_with_rules(
+    player: &mut PlayerState,
+    explosion: &Explosion,
+    push_scale: f32,
+    rules: &Ruleset,
+) -> Option<f32> {
+    let radius = splash_radius(explosion.kind, rules);
+    let push = match explosion.kind {
+        ProjectileKind::Rocket => rules.weapon_push[4],
+        ProjectileKind::Grenade => rules.weapon_push[3],
+        ProjectileKind::Plasma => rules.plasma_splash_push,
+        ProjectileKind::Bfg => rules.weapon_push[8],
     };
 
     if radius <= 0.0 {
@@ -38,32 +90,67 @@ pub fn apply_knockback_with_scale(
     let falloff = explosion_damage_falloff(radius, distance);
     let scaled_push = push * push_scale;
 
-    // Push player away from explosion center.
-    if dx > 0.01 {
-        player.velocity_x += scaled_push;
-    } else if dx < -0.01 {
-        player.velocity_x -= scaled_push;
-    }
+    match rules.knockback {
+        KnockbackModel::Classic => {
+            // Push player away from explosion center.
+            if dx > 0.01 {
+                player.velocity_x += scaled_push;
+            } else if dx < -0.01 {
+                player.velocity_x -= scaled_push;
+            }
 
-    // Only kick upward (explosions below launch the player up).
-    if dy > 0.01 {
-        player.velocity_y -= scaled_push;
+            // Only kick upward (explosions below launch the player up).
+            if dy > 0.01 {
+                player.velocity_y -= scaled_push;
+            }
+        }
+        KnockbackModel::Radial => {
+            apply_radial_impulse(
+                player,
+                dx,
+                dy,
+                scaled_push * falloff * RADIAL_KNOCKBACK_SCALE,
+            );
+        }
     }
 
     Some(falloff)
 }
 
+/// Add an impulse of `strength` along (dx, dy), divided by the player mass.
+/// A source directly on top of the player launches straight up.
+pub fn apply_radial_impulse(player: &mut PlayerState, dx: f32, dy: f32, strength: f32) {
+    let distance = (dx * dx + dy * dy).sqrt();
+    let (dir_x, dir_y) = if distance > KNOCKBACK_MIN_DISTANCE {
+        (dx / distance, dy / distance)
+    } else {
+        (0.0, -1.0)
+    };
+    let impulse = strength / PLAYER_MASS;
+    player.velocity_x += dir_x * impulse;
+    player.velocity_y += dir_y * impulse;
+}
+
+pub fn splash_radius(kind: ProjectileKind, rules: &Ruleset) -> f32 {
+    match kind {
+        ProjectileKind::Rocket => rules.splash_radius[4],
+        ProjectileKind::Grenade => rules.splash_radius[3],
+        ProjectileKind::Plasma => rules.plasma_splash_radius,
+        ProjectileKind::Bfg => rules.splash_radius[8],
+    }
+}
+
 /// Calculate explosion damage based on distance falloff and base damage.
 pub fn calculate_explosion_damage(falloff: f32, base_damage: f32) -> f32 {
     base_damage * falloff
 }
 
-pub fn base_damage(kind: ProjectileKind) -> f32 {
+pub fn base_damage(kind: ProjectileKind, rules: &Ruleset) -> f32 {
     match kind {
-        ProjectileKind::Rocket => DAMAGE[4],
-        ProjectileKind::Grenade => DAMAGE[3],
-        ProjectileKind::Plasma => PLASMA_SPLASH_DMG,
-        ProjectileKind::Bfg => DAMAGE[8],
+        ProjectileKind::Rocket => rules.damage[4],
+        ProjectileKind::Grenade => rules.damage[3],
+        ProjectileKind::Plasma => rules.plasma_splash_damage,
+        ProjectileKind::Bfg => rules.damage[8],
     }
 }
 
@@ -87,3 +174,70 @@ pub fn explosion_damage_falloff(radius: f32, distance: f32) -> f32 {
         / EXPLOSION_MID_SCALE;
     scaled.max(0.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_knockback_with_rules, KnockbackModel};
+    use crate::projectile::{Explosion, ProjectileKind};
+    use crate::ruleset::Ruleset;
+    use crate::types::PlayerState;
+
+    const RADIAL: Ruleset = Ruleset {
+        knockback: KnockbackModel::Radial,
+        ..Ruleset::DEFAULT
+    };
+
+    fn rocket_at(x: f32, y: f32) -> Explosion {
+        Explosion {
+            x,
+            y,
+            kind: ProjectileKind::Rocket,
+            owner_id: 0,
+        }
+    }
+
+    #[test]
+    fn classic_only_pushes_sideways_from_below() {
+        let mut player = PlayerState::new(1);
+        let falloff = apply_knockback_with_rules(
+            &mut player,
+            &rocket_at(-10.0, 10.0),
+            1.0,
+            &Ruleset::DEFAULT,
+        );
+
+        assert!(falloff.is_some());
+        assert!(player.velocity_x > 0.0);
+        assert_eq!(player.velocity_y, 0.0);
+    }
+
+    #[test]
+    fn radial_follows_direction_from_source() {
+        let mut player = PlayerState::new(1);
+        apply_knockback_with_rules(&mut player, &rocket_at(-10.0, 10.0), 1.0, &RADIAL);
+
+        assert!(player.velocity_x > 0.0);
+        assert!(player.velocity_y < 0.0);
+        assert!((player.velocity_x + player.velocity_y).abs() < 1e-4);
+    }
+
+    #[test]
+    fn radial_falls_off_with_distance() {
+        let mut near = PlayerState::new(1);
+        let mut far = PlayerState::new(2);
+        apply_knockback_with_rules(&mut near, &rocket_at(-5.0, 0.0), 1.0, &RADIAL);
+        apply_knockback_with_rules(&mut far, &rocket_at(-50.0, 0.0), 1.0, &RADIAL);
+
+        assert!(near.velocity_x > far.velocity_x);
+        assert!(far.velocity_x > 0.0);
+    }
+
+    #[test]
+    fn radial_launches_up_when_centered() {
+        let mut player = PlayerState::new(1);
+        apply_knockback_with_rules(&mut player, &rocket_at(0.0, 0.0), 1.0, &RADIAL);
+
+        assert_eq!(player.velocity_x, 0.0);
+        assert!(player.velocity_y < 0.0);
+    }
+}
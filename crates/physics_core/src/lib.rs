@@ -3,13 +3,22 @@
 
 pub mod constants;
 pub mod explosion;
+pub mod loadout;
+pub mod mutator;
 pub mod projectile;
+pub mod ruleset;
 pub mod step;
 pub mod tilemap;
 pub mod types;
 pub mod weapon;
 
-pub use explosion::apply_knockback;
+pub use explosion::{apply_knockback, KnockbackModel};
+pub use loadout::Loadout;
+pub use mutator::Mutator;
 pub use projectile::{calculate_bounds, step_projectile, Explosion, Projectile, ProjectileKind};
+pub use ruleset::{Ruleset, RulesetError};
 pub use step::step_player;
-pub use types::{expand_aabb, player_hitbox, segment_aabb_t};
+pub use types::{
+    expand_aabb, player_hit_zones, player_hitbox, segment_aabb_t, segment_hit_zone, ArmorType,
+    HitZone, WeaponState,
+};
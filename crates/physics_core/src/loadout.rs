@@ -0,0 +1,71 @@
+use crate::constants::{DEFAULT_AMMO, MAX_HEALTH, WEAPON_COUNT};
+use crate::types::{ArmorType, PlayerState};
+
+/// Weapons, ammo, health and armor a player gets when joining and respawning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Loadout {
+    pub weapons: [bool; WEAPON_COUNT],
+    pub ammo: [i32; WEAPON_COUNT],
+    pub weapon: i32,
+    pub health: i32,
+    pub armor: i32,
+    pub armor_type: ArmorType,
+}
+
+impl Loadout {
+    /// Gauntlet and machinegun; everything else comes from pickups.
+    pub const CLASSIC: Self = Self {
+        weapons: [true, true, false, false, false, false, false, false, false],
+        ammo: [-1, 100, 0, 0, 0, 0, 0, 0, 0],
+        weapon: 1,
+        health: MAX_HEALTH,
+        armor: 0,
+        armor_type: ArmorType::None,
+    };
+
+    /// Every weapon with its default ammo, rocket launcher raised.
+    pub const FULL: Self = Self {
+        weapons: [true; WEAPON_COUNT],
+        ammo: DEFAULT_AMMO,
+        weapon: 4,
+        health: MAX_HEALTH,
+        armor: 0,
+        armor_type: ArmorType::None,
+    };
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::CLASSIC),
+            "full" => Some(Self::FULL),
+            _ => None,
+        }
+    }
+
+    /// Same health and armor, but only `weapon` with infinite ammo.
+    pub fn only(self, weapon: usize) -> Self {
+        let mut loadout = Self {
+            weapons: [false; WEAPON_COUNT],
+            ammo: [0; WEAPON_COUNT],
+            weapon: weapon as i32,
+            ..self
+        };
+        loadout.weapons[weapon] = true;
+        loadout.ammo[weapon] = -1;
+        loadout
+    }
+
+    /// Replace the player's inventory and vitals; powerups are cleared.
+    pub fn apply(&self, player: &mut PlayerState) {
+        player.weapons = self.weapons;
+        player.ammo = self.ammo;
+        player.ready_weapon(self.weapon);
+        player.health = self.health;
+        player.armor = self.armor;
+        player.armor_type = if self.armor > 0 {
+            self.armor_type
+        } else {
+            ArmorType::None
+        };
+        player.clear_powerups();
+    }
+}
@@ -0,0 +1,106 @@
+use crate::ruleset::Ruleset;
+
+const ROCKET: usize = 4;
+const RAIL: usize = 5;
+
+/// Named preset applied on top of a ruleset when a room is created.
+/// Mutators stack; later ones win where they touch the same rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutator {
+    /// Railgun only, one-shot kills, infinite ammo, no items.
+    Instagib,
+    /// Rocket launcher only with infinite ammo, no items.
+    RocketArena,
+    /// A share of damage dealt to others is returned as health.
+    Vampire,
+    LowGravity,
+    /// Own splash damage is ignored; rocket and grenade jumps still push.
+    NoSelfSplash,
+}
+
+impl Mutator {
+    pub const ALL: [Self; 5] = [
+        Self::Instagib,
+        Self::RocketArena,
+        Self::Vampire,
+        Self::LowGravity,
+        Self::NoSelfSplash,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mutator| mutator.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Instagib => "instagib",
+            Self::RocketArena => "rocket-arena",
+            Self::Vampire => "vampire",
+            Self::LowGravity => "low-gravity",
+            Self::NoSelfSplash => "no-self-splash",
+        }
+    }
+
+    pub fn apply(self, rules: &mut Ruleset) {
+        match self {
+            Self::Instagib => {
+                rules.set_loadout(rules.loadout().only(RAIL));
+                rules.damage[RAIL] = 1000.0;
+                rules.items_enabled = false;
+            }
+            Self::RocketArena => {
+                rules.set_loadout(rules.loadout().only(ROCKET));
+                rules.items_enabled = false;
+            }
+            Self::Vampire => rules.vampire = 0.5,
+            Self::LowGravity => {
+                rules.gravity *= 0.4;
+                rules.projectile_gravity *= 0.4;
+            }
+            Self::NoSelfSplash => rules.self_damage_reduction = 0.0,
+        }
+    }
+}
+
+impl Ruleset {
+    pub fn with_mutators(mut self, mutators: &[Mutator]) -> Self {
+        for mutator in mutators {
+            mutator.apply(&mut self);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mutator;
+    use crate::ruleset::Ruleset;
+
+    #[test]
+    fn names_roundtrip() {
+        for mutator in Mutator::ALL {
+            assert_eq!(Mutator::from_name(mutator.name()), Some(mutator));
+        }
+        assert_eq!(Mutator::from_name("moon"), None);
+    }
+
+    #[test]
+    fn mutators_stack() {
+        let rules = Ruleset::DEFAULT.with_mutators(&[Mutator::Instagib, Mutator::LowGravity]);
+
+        assert_eq!(rules.spawn_weapon, 5);
+        assert_eq!(rules.spawn_weapons.iter().filter(|held| **held).count(), 1);
+        assert_eq!(rules.spawn_ammo[5], -1);
+        assert!(!rules.items_enabled);
+        assert!(rules.gravity < Ruleset::DEFAULT.gravity);
+    }
+
+    #[test]
+    fn later_weapon_mutator_wins() {
+        let rules = Ruleset::DEFAULT.with_mutators(&[Mutator::Instagib, Mutator::RocketArena]);
+
+        assert_eq!(rules.spawn_weapon, 4);
+        assert!(!rules.spawn_weapons[5]);
+        assert_eq!(rules.spawn_ammo[5], 0);
+    }
+}
@@ -1,10 +1,12 @@
 use crate::constants::{
-    BFG_LIFETIME_TICKS, BOUNDS_MARGIN, GRENADE_AIR_FRICTION, GRENADE_BOUNCE_FRICTION, GRENADE_FUSE,
-    GRENADE_MAX_FALL_SPEED, GRENADE_MIN_VELOCITY, GRENADE_RISE_DAMPING, HIT_RADIUS_BFG,
-    HIT_RADIUS_GRENADE, HIT_RADIUS_PLASMA, HIT_RADIUS_ROCKET, PROJECTILE_GRAVITY,
-    ROCKET_LIFETIME_TICKS, TILE_H, TILE_W,
+    BOUNDS_MARGIN, GRENADE_AIR_FRICTION, GRENADE_BOUNCE_FRICTION, GRENADE_MAX_FALL_SPEED,
+    GRENADE_MIN_VELOCITY, GRENADE_RISE_DAMPING, HIT_RADIUS_BFG, HIT_RADIUS_GRENADE,
+    HIT_RADIUS_PLASMA, HIT_RADIUS_ROCKET, PROJECTILE_HITBOX_BFG, PROJECTILE_HITBOX_GRENADE,
+    PROJECTILE_HITBOX_PLASMA, PROJECTILE_HITBOX_ROCKET, TILE_H, TILE_W,
 };
+use crate::ruleset::Ruleset;
 use crate::tilemap::TileMap;
+use crate::types::Aabb;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(u8)]
@@ -38,6 +40,20 @@ impl ProjectileKind {
             Self::Bfg => HIT_RADIUS_BFG,
         }
     }
+
+    pub fn hitbox_half(self) -> f32 {
+        match self {
+            Self::Rocket => PROJECTILE_HITBOX_ROCKET,
+            Self::Grenade => PROJECTILE_HITBOX_GRENADE,
+            Self::Plasma => PROJECTILE_HITBOX_PLASMA,
+            Self::Bfg => PROJECTILE_HITBOX_BFG,
+        }
+    }
+
+    /// Whether shots and explosions can set this projectile off early.
+    pub fn is_detonatable(self) -> bool {
+        matches!(self, Self::Rocket | Self::Grenade)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -79,6 +95,16 @@ impl Projectile {
             active: true,
         }
     }
+
+    pub fn hitbox(&self) -> Aabb {
+        let half = self.kind.hitbox_half();
+        Aabb {
+            min_x: self.x - half,
+            max_x: self.x + half,
+            min_y: self.y - half,
+            max_y: self.y + half,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -95,6 +121,7 @@ pub fn step_projectile(
     proj: &mut Projectile,
     map: &impl TileMap,
     bounds: (f32, f32),
+    rules: &Ruleset,
 ) -> Option<Explosion> {
     if !proj.active {
         return None;
@@ -105,7 +132,7 @@ pub fn step_projectile(
     proj.age += 1;
 
     if proj.kind == ProjectileKind::Grenade {
-        apply_grenade_physics(proj);
+        apply_grenade_physics(proj, rules);
     }
 
     let new_x = proj.x + proj.velocity_x;
@@ -123,13 +150,13 @@ pub fn step_projectile(
     }
 
     // Grenade fuse timer
-    if proj.kind == ProjectileKind::Grenade && proj.age > GRENADE_FUSE {
+    if proj.kind == ProjectileKind::Grenade && proj.age > rules.grenade_fuse {
         return Some(explode(proj));
     }
-    if proj.kind == ProjectileKind::Rocket && proj.age > ROCKET_LIFETIME_TICKS {
+    if proj.kind == ProjectileKind::Rocket && proj.age > rules.rocket_lifetime {
         return Some(explode(proj));
     }
-    if proj.kind == ProjectileKind::Bfg && proj.age > BFG_LIFETIME_TICKS {
+    if proj.kind == ProjectileKind::Bfg && proj.age > rules.bfg_lifetime {
         return Some(explode(proj));
     }
 
@@ -143,8 +170,8 @@ pub fn step_projectile(
 }
 
 /// Apply grenade-specific physics (inertia and gravity).
-pub fn apply_grenade_physics(proj: &mut Projectile) {
-    proj.velocity_y += PROJECTILE_GRAVITY;
+pub fn apply_grenade_physics(proj: &mut Projectile, rules: &Ruleset) {
+    proj.velocity_y += rules.projectile_gravity;
     if proj.velocity_y < 0.0 {
         proj.velocity_y /= GRENADE_RISE_DAMPING;
     }
@@ -0,0 +1,584 @@
+use std::fmt;
+
+use crate::constants::{
+    AMMO_BOX, ARMOR_ABSORPTION, ARMOR_CAP, ARMOR_DECAY, ARMOR_DECAY_FLOOR, ARMOR_TIERS,
+    BATTLE_SUIT_FACTOR, BFG_LIFETIME_TICKS, DAMAGE, DECAY_INTERVAL, DROPPED_ITEM_LIFETIME,
+    FIRE_RATE, GAUNTLET_RANGE, GRENADE_FUSE, HASTE_FACTOR, HEALTH_DECAY, HIT_ZONE_DAMAGE,
+    MACHINE_RANGE, MAX_HEALTH, MEGA_HEALTH, PICKUP_AMMO, PLASMA_SPLASH_DMG, PLASMA_SPLASH_PUSH,
+    PLASMA_SPLASH_RADIUS, PLAYER_MAX_VELOCITY_X, PLAYER_VELOCITY_CLAMP, POWERUP_DURATION,
+    PROJECTILE_GRAVITY, PROJECTILE_SPEED, QUAD_DURATION, QUAD_MULTIPLIER, RAIL_PIERCE_FALLOFF,
+    RAIL_RANGE, REGEN_HEALTH, RESPAWN_TIME, ROCKET_LIFETIME_TICKS, SELF_DAMAGE_REDUCTION,
+    SHAFT_RANGE, SHOTGUN_RANGE, SPAWN_PROTECTION, SPLASH_RADIUS, WEAPON_COUNT, WEAPON_LOWER_TIME,
+    WEAPON_PUSH, WEAPON_RAISE_TIME,
+};
+use crate::explosion::KnockbackModel;
+use crate::loadout::Loadout;
+use crate::types::ArmorType;
+
+/// Version byte leading the wire encoding; bump when the field list changes.
+pub const RULESET_WIRE_VERSION: u8 = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RulesetError {
+    /// Line without a `key = value` pair.
+    Syntax {
+        line: usize,
+    },
+    UnknownKey {
+        line: usize,
+        key: String,
+    },
+    InvalidValue {
+        line: usize,
+        key: String,
+    },
+}
+
+impl fmt::Display for RulesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { line } => write!(f, "line {line}: expected `key = value`"),
+            Self::UnknownKey { line, key } => write!(f, "line {line}: unknown rule `{key}`"),
+            Self::InvalidValue { line, key } => write!(f, "line {line}: invalid value for `{key}`"),
+        }
+    }
+}
+
+impl std::error::Error for RulesetError {}
+
+/// A value that can appear in a ruleset file and on the wire.
+trait RuleValue: Sized {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self>;
+    /// Append the value the way `parse_tokens` reads it back.
+    fn write_text(&self, out: &mut String);
+    fn write(&self, out: &mut Vec<u8>);
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self>;
+    /// Whether every number in the value passes `bound`; other values always do.
+    fn within(&self, _bound: fn(f64) -> bool) -> bool {
+        true
+    }
+}
+
+/// Bound for divisors and factors: zero or less breaks the simulation.
+fn positive(value: f64) -> bool {
+    value > 0.0
+}
+
+/// Bound for caps, ranges and radii.
+fn non_negative(value: f64) -> bool {
+    value >= 0.0
+}
+
+fn take<const N: usize>(bytes: &[u8], offset: &mut usize) -> Option<[u8; N]> {
+    let raw = bytes.get(*offset..*offset + N)?;
+    *offset += N;
+    raw.try_into().ok()
+}
+
+impl RuleValue for f32 {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        tokens.next()?.parse().ok().filter(|v: &f32| v.is_finite())
+    }
+    fn write_text(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        Some(f32::from_le_bytes(take(bytes, offset)?)).filter(|v| v.is_finite())
+    }
+    fn within(&self, bound: fn(f64) -> bool) -> bool {
+        bound(f64::from(*self))
+    }
+}
+
+impl RuleValue for i32 {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        tokens.next()?.parse().ok()
+    }
+    fn write_text(&self, out: &mut String) {
+        out.push_str(&self.to_string());
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        Some(i32::from_le_bytes(take(bytes, offset)?))
+    }
+    fn within(&self, bound: fn(f64) -> bool) -> bool {
+        bound(f64::from(*self))
+    }
+}
+
+impl RuleValue for bool {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        match tokens.next()? {
+            "1" | "true" | "on" => Some(true),
+            "0" | "false" | "off" => Some(false),
+            _ => None,
+        }
+    }
+    fn write_text(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let [raw] = take(bytes, offset)?;
+        Some(raw != 0)
+    }
+}
+
+impl RuleValue for KnockbackModel {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        KnockbackModel::from_name(tokens.next()?)
+    }
+    fn write_text(&self, out: &mut String) {
+        out.push_str(self.name());
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.as_u8());
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let [raw] = take(bytes, offset)?;
+        KnockbackModel::from_u8(raw)
+    }
+}
+
+impl RuleValue for ArmorType {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        ArmorType::from_name(tokens.next()?)
+    }
+    fn write_text(&self, out: &mut String) {
+        out.push_str(self.name());
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.as_u8());
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let [raw] = take(bytes, offset)?;
+        ArmorType::from_u8(raw)
+    }
+}
+
+/// Arrays are written as comma-separated values; nested arrays are flattened.
+impl<T: RuleValue + Copy + Default, const N: usize> RuleValue for [T; N] {
+    fn parse_tokens<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut values = [T::default(); N];
+        for value in &mut values {
+            *value = T::parse_tokens(tokens)?;
+        }
+        Some(values)
+    }
+    fn write_text(&self, out: &mut String) {
+        for (idx, value) in self.iter().enumerate() {
+            if idx > 0 {
+                out.push_str(", ");
+            }
+            value.write_text(out);
+        }
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        for value in self {
+            value.write(out);
+        }
+    }
+    fn read(bytes: &[u8], offset: &mut usize) -> Option<Self> {
+        let mut values = [T::default(); N];
+        for value in &mut values {
+            *value = T::read(bytes, offset)?;
+        }
+        Some(values)
+    }
+    fn within(&self, bound: fn(f64) -> bool) -> bool {
+        self.iter().all(|value| value.within(bound))
+    }
+}
+
+/// `name: type = default`, optionally followed by `=> bound` to reject values
+/// outside the bound both when parsing and when decoding.
+macro_rules! define_ruleset {
+    ($($(#[$doc:meta])* $name:ident: $ty:ty = $default:expr $(=> $bound:ident)?,)*) => {
+        /// Gameplay tunables shared by the server simulation and client prediction.
+        /// Defaults mirror the values in `constants`.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct Ruleset {
+            $($(#[$doc])* pub $name: $ty,)*
+        }
+
+        impl Ruleset {
+            pub const DEFAULT: Self = Self {
+                $($name: $default,)*
+            };
+
+            fn set(&mut self, key: &str, value: &str) -> Option<Result<(), ()>> {
+                let mut tokens = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|token| !token.is_empty());
+                match key {
+                    $(stringify!($name) => Some(
+                        RuleValue::parse_tokens(&mut tokens)
+                            .filter(|_| tokens.next().is_none())
+                            $(.filter(|parsed: &$ty| parsed.within($bound)))?
+                            .map(|parsed| self.$name = parsed)
+                            .ok_or(()),
+                    ),)*
+                    _ => None,
+                }
+            }
+
+            /// Every rule as a `key = value` line, in the format `parse` reads.
+            /// Unlike `to_bytes` it survives rules being added or reordered.
+            pub fn to_text(&self) -> String {
+                let mut out = String::new();
+                $(
+                    out.push_str(concat!(stringify!($name), " = "));
+                    self.$name.write_text(&mut out);
+                    out.push('\n');
+                )*
+                out
+            }
+
+            /// Compact little-endian encoding sent to clients in the room state.
+            pub fn to_bytes(&self) -> Vec<u8> {
+                let mut out = vec![RULESET_WIRE_VERSION];
+                $(self.$name.write(&mut out);)*
+                out
+            }
+
+            pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+                if bytes.first() != Some(&RULESET_WIRE_VERSION) {
+                    return None;
+                }
+                let mut offset = 1;
+                Some(Self {
+                    $($name: RuleValue::read(bytes, &mut offset)
+                        $(.filter(|value: &$ty| value.within($bound)))??,)*
+                })
+            }
+        }
+    };
+}
+
+define_ruleset! {
+    /// Downward acceleration added to player velocity every tick.
+    gravity: f32 = 0.056,
+    /// Velocity divisor while sliding on the ground without input.
+    ground_friction: f32 = 1.14 => positive,
+    /// Velocity divisor while airborne without input.
+    air_friction: f32 = 1.025 => positive,
+    run_acceleration: f32 = 0.35,
+    max_velocity_x: f32 = PLAYER_MAX_VELOCITY_X => non_negative,
+    velocity_clamp: f32 = PLAYER_VELOCITY_CLAMP => non_negative,
+    jump_velocity: f32 = 2.9,
+    double_jump_velocity: f32 = 3.0,
+
+    damage: [f32; WEAPON_COUNT] = DAMAGE,
+    fire_rate: [i32; WEAPON_COUNT] = FIRE_RATE,
+    weapon_lower_time: [i32; WEAPON_COUNT] = WEAPON_LOWER_TIME,
+    weapon_raise_time: [i32; WEAPON_COUNT] = WEAPON_RAISE_TIME,
+    splash_radius: [f32; WEAPON_COUNT] = SPLASH_RADIUS => non_negative,
+    weapon_push: [f32; WEAPON_COUNT] = WEAPON_PUSH,
+    projectile_speed: [f32; WEAPON_COUNT] = PROJECTILE_SPEED,
+    pickup_ammo: [i32; WEAPON_COUNT] = PICKUP_AMMO,
+    ammo_box: [i32; WEAPON_COUNT] = AMMO_BOX,
+    /// Head, torso and legs multipliers per weapon; only used with `hit_zones`.
+    hit_zone_damage: [[f32; 3]; WEAPON_COUNT] = HIT_ZONE_DAMAGE,
+    gauntlet_range: f32 = GAUNTLET_RANGE => non_negative,
+    machine_range: f32 = MACHINE_RANGE => non_negative,
+    shotgun_range: f32 = SHOTGUN_RANGE => non_negative,
+    rail_range: f32 = RAIL_RANGE => non_negative,
+    shaft_range: f32 = SHAFT_RANGE => non_negative,
+    rail_pierce_falloff: f32 = RAIL_PIERCE_FALLOFF,
+
+    projectile_gravity: f32 = PROJECTILE_GRAVITY,
+    grenade_fuse: i32 = GRENADE_FUSE,
+    rocket_lifetime: i32 = ROCKET_LIFETIME_TICKS,
+    bfg_lifetime: i32 = BFG_LIFETIME_TICKS,
+    plasma_splash_damage: f32 = PLASMA_SPLASH_DMG,
+    plasma_splash_radius: f32 = PLASMA_SPLASH_RADIUS => non_negative,
+    plasma_splash_push: f32 = PLASMA_SPLASH_PUSH,
+
+    max_health: i32 = MAX_HEALTH => non_negative,
+    mega_health: i32 = MEGA_HEALTH => non_negative,
+    /// Share of damage taken by green, yellow and red armor.
+    armor_absorption: [f32; ARMOR_TIERS] = ARMOR_ABSORPTION,
+    /// Most points each armor tier can hold.
+    armor_cap: [i32; ARMOR_TIERS] = ARMOR_CAP => non_negative,
+    /// Ticks between decay steps for health above `max_health` and armor above
+    /// `armor_decay_floor`. Regeneration pauses health decay.
+    decay_interval: i32 = DECAY_INTERVAL,
+    health_decay: i32 = HEALTH_DECAY,
+    armor_decay: i32 = ARMOR_DECAY,
+    armor_decay_floor: i32 = ARMOR_DECAY_FLOOR,
+    self_damage_reduction: f32 = SELF_DAMAGE_REDUCTION,
+    quad_multiplier: f32 = QUAD_MULTIPLIER,
+    quad_duration: i32 = QUAD_DURATION,
+    regen_duration: i32 = POWERUP_DURATION,
+    /// Health restored every `REGEN_INTERVAL` ticks while regenerating, up to mega health.
+    regen_health: i32 = REGEN_HEALTH,
+    haste_duration: i32 = POWERUP_DURATION,
+    /// Movement speed and fire rate multiplier while hasted.
+    haste_factor: f32 = HASTE_FACTOR => positive,
+    battle_suit_duration: i32 = POWERUP_DURATION,
+    /// Direct damage multiplier while wearing the battle suit; splash is ignored.
+    battle_suit_factor: f32 = BATTLE_SUIT_FACTOR,
+    invisibility_duration: i32 = POWERUP_DURATION,
+    respawn_time: i32 = RESPAWN_TIME,
+    spawn_protection: i32 = SPAWN_PROTECTION,
+    /// Respawning onto another player kills them; otherwise occupied spawn
+    /// points are avoided.
+    telefrag: bool = false,
+    /// Spawn loadout; see `Ruleset::loadout`.
+    spawn_weapons: [bool; WEAPON_COUNT] = Loadout::CLASSIC.weapons,
+    spawn_ammo: [i32; WEAPON_COUNT] = Loadout::CLASSIC.ammo,
+    spawn_weapon: i32 = Loadout::CLASSIC.weapon,
+    spawn_health: i32 = Loadout::CLASSIC.health,
+    spawn_armor: i32 = Loadout::CLASSIC.armor,
+    spawn_armor_type: ArmorType = Loadout::CLASSIC.armor_type,
+    /// Map items spawn and can be picked up.
+    items_enabled: bool = true,
+    /// Dying players drop their held weapon with its remaining ammo.
+    drop_weapon: bool = true,
+    /// Dying players drop active powerups with their remaining time.
+    drop_powerups: bool = true,
+    /// Ticks a dropped weapon or powerup stays before it disappears.
+    dropped_item_lifetime: i32 = DROPPED_ITEM_LIFETIME,
+    /// Share of damage dealt to other players returned to the attacker as health.
+    vampire: f32 = 0.0,
+
+    knockback: KnockbackModel = KnockbackModel::Classic,
+    /// Shots and explosions can destroy rockets, grenades and plasma in flight.
+    projectile_interactions: bool = false,
+    /// Hitscan damage depends on whether the head, torso or legs were hit.
+    hit_zones: bool = false,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Ruleset {
+    /// Parse a ruleset file on top of the defaults.
+    ///
+    /// Each non-empty line is `key = value`; `#` starts a comment. Array rules take
+    /// comma-separated values in weapon order. `loadout = <name>` sets every `spawn_*`
+    /// rule from a named loadout; later lines can still adjust them.
+    pub fn parse(text: &str) -> Result<Self, RulesetError> {
+        let mut rules = Self::DEFAULT;
+        for (idx, raw_line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(RulesetError::Syntax { line: line_no });
+            };
+            let key = key.trim();
+            if key == "loadout" {
+                let Some(loadout) = Loadout::from_name(value.trim()) else {
+                    return Err(RulesetError::InvalidValue {
+                        line: line_no,
+                        key: key.to_string(),
+                    });
+                };
+                rules.set_loadout(loadout);
+                continue;
+            }
+            match rules.set(key, value.trim()) {
+                Some(Ok(())) => {}
+                Some(Err(())) => {
+                    return Err(RulesetError::InvalidValue {
+                        line: line_no,
+                        key: key.to_string(),
+                    })
+                }
+                None => {
+                    return Err(RulesetError::UnknownKey {
+                        line: line_no,
+                        key: key.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// What players hold on join and after respawning.
+    pub fn loadout(&self) -> Loadout {
+        Loadout {
+            weapons: self.spawn_weapons,
+            ammo: self.spawn_ammo,
+            weapon: self.spawn_weapon,
+            health: self.spawn_health,
+            armor: self.spawn_armor,
+            armor_type: self.spawn_armor_type,
+        }
+    }
+
+    pub fn set_loadout(&mut self, loadout: Loadout) {
+        self.spawn_weapons = loadout.weapons;
+        self.spawn_ammo = loadout.ammo;
+        self.spawn_weapon = loadout.weapon;
+        self.spawn_health = loadout.health;
+        self.spawn_armor = loadout.armor;
+        self.spawn_armor_type = loadout.armor_type;
+    }
+
+    pub fn damage_for(&self, weapon_id: i32) -> f32 {
+        weapon_value(&self.damage, weapon_id).unwrap_or(0.0)
+    }
+
+    pub fn fire_rate_for(&self, weapon_id: i32) -> i32 {
+        weapon_value(&self.fire_rate, weapon_id).unwrap_or(0)
+    }
+
+    pub fn lower_time_for(&self, weapon_id: i32) -> i32 {
+        weapon_value(&self.weapon_lower_time, weapon_id).unwrap_or(0)
+    }
+
+    pub fn raise_time_for(&self, weapon_id: i32) -> i32 {
+        weapon_value(&self.weapon_raise_time, weapon_id).unwrap_or(0)
+    }
+
+    pub fn hitscan_range(&self, weapon_id: i32) -> Option<f32> {
+        match weapon_id {
+            1 => Some(self.machine_range),
+            2 => Some(self.shotgun_range),
+            5 => Some(self.rail_range),
+            7 => Some(self.shaft_range),
+            _ => None,
+        }
+    }
+}
+
+fn weapon_value<T: Copy>(values: &[T; WEAPON_COUNT], weapon_id: i32) -> Option<T> {
+    usize::try_from(weapon_id)
+        .ok()
+        .and_then(|idx| values.get(idx).copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ruleset, RulesetError};
+    use crate::explosion::KnockbackModel;
+    use crate::loadout::Loadout;
+
+    #[test]
+    fn parse_overrides_defaults() {
+        let rules = Ruleset::parse(
+            "# low-grav instagib-ish\n\
+             gravity = 0.03\n\
+             knockback = radial   # trailing comment\n\
+             hit_zones = true\n\
+             fire_rate = 20, 5, 50, 40, 40, 60, 5, 3, 10\n",
+        )
+        .expect("ruleset should parse");
+
+        assert_eq!(rules.gravity, 0.03);
+        assert_eq!(rules.knockback, KnockbackModel::Radial);
+        assert!(rules.hit_zones);
+        assert_eq!(rules.fire_rate[5], 60);
+        assert_eq!(rules.damage, Ruleset::DEFAULT.damage);
+    }
+
+    #[test]
+    fn parse_applies_named_loadout_then_overrides() {
+        let rules =
+            Ruleset::parse("loadout = full\nspawn_armor = 50\n").expect("ruleset should parse");
+
+        assert_eq!(
+            rules.loadout(),
+            Loadout {
+                armor: 50,
+                ..Loadout::FULL
+            }
+        );
+        assert!(matches!(
+            Ruleset::parse("loadout = everything"),
+            Err(RulesetError::InvalidValue { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys_and_bad_values() {
+        assert_eq!(
+            Ruleset::parse("gravity = 0.1\nwarp = 9"),
+            Err(RulesetError::UnknownKey {
+                line: 2,
+                key: "warp".to_string()
+            })
+        );
+        assert!(matches!(
+            Ruleset::parse("damage = 1, 2, 3"),
+            Err(RulesetError::InvalidValue { line: 1, .. })
+        ));
+        assert!(matches!(
+            Ruleset::parse("gravity"),
+            Err(RulesetError::Syntax { line: 1 })
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_out_of_range_values() {
+        for (key, value) in [
+            ("gravity", "NaN"),
+            ("max_velocity_x", "inf"),
+            ("haste_factor", "0"),
+            ("ground_friction", "-1.1"),
+            ("air_friction", "0.0"),
+            ("velocity_clamp", "-4"),
+            ("armor_cap", "100, -1, 200"),
+            ("splash_radius", "0, 0, 0, 120, 120, 0, 0, 0, -5"),
+        ] {
+            assert_eq!(
+                Ruleset::parse(&format!("{key} = {value}")),
+                Err(RulesetError::InvalidValue {
+                    line: 1,
+                    key: key.to_string()
+                })
+            );
+        }
+        let rules =
+            Ruleset::parse("velocity_clamp = 0\nmax_health = 0\n").expect("ruleset should parse");
+        assert_eq!((rules.velocity_clamp, rules.max_health), (0.0, 0));
+
+        let stalled = Ruleset {
+            haste_factor: 0.0,
+            ..Ruleset::DEFAULT
+        };
+        assert_eq!(Ruleset::from_bytes(&stalled.to_bytes()), None);
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let rules = Ruleset {
+            max_health: 150,
+            knockback: KnockbackModel::Radial,
+            projectile_interactions: true,
+            ..Ruleset::DEFAULT
+        };
+        let bytes = rules.to_bytes();
+
+        assert_eq!(Ruleset::from_bytes(&bytes), Some(rules));
+        assert_eq!(Ruleset::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+
+    #[test]
+    fn text_roundtrip() {
+        let mut rules = Ruleset {
+            gravity: 0.1 + 0.2,
+            knockback: KnockbackModel::Radial,
+            telefrag: true,
+            ..Ruleset::DEFAULT
+        };
+        rules.set_loadout(Loadout::FULL);
+
+        assert_eq!(Ruleset::parse(&rules.to_text()), Ok(rules));
+    }
+}
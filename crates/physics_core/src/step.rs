@@ -1,13 +1,18 @@
 use crate::constants::{
     CROUCH_HEAD_OFFSET, CROUCH_HEAD_PROBE, GROUND_PROBE, HEAD_PROBE, PLAYER_CROUCH_HALF_H,
-    PLAYER_HALF_H, PLAYER_MAX_VELOCITY_X, PLAYER_VELOCITY_CLAMP, SPEED_JUMP_X, SPEED_JUMP_Y,
-    STAND_HEAD_OFFSET, TILE_H, TILE_W, WALL_PROBE_X_LEFT, WALL_PROBE_X_RIGHT, WALL_SNAP_LEFT,
-    WALL_SNAP_RIGHT,
+    PLAYER_HALF_H, SPEED_JUMP_X, SPEED_JUMP_Y, STAND_HEAD_OFFSET, TILE_H, TILE_W,
+    WALL_PROBE_X_LEFT, WALL_PROBE_X_RIGHT, WALL_SNAP_LEFT, WALL_SNAP_RIGHT,
 };
+use crate::ruleset::Ruleset;
 use crate::tilemap::TileMap;
 use crate::types::{clamp, trunc_i32, PlayerInput, PlayerState};
 
-pub fn step_player<M: TileMap + ?Sized>(player: &mut PlayerState, input: PlayerInput, map: &M) {
+pub fn step_player<M: TileMap + ?Sized>(
+    player: &mut PlayerState,
+    input: PlayerInput,
+    map: &M,
+    rules: &Ruleset,
+) {
     player.key_up = input.key_up;
     player.key_down = input.key_down;
     player.key_left = input.key_left;
@@ -16,12 +21,12 @@ pub fn step_player<M: TileMap + ?Sized>(player: &mut PlayerState, input: PlayerI
     player.prev_x = player.x;
     player.prev_y = player.y;
 
-    player.update();
+    player.update(rules);
     if player.dead {
         return;
     }
 
-    apply_physics(player, map);
+    apply_physics(player, map, rules);
     if player.doublejump_countdown > 0 {
         player.doublejump_countdown -= 1;
     }
@@ -29,9 +34,9 @@ pub fn step_player<M: TileMap + ?Sized>(player: &mut PlayerState, input: PlayerI
         player.velocity_y = 0.0;
     }
 
-    handle_jump(player);
+    handle_jump(player, rules);
     handle_crouch(player);
-    handle_horizontal_movement(player);
+    handle_horizontal_movement(player, rules);
 
     debug_assert!(player.x.is_finite());
     debug_assert!(player.y.is_finite());
@@ -39,11 +44,11 @@ pub fn step_player<M: TileMap + ?Sized>(player: &mut PlayerState, input: PlayerI
     debug_assert!(player.velocity_y.is_finite());
 }
 
-fn apply_physics<M: TileMap + ?Sized>(player: &mut PlayerState, map: &M) {
+fn apply_physics<M: TileMap + ?Sized>(player: &mut PlayerState, map: &M, rules: &Ruleset) {
     let start_x = player.x;
     let start_y = player.y;
 
-    player.velocity_y += 0.056;
+    player.velocity_y += rules.gravity;
     if player.velocity_y > -1.0 && player.velocity_y < 0.0 {
         player.velocity_y /= 1.11;
     }
@@ -53,7 +58,11 @@ fn apply_physics<M: TileMap + ?Sized>(player: &mut PlayerState, map: &M) {
 
     if player.velocity_x.abs() > 0.2 {
         if player.key_left == player.key_right {
-            player.velocity_x /= if player.is_on_ground() { 1.14 } else { 1.025 };
+            player.velocity_x /= if player.is_on_ground() {
+                rules.ground_friction
+            } else {
+                rules.air_friction
+            };
         }
     } else {
         player.velocity_x = 0.0;
@@ -122,17 +131,17 @@ fn apply_physics<M: TileMap + ?Sized>(player: &mut PlayerState, map: &M) {
 
     player.velocity_x = clamp(
         player.velocity_x,
-        -PLAYER_VELOCITY_CLAMP,
-        PLAYER_VELOCITY_CLAMP,
+        -rules.velocity_clamp,
+        rules.velocity_clamp,
     );
     player.velocity_y = clamp(
         player.velocity_y,
-        -PLAYER_VELOCITY_CLAMP,
-        PLAYER_VELOCITY_CLAMP,
+        -rules.velocity_clamp,
+        rules.velocity_clamp,
     );
 }
 
-fn handle_jump(player: &mut PlayerState) {
+fn handle_jump(player: &mut PlayerState, rules: &Ruleset) {
     let keys_changed = player.key_up != player.last_key_up
         || (player.key_left && player.speed_jump_dir != -1)
         || (player.key_right && player.speed_jump_dir != 1);
@@ -150,7 +159,7 @@ fn handle_jump(player: &mut PlayerState) {
 
         if is_double_jump {
             player.doublejump_countdown = 14;
-            player.velocity_y = -3.0;
+            player.velocity_y = -rules.double_jump_velocity;
 
             let total_speed_x = if player.velocity_x != 0.0 {
                 player.velocity_x.abs() + SPEED_JUMP_X[player.speed_jump as usize]
@@ -167,7 +176,7 @@ fn handle_jump(player: &mut PlayerState) {
             if player.doublejump_countdown == 0 {
                 player.doublejump_countdown = 14;
             }
-            player.velocity_y = -2.9 + SPEED_JUMP_Y[player.speed_jump as usize];
+            player.velocity_y = -rules.jump_velocity + SPEED_JUMP_Y[player.speed_jump as usize];
 
             if player.speed_jump < 6 && !player.last_was_jump && player.key_left != player.key_right
             {
@@ -191,12 +200,17 @@ fn handle_crouch(player: &mut PlayerState) {
     }
 }
 
-fn handle_horizontal_movement(player: &mut PlayerState) {
+fn handle_horizontal_movement(player: &mut PlayerState, rules: &Ruleset) {
     if player.key_left == player.key_right {
         return;
     }
 
-    let mut max_vel = PLAYER_MAX_VELOCITY_X;
+    let haste = if player.has_haste() {
+        rules.haste_factor
+    } else {
+        1.0
+    };
+    let mut max_vel = rules.max_velocity_x * haste;
     if player.crouch {
         max_vel -= 1.0;
     }
@@ -208,7 +222,7 @@ fn handle_horizontal_movement(player: &mut PlayerState) {
 
     let abs_vel = player.velocity_x.abs();
     if abs_vel < max_vel {
-        player.velocity_x += sign * 0.35;
+        player.velocity_x += sign * rules.run_acceleration * haste;
     } else if abs_vel > max_vel {
         player.velocity_x = sign * max_vel;
     }
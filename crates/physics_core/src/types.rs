@@ -1,4 +1,6 @@
-use crate::constants::{DEFAULT_AMMO, WEAPON_COUNT};
+use crate::constants::{REGEN_INTERVAL, WEAPON_COUNT};
+use crate::loadout::Loadout;
+use crate::ruleset::Ruleset;
 use crate::tilemap::TileMap;
 
 #[derive(Clone, Copy, Default)]
@@ -17,6 +19,96 @@ pub struct Aabb {
     pub max_y: f32,
 }
 
+/// Armor class worn by a player. Higher tiers absorb a larger share of damage
+/// and hold more points; `None` means no armor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ArmorType {
+    #[default]
+    None = 0,
+    Green = 1,
+    Yellow = 2,
+    Red = 3,
+}
+
+impl ArmorType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Green),
+            2 => Some(Self::Yellow),
+            3 => Some(Self::Red),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "green" => Some(Self::Green),
+            "yellow" => Some(Self::Yellow),
+            "red" => Some(Self::Red),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Green => "green",
+            Self::Yellow => "yellow",
+            Self::Red => "red",
+        }
+    }
+
+    /// Share of incoming damage this tier takes off health.
+    pub fn absorption(self, rules: &Ruleset) -> f32 {
+        match self {
+            Self::None => 0.0,
+            tier => rules.armor_absorption[tier as usize - 1],
+        }
+    }
+
+    pub fn cap(self, rules: &Ruleset) -> i32 {
+        match self {
+            Self::None => 0,
+            tier => rules.armor_cap[tier as usize - 1],
+        }
+    }
+}
+
+/// Weapon cycle. Switching lowers the held weapon once any shot in progress
+/// is over, then raises the requested one; firing needs `Ready`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum WeaponState {
+    #[default]
+    Ready = 0,
+    Firing = 1,
+    Lowering = 2,
+    Raising = 3,
+}
+
+impl WeaponState {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Ready),
+            1 => Some(Self::Firing),
+            2 => Some(Self::Lowering),
+            3 => Some(Self::Raising),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
 #[derive(Clone)]
 pub struct PlayerState {
     pub id: u64,
@@ -40,6 +132,7 @@ pub struct PlayerState {
     pub last_cache_y: i32,
     pub health: i32,
     pub armor: i32,
+    pub armor_type: ArmorType,
     pub dead: bool,
     pub respawn_timer: i32,
     pub spawn_protection: i32,
@@ -47,17 +140,31 @@ pub struct PlayerState {
     pub facing_left: bool,
     pub current_weapon: i32,
     pub fire_cooldown: i32,
+    pub weapon_state: WeaponState,
+    /// Ticks left in the current lowering or raising phase.
+    pub weapon_timer: i32,
+    /// Weapon to bring up next; equals `current_weapon` when no switch is queued.
+    pub pending_weapon: i32,
     pub weapons: [bool; WEAPON_COUNT],
     pub ammo: [i32; WEAPON_COUNT],
     pub quad_damage: bool,
     pub quad_timer: i32,
+    /// Remaining powerup ticks; a powerup is active while its timer is positive.
+    pub regen_timer: i32,
+    pub haste_timer: i32,
+    pub battle_suit_timer: i32,
+    pub invisibility_timer: i32,
+    /// Ticks since the last mega-health / over-armor decay step.
+    pub decay_timer: i32,
     pub last_key_up: bool,
     pub last_was_jump: bool,
     pub speed_jump_dir: i32,
 }
 
 impl PlayerState {
+    /// A fresh player holding the classic loadout.
     pub fn new(id: u64) -> Self {
+        let loadout = Loadout::CLASSIC;
         Self {
             id,
             x: 0.0,
@@ -78,19 +185,28 @@ impl PlayerState {
             cache_brick_crouch_on_head: false,
             last_cache_x: i32::MIN,
             last_cache_y: i32::MIN,
-            health: 100,
-            armor: 0,
+            health: loadout.health,
+            armor: loadout.armor,
+            armor_type: loadout.armor_type,
             dead: false,
             respawn_timer: 0,
             spawn_protection: 0,
             aim_angle: 0.0,
             facing_left: false,
-            current_weapon: 4,
+            current_weapon: loadout.weapon,
             fire_cooldown: 0,
-            weapons: [true; WEAPON_COUNT],
-            ammo: DEFAULT_AMMO,
+            weapon_state: WeaponState::Ready,
+            weapon_timer: 0,
+            pending_weapon: loadout.weapon,
+            weapons: loadout.weapons,
+            ammo: loadout.ammo,
             quad_damage: false,
             quad_timer: 0,
+            regen_timer: 0,
+            haste_timer: 0,
+            battle_suit_timer: 0,
+            invisibility_timer: 0,
+            decay_timer: 0,
             last_key_up: false,
             last_was_jump: false,
             speed_jump_dir: 0,
@@ -111,7 +227,7 @@ impl PlayerState {
         self.update_caches(map);
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, rules: &Ruleset) {
         if self.fire_cooldown > 0 {
             self.fire_cooldown -= 1;
         }
@@ -127,6 +243,136 @@ impl PlayerState {
                 self.quad_damage = false;
             }
         }
+        if self.regen_timer > 0 {
+            self.regen_timer -= 1;
+            if !self.dead
+                && self.regen_timer % REGEN_INTERVAL == 0
+                && self.health < rules.mega_health
+            {
+                self.health = (self.health + rules.regen_health).min(rules.mega_health);
+            }
+        }
+        for timer in [
+            &mut self.haste_timer,
+            &mut self.battle_suit_timer,
+            &mut self.invisibility_timer,
+        ] {
+            if *timer > 0 {
+                *timer -= 1;
+            }
+        }
+        self.update_weapon(rules);
+        self.decay(rules);
+    }
+
+    /// Queue a switch to `weapon`; it comes up after the held one is lowered.
+    pub fn request_weapon(&mut self, weapon: i32) {
+        if usize::try_from(weapon).is_ok_and(|idx| self.weapons.get(idx) == Some(&true)) {
+            self.pending_weapon = weapon;
+        }
+    }
+
+    /// Hold `weapon` immediately, skipping the switch animation.
+    pub fn ready_weapon(&mut self, weapon: i32) {
+        self.current_weapon = weapon;
+        self.pending_weapon = weapon;
+        self.weapon_state = WeaponState::Ready;
+        self.weapon_timer = 0;
+    }
+
+    pub fn weapon_ready(&self) -> bool {
+        self.weapon_state == WeaponState::Ready
+    }
+
+    fn update_weapon(&mut self, rules: &Ruleset) {
+        if self.weapon_timer > 0 {
+            self.weapon_timer -= 1;
+        }
+        if self.weapon_timer > 0
+            || (self.weapon_state == WeaponState::Firing && self.fire_cooldown > 0)
+        {
+            return;
+        }
+        if self.weapon_state == WeaponState::Lowering {
+            self.current_weapon = self.pending_weapon;
+            self.weapon_state = WeaponState::Raising;
+            self.weapon_timer = rules.raise_time_for(self.current_weapon);
+            if self.weapon_timer > 0 {
+                return;
+            }
+        }
+        self.weapon_state = WeaponState::Ready;
+        if self.pending_weapon != self.current_weapon {
+            self.weapon_state = WeaponState::Lowering;
+            self.weapon_timer = rules.lower_time_for(self.current_weapon);
+        }
+    }
+
+    /// Mega health and armor above the floor tick back down. Part of
+    /// `update`; hosts that only track health and armor can call it alone.
+    pub fn decay(&mut self, rules: &Ruleset) {
+        let health_over = self.health > rules.max_health && self.regen_timer <= 0;
+        let armor_over = self.armor > rules.armor_decay_floor;
+        if self.dead || !(health_over || armor_over) {
+            self.decay_timer = 0;
+            return;
+        }
+        self.decay_timer += 1;
+        if self.decay_timer < rules.decay_interval {
+            return;
+        }
+        self.decay_timer = 0;
+        if health_over {
+            self.health = (self.health - rules.health_decay).max(rules.max_health);
+        }
+        if armor_over {
+            self.armor = (self.armor - rules.armor_decay).max(rules.armor_decay_floor);
+        }
+    }
+
+    /// Armor pickup. The result keeps the pickup's tier, so a lower tier only
+    /// replaces a higher one when it would protect more (points × absorption).
+    /// Returns false when the pickup is refused.
+    pub fn give_armor(&mut self, armor_type: ArmorType, amount: i32, rules: &Ruleset) -> bool {
+        let armor = (self.armor + amount).min(armor_type.cap(rules));
+        let protection = armor as f32 * armor_type.absorption(rules);
+        if protection <= self.armor as f32 * self.armor_type.absorption(rules) {
+            return false;
+        }
+        self.armor = armor;
+        self.armor_type = armor_type;
+        true
+    }
+
+    /// Let armor soak its share of `damage`; returns what is left for health.
+    pub fn absorb_damage(&mut self, damage: f32, rules: &Ruleset) -> f32 {
+        if self.armor <= 0 {
+            return damage;
+        }
+        let armor_damage = (damage * self.armor_type.absorption(rules)).floor() as i32;
+        let absorbed = armor_damage.min(self.armor);
+        self.armor -= absorbed;
+        if self.armor <= 0 {
+            self.armor_type = ArmorType::None;
+        }
+        damage - absorbed as f32
+    }
+
+    pub fn has_haste(&self) -> bool {
+        self.haste_timer > 0
+    }
+
+    pub fn has_battle_suit(&self) -> bool {
+        self.battle_suit_timer > 0
+    }
+
+    pub fn clear_powerups(&mut self) {
+        self.quad_damage = false;
+        self.quad_timer = 0;
+        self.regen_timer = 0;
+        self.haste_timer = 0;
+        self.battle_suit_timer = 0;
+        self.invisibility_timer = 0;
     }
 
     fn update_caches<M: TileMap + ?Sized>(&mut self, map: &M) {
@@ -202,6 +448,105 @@ pub fn player_hitbox(x: f32, y: f32, crouch: bool, padding: f32) -> Aabb {
     }
 }
 
+/// Body part struck by a hit. `Body` is used when zones are not resolved
+/// (splash damage, melee, or rule sets without hit zones).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HitZone {
+    #[default]
+    Body = 0,
+    Head = 1,
+    Torso = 2,
+    Legs = 3,
+}
+
+impl HitZone {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Body),
+            1 => Some(Self::Head),
+            2 => Some(Self::Torso),
+            3 => Some(Self::Legs),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// Damage multiplier for `weapon_id` hitting this zone.
+    pub fn damage_multiplier(self, weapon_id: i32, rules: &crate::ruleset::Ruleset) -> f32 {
+        let Some(row) = usize::try_from(weapon_id)
+            .ok()
+            .and_then(|idx| rules.hit_zone_damage.get(idx))
+        else {
+            return 1.0;
+        };
+        match self {
+            Self::Body => 1.0,
+            Self::Head => row[0],
+            Self::Torso => row[1],
+            Self::Legs => row[2],
+        }
+    }
+}
+
+/// Splits the player hitbox into head, torso and legs for the given stance.
+/// Padding only grows the outer edges so the zones stay contiguous.
+pub fn player_hit_zones(x: f32, y: f32, crouch: bool, padding: f32) -> [(HitZone, Aabb); 3] {
+    use crate::constants::{
+        HIT_ZONE_HEAD_BOTTOM_CROUCH, HIT_ZONE_HEAD_BOTTOM_STAND, HIT_ZONE_LEGS_TOP,
+    };
+    let full = player_hitbox(x, y, crouch, padding);
+    let head_bottom = y + if crouch {
+        HIT_ZONE_HEAD_BOTTOM_CROUCH
+    } else {
+        HIT_ZONE_HEAD_BOTTOM_STAND
+    };
+    let legs_top = y + HIT_ZONE_LEGS_TOP;
+    [
+        (
+            HitZone::Head,
+            Aabb {
+                max_y: head_bottom,
+                ..full
+            },
+        ),
+        (
+            HitZone::Torso,
+            Aabb {
+                min_y: head_bottom,
+                max_y: legs_top,
+                ..full
+            },
+        ),
+        (
+            HitZone::Legs,
+            Aabb {
+                min_y: legs_top,
+                ..full
+            },
+        ),
+    ]
+}
+
+/// Like `segment_aabb_t` against the player hitbox, but also reports the zone
+/// the segment enters first.
+pub fn segment_hit_zone(
+    (x0, y0): (f32, f32),
+    (x1, y1): (f32, f32),
+    x: f32,
+    y: f32,
+    crouch: bool,
+    padding: f32,
+) -> Option<(f32, HitZone)> {
+    player_hit_zones(x, y, crouch, padding)
+        .into_iter()
+        .filter_map(|(zone, aabb)| segment_aabb_t(x0, y0, x1, y1, aabb).map(|t| (t, zone)))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
 pub fn expand_aabb(aabb: Aabb, padding: f32) -> Aabb {
     Aabb {
         min_x: aabb.min_x - padding,
@@ -239,3 +584,115 @@ pub fn segment_aabb_t(x0: f32, y0: f32, x1: f32, y1: f32, aabb: Aabb) -> Option<
     }
     Some(t_min.clamp(0.0, 1.0))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{segment_hit_zone, ArmorType, HitZone, PlayerState, WeaponState};
+    use crate::constants::REGEN_INTERVAL;
+    use crate::ruleset::Ruleset;
+
+    #[test]
+    fn segment_hit_zone_reports_first_zone_entered() {
+        let head = segment_hit_zone((-100.0, -18.0), (100.0, -18.0), 0.0, 0.0, false, 0.0);
+        let legs = segment_hit_zone((-100.0, 15.0), (100.0, 15.0), 0.0, 0.0, false, 0.0);
+        let from_above = segment_hit_zone((0.0, -100.0), (0.0, 100.0), 0.0, 0.0, false, 0.0);
+
+        assert_eq!(head.map(|hit| hit.1), Some(HitZone::Head));
+        assert_eq!(legs.map(|hit| hit.1), Some(HitZone::Legs));
+        assert_eq!(from_above.map(|hit| hit.1), Some(HitZone::Head));
+    }
+
+    #[test]
+    fn crouching_lowers_the_head_zone() {
+        let hit = segment_hit_zone((-100.0, -5.0), (100.0, -5.0), 0.0, 0.0, true, 0.0);
+        assert_eq!(hit.map(|hit| hit.1), Some(HitZone::Head));
+
+        let miss = segment_hit_zone((-100.0, -18.0), (100.0, -18.0), 0.0, 0.0, true, 0.0);
+        assert!(miss.is_none());
+    }
+
+    #[test]
+    fn regeneration_heals_each_interval_until_it_runs_out() {
+        let rules = Ruleset::DEFAULT;
+        let mut player = PlayerState::new(1);
+        player.health = 50;
+        player.regen_timer = REGEN_INTERVAL * 2;
+
+        for _ in 0..REGEN_INTERVAL * 3 {
+            player.update(&rules);
+        }
+
+        assert_eq!(player.health, 50 + rules.regen_health * 2);
+        assert_eq!(player.regen_timer, 0);
+    }
+
+    #[test]
+    fn mega_health_and_over_armor_decay_to_their_caps() {
+        let rules = Ruleset::DEFAULT;
+        let mut player = PlayerState::new(1);
+        player.health = rules.max_health + 2;
+        player.armor = rules.armor_decay_floor + 5;
+
+        for _ in 0..rules.decay_interval * 3 {
+            player.update(&rules);
+        }
+
+        assert_eq!(player.health, rules.max_health);
+        assert_eq!(player.armor, rules.armor_decay_floor + 2);
+    }
+
+    #[test]
+    fn lower_armor_tier_only_replaces_when_it_protects_more() {
+        let rules = Ruleset::DEFAULT;
+        let mut player = PlayerState::new(1);
+        assert!(player.give_armor(ArmorType::Red, 200, &rules));
+
+        assert!(!player.give_armor(ArmorType::Green, 50, &rules));
+        assert_eq!(player.armor_type, ArmorType::Red);
+
+        player.armor = 10;
+        assert!(player.give_armor(ArmorType::Green, 50, &rules));
+        assert_eq!((player.armor_type, player.armor), (ArmorType::Green, 60));
+
+        let left = player.absorb_damage(200.0, &rules);
+        assert_eq!((player.armor_type, player.armor), (ArmorType::None, 0));
+        assert_eq!(left, 140.0);
+    }
+
+    #[test]
+    fn weapon_switch_lowers_then_raises_before_ready() {
+        let rules = Ruleset::DEFAULT;
+        let mut player = PlayerState::new(1);
+        player.request_weapon(0);
+
+        player.update(&rules);
+        assert_eq!(player.weapon_state, WeaponState::Lowering);
+        for _ in 0..rules.lower_time_for(1) {
+            assert_eq!(player.current_weapon, 1);
+            player.update(&rules);
+        }
+        assert_eq!(player.current_weapon, 0);
+        assert_eq!(player.weapon_state, WeaponState::Raising);
+        for _ in 0..rules.raise_time_for(0) {
+            assert!(!player.weapon_ready());
+            player.update(&rules);
+        }
+        assert!(player.weapon_ready());
+    }
+
+    #[test]
+    fn weapon_switch_waits_for_shot_in_progress() {
+        let rules = Ruleset::DEFAULT;
+        let mut player = PlayerState::new(1);
+        player.weapon_state = WeaponState::Firing;
+        player.fire_cooldown = 3;
+        player.request_weapon(0);
+
+        for _ in 0..2 {
+            player.update(&rules);
+            assert_eq!(player.weapon_state, WeaponState::Firing);
+        }
+        player.update(&rules);
+        assert_eq!(player.weapon_state, WeaponState::Lowering);
+    }
+}
@@ -1,8 +1,6 @@
-use crate::constants::{
-    GRENADE_LOFT, MACHINE_RANGE, PROJECTILE_OFFSET, PROJECTILE_SPEED, RAIL_RANGE, SHAFT_RANGE,
-    SHOTGUN_RANGE, TILE_H, TILE_W,
-};
+use crate::constants::{GRENADE_LOFT, PROJECTILE_OFFSET, TILE_H, TILE_W};
 use crate::projectile::ProjectileKind;
+use crate::ruleset::Ruleset;
 use crate::tilemap::TileMap;
 
 #[derive(Clone, Copy, Debug)]
@@ -21,6 +19,7 @@ pub fn compute_projectile_spawn(
     origin_x: f32,
     origin_y: f32,
     aim_angle: f32,
+    rules: &Ruleset,
 ) -> Option<ProjectileSpawn> {
     let kind = match weapon_id {
         3 => ProjectileKind::Grenade,
@@ -31,7 +30,7 @@ pub fn compute_projectile_spawn(
     };
 
     let idx = usize::try_from(weapon_id).ok()?;
-    let speed = *PROJECTILE_SPEED.get(idx)?;
+    let speed = *rules.projectile_speed.get(idx)?;
     let offset = *PROJECTILE_OFFSET.get(idx)?;
 
     let cos = aim_angle.cos();
@@ -55,16 +54,6 @@ pub fn compute_projectile_spawn(
     })
 }
 
-pub fn hitscan_range(weapon_id: i32) -> Option<f32> {
-    match weapon_id {
-        1 => Some(MACHINE_RANGE),
-        2 => Some(SHOTGUN_RANGE),
-        5 => Some(RAIL_RANGE),
-        7 => Some(SHAFT_RANGE),
-        _ => None,
-    }
-}
-
 #[derive(Clone, Copy, Debug)]
 pub struct RayTraceResult {
     pub hit_wall: bool,
@@ -1,12 +1,15 @@
+use std::cell::RefCell;
+
 use wasm_bindgen::prelude::*;
 
 use physics_core::constants;
-use physics_core::explosion::{apply_knockback, apply_knockback_with_scale};
+use physics_core::explosion::{apply_knockback_with_rules, KnockbackModel};
 use physics_core::projectile::{calculate_bounds, step_projectile, Projectile, ProjectileKind};
 use physics_core::step::step_player;
 use physics_core::tilemap::FlatTileMap;
-use physics_core::types::{PlayerInput, PlayerState};
+use physics_core::types::{ArmorType, PlayerInput, PlayerState};
 use physics_core::weapon;
+use physics_core::Ruleset;
 
 pub use binary_protocol::wasm::{
     wasm_decode_server_message, wasm_encode_hello, wasm_encode_input, wasm_encode_join_room,
@@ -15,6 +18,31 @@ pub use binary_protocol::wasm::{
 
 const HOST_EXPORT_LEN: usize = 12;
 
+thread_local! {
+    // Ruleset of the current room; prediction and the getters below read from it.
+    static RULES: RefCell<Ruleset> = const { RefCell::new(Ruleset::DEFAULT) };
+}
+
+fn with_rules<T>(f: impl FnOnce(&Ruleset) -> T) -> T {
+    RULES.with(|rules| f(&rules.borrow()))
+}
+
+/// Install the ruleset bytes from the room state message.
+/// Returns false (keeping the previous ruleset) if the bytes are malformed.
+#[wasm_bindgen]
+pub fn wasm_set_ruleset(bytes: &[u8]) -> bool {
+    let Some(rules) = Ruleset::from_bytes(bytes) else {
+        return false;
+    };
+    RULES.with(|current| *current.borrow_mut() = rules);
+    true
+}
+
+#[wasm_bindgen]
+pub fn wasm_reset_ruleset() {
+    RULES.with(|current| *current.borrow_mut() = Ruleset::DEFAULT);
+}
+
 #[wasm_bindgen]
 pub struct WasmMap {
     inner: FlatTileMap,
@@ -51,6 +79,7 @@ impl WasmPlayerState {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn import_host_state(
         &mut self,
         x: f32,
@@ -82,6 +111,12 @@ impl WasmPlayerState {
         }
     }
 
+    /// Haste speeds up movement. The host owns the powerup timer, so keep it
+    /// running until told otherwise.
+    pub fn set_haste(&mut self, active: bool) {
+        self.inner.haste_timer = if active { i32::MAX } else { 0 };
+    }
+
     pub fn export_to_host(&self, out: &mut [f32]) {
         if out.len() < HOST_EXPORT_LEN {
             return;
@@ -131,6 +166,7 @@ impl WasmPlayerInput {
 }
 
 #[wasm_bindgen]
+#[derive(Default)]
 pub struct WasmPhysicsKernel;
 
 #[wasm_bindgen]
@@ -141,11 +177,12 @@ impl WasmPhysicsKernel {
     }
 
     pub fn step_player(&self, state: &mut WasmPlayerState, input: &WasmPlayerInput, map: &WasmMap) {
-        step_player(&mut state.inner, input.inner, &map.inner);
+        with_rules(|rules| step_player(&mut state.inner, input.inner, &map.inner, rules));
     }
 }
 
 #[wasm_bindgen]
+#[derive(Default)]
 pub struct WasmRayTracer {
     hit_wall: bool,
     x: f32,
@@ -157,12 +194,7 @@ pub struct WasmRayTracer {
 impl WasmRayTracer {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self {
-            hit_wall: false,
-            x: 0.0,
-            y: 0.0,
-            distance: 0.0,
-        }
+        Self::default()
     }
 
     pub fn trace(
@@ -198,6 +230,7 @@ impl WasmRayTracer {
 }
 
 #[wasm_bindgen]
+#[derive(Default)]
 pub struct WasmWeaponKernel {
     has_spawn: bool,
     spawn_kind: u8,
@@ -211,14 +244,7 @@ pub struct WasmWeaponKernel {
 impl WasmWeaponKernel {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Self {
-        Self {
-            has_spawn: false,
-            spawn_kind: 0,
-            spawn_x: 0.0,
-            spawn_y: 0.0,
-            spawn_velocity_x: 0.0,
-            spawn_velocity_y: 0.0,
-        }
+        Self::default()
     }
 
     pub fn compute_projectile_spawn(
@@ -228,9 +254,9 @@ impl WasmWeaponKernel {
         origin_y: f32,
         aim_angle: f32,
     ) -> bool {
-        if let Some(spawn) =
-            weapon::compute_projectile_spawn(weapon_id, origin_x, origin_y, aim_angle)
-        {
+        if let Some(spawn) = with_rules(|rules| {
+            weapon::compute_projectile_spawn(weapon_id, origin_x, origin_y, aim_angle, rules)
+        }) {
             self.has_spawn = true;
             self.spawn_kind = spawn.kind.as_u8();
             self.spawn_x = spawn.x;
@@ -244,7 +270,7 @@ impl WasmWeaponKernel {
     }
 
     pub fn hitscan_range(&self, weapon_id: i32) -> f32 {
-        weapon::hitscan_range(weapon_id).unwrap_or(-1.0)
+        with_rules(|rules| rules.hitscan_range(weapon_id)).unwrap_or(-1.0)
     }
 
     pub fn spawn_kind(&self) -> u8 {
@@ -304,7 +330,9 @@ impl WasmProjectile {
         self.exploded = false;
 
         let bounds = calculate_bounds(cols, rows);
-        if let Some(explosion) = step_projectile(&mut self.inner, &map.inner, bounds) {
+        let explosion =
+            with_rules(|rules| step_projectile(&mut self.inner, &map.inner, bounds, rules));
+        if let Some(explosion) = explosion {
             self.exploded = true;
             self.explosion_x = explosion.x;
             self.explosion_y = explosion.y;
@@ -416,6 +444,32 @@ impl WasmProjectile {
     }
 }
 
+/// One tick of `PlayerState::decay` for a player whose health lives on the
+/// host. `out` receives health, armor and the decay timer.
+#[wasm_bindgen]
+pub fn wasm_decay_player(
+    health: i32,
+    armor: i32,
+    regen_timer: i32,
+    decay_timer: i32,
+    dead: bool,
+    out: &mut [i32],
+) {
+    if out.len() < 3 {
+        return;
+    }
+    let mut state = PlayerState::new(0);
+    state.health = health;
+    state.armor = armor;
+    state.regen_timer = regen_timer;
+    state.decay_timer = decay_timer;
+    state.dead = dead;
+    with_rules(|rules| state.decay(rules));
+    out[0] = state.health;
+    out[1] = state.armor;
+    out[2] = state.decay_timer;
+}
+
 /// Apply explosion knockback to a player state.
 /// Returns the damage falloff (0.0-1.0) if player was in radius, -1.0 otherwise.
 #[wasm_bindgen]
@@ -433,7 +487,8 @@ pub fn wasm_apply_knockback(
         kind,
         owner_id,
     };
-    apply_knockback(&mut player.inner, &explosion).unwrap_or(-1.0)
+    with_rules(|rules| apply_knockback_with_rules(&mut player.inner, &explosion, 1.0, rules))
+        .unwrap_or(-1.0)
 }
 
 #[wasm_bindgen]
@@ -452,19 +507,47 @@ pub fn wasm_apply_knockback_scaled(
         kind,
         owner_id,
     };
-    apply_knockback_with_scale(&mut player.inner, &explosion, push_scale).unwrap_or(-1.0)
+    with_rules(|rules| apply_knockback_with_rules(&mut player.inner, &explosion, push_scale, rules))
+        .unwrap_or(-1.0)
+}
+
+/// Same as `wasm_apply_knockback_scaled` with an explicit knockback model
+/// (0 = classic, 1 = radial) overriding the active ruleset's. Unknown values fall back to classic.
+#[wasm_bindgen]
+pub fn wasm_apply_knockback_model(
+    player: &mut WasmPlayerState,
+    explosion_x: f32,
+    explosion_y: f32,
+    explosion_kind: u8,
+    owner_id: u64,
+    push_scale: f32,
+    model: u8,
+) -> f32 {
+    let kind = ProjectileKind::from_u8(explosion_kind).unwrap_or(ProjectileKind::Rocket);
+    let model = KnockbackModel::from_u8(model).unwrap_or_default();
+    let explosion = physics_core::projectile::Explosion {
+        x: explosion_x,
+        y: explosion_y,
+        kind,
+        owner_id,
+    };
+    let rules = with_rules(|rules| Ruleset {
+        knockback: model,
+        ..rules.clone()
+    });
+    apply_knockback_with_rules(&mut player.inner, &explosion, push_scale, &rules).unwrap_or(-1.0)
 }
 
 #[wasm_bindgen]
 pub fn get_explosion_base_damage(explosion_kind: u8) -> f32 {
     let kind = ProjectileKind::from_u8(explosion_kind).unwrap_or(ProjectileKind::Rocket);
-    physics_core::explosion::base_damage(kind)
+    with_rules(|rules| physics_core::explosion::base_damage(kind, rules))
 }
 
 // Constants getters for JS
 #[wasm_bindgen]
 pub fn get_projectile_gravity() -> f32 {
-    constants::PROJECTILE_GRAVITY
+    with_rules(|rules| rules.projectile_gravity)
 }
 
 #[wasm_bindgen]
@@ -474,7 +557,7 @@ pub fn get_tick_millis() -> u32 {
 
 #[wasm_bindgen]
 pub fn get_grenade_fuse() -> i32 {
-    constants::GRENADE_FUSE
+    with_rules(|rules| rules.grenade_fuse)
 }
 
 #[wasm_bindgen]
@@ -524,18 +607,18 @@ pub fn get_explosion_radius() -> f32 {
 
 #[wasm_bindgen]
 pub fn get_plasma_splash_damage() -> f32 {
-    constants::PLASMA_SPLASH_DMG
+    with_rules(|rules| rules.plasma_splash_damage)
 }
 
 #[wasm_bindgen]
 #[inline(never)]
 pub fn get_plasma_splash_radius() -> f32 {
-    constants::PLASMA_SPLASH_RADIUS
+    with_rules(|rules| rules.plasma_splash_radius)
 }
 
 #[wasm_bindgen]
 pub fn get_plasma_splash_push() -> f32 {
-    constants::PLASMA_SPLASH_PUSH
+    with_rules(|rules| rules.plasma_splash_push)
 }
 
 #[wasm_bindgen]
@@ -555,7 +638,7 @@ pub fn get_shotgun_spread() -> f32 {
 
 #[wasm_bindgen]
 pub fn get_shotgun_range() -> f32 {
-    constants::SHOTGUN_RANGE
+    with_rules(|rules| rules.shotgun_range)
 }
 
 #[wasm_bindgen]
@@ -570,22 +653,22 @@ pub fn get_shotgun_bonus_max() -> f32 {
 
 #[wasm_bindgen]
 pub fn get_gauntlet_range() -> f32 {
-    constants::GAUNTLET_RANGE
+    with_rules(|rules| rules.gauntlet_range)
 }
 
 #[wasm_bindgen]
 pub fn get_shaft_range() -> f32 {
-    constants::SHAFT_RANGE
+    with_rules(|rules| rules.shaft_range)
 }
 
 #[wasm_bindgen]
 pub fn get_machine_range() -> f32 {
-    constants::MACHINE_RANGE
+    with_rules(|rules| rules.machine_range)
 }
 
 #[wasm_bindgen]
 pub fn get_rail_range() -> f32 {
-    constants::RAIL_RANGE
+    with_rules(|rules| rules.rail_range)
 }
 
 #[wasm_bindgen]
@@ -616,58 +699,73 @@ pub fn get_projectile_aabb_radius_scale() -> f32 {
 
 #[wasm_bindgen]
 pub fn get_damage(weapon_id: u8) -> f32 {
-    constants::DAMAGE
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0.0)
+    with_rules(|rules| rules.damage.get(weapon_id as usize).copied()).unwrap_or(0.0)
 }
 
+/// Ammo the current ruleset's spawn loadout starts with.
 #[wasm_bindgen]
 pub fn get_default_ammo(weapon_id: u8) -> i32 {
-    constants::DEFAULT_AMMO
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0)
+    with_rules(|rules| rules.spawn_ammo.get(weapon_id as usize).copied()).unwrap_or(0)
 }
 
 #[wasm_bindgen]
 pub fn get_pickup_ammo(weapon_id: u8) -> i32 {
-    constants::PICKUP_AMMO
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0)
+    with_rules(|rules| rules.pickup_ammo.get(weapon_id as usize).copied()).unwrap_or(0)
+}
+
+#[wasm_bindgen]
+pub fn get_ammo_box(weapon_id: u8) -> i32 {
+    with_rules(|rules| rules.ammo_box.get(weapon_id as usize).copied()).unwrap_or(0)
+}
+
+#[wasm_bindgen]
+pub fn get_regen_duration() -> i32 {
+    with_rules(|rules| rules.regen_duration)
+}
+
+#[wasm_bindgen]
+pub fn get_haste_duration() -> i32 {
+    with_rules(|rules| rules.haste_duration)
+}
+
+#[wasm_bindgen]
+pub fn get_battle_suit_duration() -> i32 {
+    with_rules(|rules| rules.battle_suit_duration)
+}
+
+#[wasm_bindgen]
+pub fn get_invisibility_duration() -> i32 {
+    with_rules(|rules| rules.invisibility_duration)
 }
 
 #[wasm_bindgen]
 pub fn get_weapon_push(weapon_id: u8) -> f32 {
-    constants::WEAPON_PUSH
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0.0)
+    with_rules(|rules| rules.weapon_push.get(weapon_id as usize).copied()).unwrap_or(0.0)
 }
 
 #[wasm_bindgen]
 pub fn get_splash_radius(weapon_id: u8) -> f32 {
-    constants::SPLASH_RADIUS
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0.0)
+    with_rules(|rules| rules.splash_radius.get(weapon_id as usize).copied()).unwrap_or(0.0)
 }
 
 #[wasm_bindgen]
 pub fn get_projectile_speed(weapon_id: u8) -> f32 {
-    constants::PROJECTILE_SPEED
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(0.0)
+    with_rules(|rules| rules.projectile_speed.get(weapon_id as usize).copied()).unwrap_or(0.0)
 }
 
 #[wasm_bindgen]
 pub fn get_fire_rate(weapon_id: u8) -> i32 {
-    constants::FIRE_RATE
-        .get(weapon_id as usize)
-        .copied()
-        .unwrap_or(50)
+    with_rules(|rules| rules.fire_rate.get(weapon_id as usize).copied()).unwrap_or(50)
+}
+
+#[wasm_bindgen]
+pub fn get_weapon_lower_time(weapon_id: u8) -> i32 {
+    with_rules(|rules| rules.lower_time_for(weapon_id as i32))
+}
+
+#[wasm_bindgen]
+pub fn get_weapon_raise_time(weapon_id: u8) -> i32 {
+    with_rules(|rules| rules.raise_time_for(weapon_id as i32))
 }
 
 #[wasm_bindgen]
@@ -753,49 +851,69 @@ pub fn get_pickup_radius() -> f32 {
 #[wasm_bindgen]
 #[inline(never)]
 pub fn get_max_health() -> i32 {
-    constants::MAX_HEALTH
+    with_rules(|rules| rules.max_health)
 }
 
 #[wasm_bindgen]
-pub fn get_max_armor() -> i32 {
-    constants::MAX_ARMOR
+pub fn get_armor_cap(armor_type: u8) -> i32 {
+    ArmorType::from_u8(armor_type).map_or(0, |tier| with_rules(|rules| tier.cap(rules)))
 }
 
 #[wasm_bindgen]
 #[inline(never)]
 pub fn get_mega_health() -> i32 {
-    constants::MEGA_HEALTH
+    with_rules(|rules| rules.mega_health)
+}
+
+#[wasm_bindgen]
+pub fn get_decay_interval() -> i32 {
+    with_rules(|rules| rules.decay_interval)
+}
+
+#[wasm_bindgen]
+pub fn get_health_decay() -> i32 {
+    with_rules(|rules| rules.health_decay)
+}
+
+#[wasm_bindgen]
+pub fn get_armor_decay() -> i32 {
+    with_rules(|rules| rules.armor_decay)
+}
+
+#[wasm_bindgen]
+pub fn get_armor_decay_floor() -> i32 {
+    with_rules(|rules| rules.armor_decay_floor)
 }
 
 #[wasm_bindgen]
-pub fn get_armor_absorption() -> f32 {
-    constants::ARMOR_ABSORPTION
+pub fn get_armor_absorption(armor_type: u8) -> f32 {
+    ArmorType::from_u8(armor_type).map_or(0.0, |tier| with_rules(|rules| tier.absorption(rules)))
 }
 
 #[wasm_bindgen]
 #[inline(never)]
 pub fn get_self_damage_reduction() -> f32 {
-    constants::SELF_DAMAGE_REDUCTION
+    with_rules(|rules| rules.self_damage_reduction)
 }
 
 #[wasm_bindgen]
 pub fn get_quad_multiplier() -> f32 {
-    constants::QUAD_MULTIPLIER
+    with_rules(|rules| rules.quad_multiplier)
 }
 
 #[wasm_bindgen]
 pub fn get_quad_duration() -> i32 {
-    constants::QUAD_DURATION
+    with_rules(|rules| rules.quad_duration)
 }
 
 #[wasm_bindgen]
 pub fn get_respawn_time() -> i32 {
-    constants::RESPAWN_TIME
+    with_rules(|rules| rules.respawn_time)
 }
 
 #[wasm_bindgen]
 pub fn get_spawn_protection() -> i32 {
-    constants::SPAWN_PROTECTION
+    with_rules(|rules| rules.spawn_protection)
 }
 
 /// Returns [min_x, max_x, min_y, max_y] of the player hitbox with optional padding.
@@ -812,6 +930,7 @@ pub fn wasm_player_hitbox(x: f32, y: f32, crouch: bool, padding: f32, out: &mut
 
 /// Segment vs AABB intersection. Returns t in [0,1] or -1.0 if no hit.
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn wasm_segment_aabb_t(
     x0: f32,
     y0: f32,
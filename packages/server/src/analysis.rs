@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::binary::EffectEvent;
+use crate::constants::{SHOTGUN_PELLETS, WEAPON_COUNT};
+use crate::demo::{Demo, DemoRecord};
+use crate::game::WeaponId;
+use crate::physics::PlayerState;
+
+/// Per-player totals over a replayed demo.
+#[derive(Clone, Debug, Default)]
+pub struct PlayerSummary {
+    pub player_id: u64,
+    pub username: String,
+    /// Kills of other players; suicides only count as deaths.
+    pub frags: u32,
+    pub deaths: u32,
+    /// Damage dealt to other players.
+    pub damage_given: i64,
+    /// Damage received from anyone, including yourself.
+    pub damage_taken: i64,
+    /// Indexed by `WeaponId`. Every shotgun pellet counts as a shot.
+    pub shots: [u32; WEAPON_COUNT],
+    /// Damage events against other players, so a rail through two bodies or
+    /// a rocket splashing two players counts twice.
+    pub hits: [u32; WEAPON_COUNT],
+    /// Pickups keyed by map item token.
+    pub items: BTreeMap<char, u32>,
+    pub quad_ticks: u32,
+}
+
+impl PlayerSummary {
+    pub fn accuracy(&self, weapon: WeaponId) -> Option<f32> {
+        let idx = weapon as usize;
+        (self.shots[idx] > 0).then(|| self.hits[idx] as f32 / self.shots[idx] as f32)
+    }
+
+    pub fn items_taken(&self) -> u32 {
+        self.items.values().sum()
+    }
+}
+
+/// One player's state at the end of a tick.
+#[derive(Clone, Debug, Serialize)]
+pub struct TimelineRow {
+    pub tick: u64,
+    pub player_id: u64,
+    pub x: f32,
+    pub y: f32,
+    pub health: i32,
+    pub armor: i32,
+    pub weapon: i32,
+    pub dead: bool,
+    pub quad: bool,
+}
+
+/// Collects statistics tick by tick while a `DemoReplay` runs.
+pub struct DemoAnalysis {
+    players: BTreeMap<u64, PlayerSummary>,
+    timeline: Option<Vec<TimelineRow>>,
+    ticks: u64,
+}
+
+impl DemoAnalysis {
+    /// Pass `keep_timeline` to also record every player's state each tick.
+    pub fn new(demo: &Demo, keep_timeline: bool) -> Self {
+        let mut players = BTreeMap::new();
+        for record in &demo.records {
+            if let DemoRecord::Join {
+                player_id,
+                username,
+            } = record
+            {
+                players.insert(
+                    *player_id,
+                    PlayerSummary {
+                        player_id: *player_id,
+                        username: username.clone(),
+                        ..PlayerSummary::default()
+                    },
+                );
+            }
+        }
+        Self {
+            players,
+            timeline: keep_timeline.then(Vec::new),
+            ticks: 0,
+        }
+    }
+
+    pub fn observe(&mut self, tick: u64, states: &[PlayerState], events: &[EffectEvent]) {
+        self.ticks = tick;
+        for event in events {
+            self.observe_event(event);
+        }
+        for state in states {
+            if state.quad_damage {
+                self.player(state.id).quad_ticks += 1;
+            }
+        }
+        if let Some(timeline) = &mut self.timeline {
+            timeline.extend(states.iter().map(|state| TimelineRow {
+                tick,
+                player_id: state.id,
+                x: state.x,
+                y: state.y,
+                health: state.health,
+                armor: state.armor,
+                weapon: state.current_weapon,
+                dead: state.dead,
+                quad: state.quad_damage,
+            }));
+        }
+    }
+
+    fn observe_event(&mut self, event: &EffectEvent) {
+        match *event {
+            EffectEvent::WeaponFired {
+                player_id,
+                weapon_id,
+            } => {
+                let Ok(weapon) = WeaponId::try_from(weapon_id) else {
+                    return;
+                };
+                let shots = if weapon == WeaponId::Shotgun {
+                    SHOTGUN_PELLETS as u32
+                } else {
+                    1
+                };
+                self.player(player_id).shots[weapon as usize] += shots;
+            }
+            EffectEvent::Damage {
+                attacker_id,
+                target_id,
+                amount,
+                killed,
+                weapon_id,
+                ..
+            } => {
+                let target = self.player(target_id);
+                target.damage_taken += i64::from(amount);
+                if killed {
+                    target.deaths += 1;
+                }
+                if attacker_id == target_id {
+                    return;
+                }
+                let attacker = self.player(attacker_id);
+                attacker.damage_given += i64::from(amount);
+                if killed {
+                    attacker.frags += 1;
+                }
+                if let Ok(weapon) = WeaponId::try_from(weapon_id) {
+                    attacker.hits[weapon as usize] += 1;
+                }
+            }
+            EffectEvent::ItemPickup {
+                player_id, kind, ..
+            } => {
+                *self
+                    .player(player_id)
+                    .items
+                    .entry(char::from(kind))
+                    .or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn player(&mut self, player_id: u64) -> &mut PlayerSummary {
+        self.players
+            .entry(player_id)
+            .or_insert_with(|| PlayerSummary {
+                player_id,
+                ..PlayerSummary::default()
+            })
+    }
+
+    /// Last simulated tick.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = &PlayerSummary> {
+        self.players.values()
+    }
+
+    pub fn timeline(&self) -> &[TimelineRow] {
+        self.timeline.as_deref().unwrap_or_default()
+    }
+}
+
+pub fn write_timeline_csv(rows: &[TimelineRow], mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "tick,player_id,x,y,health,armor,weapon,dead,quad")?;
+    for row in rows {
+        writeln!(
+            out,
+            "{},{},{:.2},{:.2},{},{},{},{},{}",
+            row.tick,
+            row.player_id,
+            row.x,
+            row.y,
+            row.health,
+            row.armor,
+            row.weapon,
+            row.dead,
+            row.quad
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_timeline_json(rows: &[TimelineRow], out: impl Write) -> io::Result<()> {
+    serde_json::to_writer(out, rows).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::DemoAnalysis;
+    use crate::binary::EffectEvent;
+    use crate::constants::TICK_MILLIS;
+    use crate::demo::{Demo, DemoHeader, DemoRecord};
+    use crate::game::WeaponId;
+    use crate::map::GameMap;
+    use crate::physics::PlayerState;
+    use crate::room::DemoReplay;
+    use physics_core::Ruleset;
+
+    fn demo() -> Demo {
+        Demo {
+            header: DemoHeader {
+                room_name: "test".to_string(),
+                map_name: "test".to_string(),
+                map_hash: 0,
+                seed: 0,
+                tick_millis: TICK_MILLIS,
+                max_players: 2,
+                rules: Ruleset::DEFAULT,
+            },
+            records: vec![
+                DemoRecord::Join {
+                    player_id: 1,
+                    username: "alice".to_string(),
+                },
+                DemoRecord::Join {
+                    player_id: 2,
+                    username: "bob".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn credits_frags_damage_and_accuracy() {
+        let mut analysis = DemoAnalysis::new(&demo(), true);
+        let mut states = [PlayerState::new(1), PlayerState::new(2)];
+        states[0].quad_damage = true;
+        let fire = EffectEvent::WeaponFired {
+            player_id: 1,
+            weapon_id: WeaponId::Rail as i32,
+        };
+        let damage = |target_id, amount, killed| EffectEvent::Damage {
+            attacker_id: 1,
+            target_id,
+            amount,
+            killed,
+            hit_zone: 0,
+            weapon_id: WeaponId::Rail as i32,
+        };
+
+        analysis.observe(1, &states, &[fire.clone(), damage(2, 80, false)]);
+        analysis.observe(2, &states, std::slice::from_ref(&fire));
+        analysis.observe(
+            3,
+            &states,
+            &[fire, damage(2, 80, true), damage(1, 20, false)],
+        );
+
+        let players: Vec<_> = analysis.players().collect();
+        let (alice, bob) = (players[0], players[1]);
+        assert_eq!(alice.username, "alice");
+        assert_eq!((alice.frags, alice.deaths), (1, 0));
+        assert_eq!((bob.frags, bob.deaths), (0, 1));
+        assert_eq!(alice.damage_given, 160);
+        assert_eq!(alice.damage_taken, 20);
+        assert_eq!(bob.damage_taken, 160);
+        assert_eq!(alice.hits[WeaponId::Rail as usize], 2);
+        assert_eq!(alice.accuracy(WeaponId::Rail), Some(2.0 / 3.0));
+        assert_eq!(alice.accuracy(WeaponId::Rocket), None);
+        assert_eq!(alice.quad_ticks, 3);
+        assert_eq!(analysis.timeline().len(), 6);
+    }
+
+    /// A short machinegun duel recorded on the shipped dm2 map. Changes to the
+    /// demo format or the simulation that break existing recordings fail here.
+    #[test]
+    fn replays_checked_in_demo() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let demo = Demo::load(&manifest_dir.join("tests/fixtures/duel.demo"))
+            .expect("fixture should decode");
+        assert_eq!(demo.header.room_name, "duel");
+        assert_eq!(demo.header.rules, Ruleset::DEFAULT);
+        let map = GameMap::load(&manifest_dir.join("../../apps/client/public/maps"), "dm2")
+            .expect("dm2 should load");
+        assert!(demo.check_map(&map).is_ok());
+
+        let mut analysis = DemoAnalysis::new(&demo, false);
+        let mut replay = DemoReplay::new(demo.clone(), map);
+        while replay.step() {
+            analysis.observe(replay.tick(), replay.player_states(), replay.events());
+        }
+
+        assert_eq!(analysis.ticks(), demo.tick_count());
+        for player in analysis.players() {
+            assert_eq!(player.shots[WeaponId::Machine as usize], 50);
+            assert_eq!(player.hits[WeaponId::Machine as usize], 5);
+            assert_eq!((player.damage_given, player.damage_taken), (35, 35));
+            assert_eq!((player.frags, player.deaths), (0, 0));
+        }
+        let health: Vec<i32> = replay.player_states().iter().map(|s| s.health).collect();
+        assert_eq!(health, [65, 65]);
+    }
+}
@@ -0,0 +1,138 @@
+#![forbid(unsafe_code)]
+#![deny(rust_2018_idioms)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::panic)]
+
+//! Replays a recorded demo through the room simulation without networking
+//! and prints a per-player summary.
+//!
+//! Usage: demo_player <file.demo> [--maps DIR] [--timeline FILE]
+//!
+//! `--timeline` writes every player's state for every tick, as JSON when the
+//! file ends in `.json` and as CSV otherwise.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use raster_arena_server::analysis::{write_timeline_csv, write_timeline_json, DemoAnalysis};
+use raster_arena_server::constants::DEFAULT_MAP_DIR;
+use raster_arena_server::demo::Demo;
+use raster_arena_server::game::WeaponId;
+use raster_arena_server::map::GameMap;
+use raster_arena_server::room::DemoReplay;
+
+struct Args {
+    demo: PathBuf,
+    map_dir: PathBuf,
+    timeline: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut demo = None;
+    let mut map_dir = std::env::var("MAP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_MAP_DIR));
+    let mut timeline = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--maps" => map_dir = args.next().ok_or("--maps needs a directory")?.into(),
+            "--timeline" => timeline = Some(args.next().ok_or("--timeline needs a file")?.into()),
+            _ if demo.is_none() && !arg.starts_with("--") => demo = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    Ok(Args {
+        demo: demo.ok_or("no demo file given")?,
+        map_dir,
+        timeline,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let demo = Demo::load(&args.demo)
+        .map_err(|err| format!("failed to read {}: {err}", args.demo.display()))?;
+    let header = demo.header.clone();
+    let map = GameMap::load(&args.map_dir, &header.map_name)
+        .map_err(|err| format!("failed to load map '{}': {err}", header.map_name))?;
+    demo.check_map(&map)
+        .map_err(|err| format!("cannot replay {}: {err}", args.demo.display()))?;
+
+    let mut analysis = DemoAnalysis::new(&demo, args.timeline.is_some());
+    let mut replay = DemoReplay::new(demo, map);
+    while replay.step() {
+        analysis.observe(replay.tick(), replay.player_states(), replay.events());
+    }
+
+    let tick_secs = header.tick_millis as f32 / 1000.0;
+    println!(
+        "room '{}' on {}: {} ticks ({:.1}s)",
+        header.room_name,
+        header.map_name,
+        analysis.ticks(),
+        analysis.ticks() as f32 * tick_secs
+    );
+    println!(
+        "{:<20} {:>6} {:>6} {:>8} {:>8} {:>6} {:>7}",
+        "player", "frags", "deaths", "dmg_out", "dmg_in", "items", "quad_s"
+    );
+    for player in analysis.players() {
+        println!(
+            "{:<20} {:>6} {:>6} {:>8} {:>8} {:>6} {:>7.1}",
+            format!("{} ({})", player.username, player.player_id),
+            player.frags,
+            player.deaths,
+            player.damage_given,
+            player.damage_taken,
+            player.items_taken(),
+            player.quad_ticks as f32 * tick_secs
+        );
+        for weapon in WeaponId::ALL {
+            let Some(accuracy) = player.accuracy(weapon) else {
+                continue;
+            };
+            let idx = weapon as usize;
+            println!(
+                "    {:<12} {:>5}/{:<5} {:>5.1}%",
+                weapon.name(),
+                player.hits[idx],
+                player.shots[idx],
+                accuracy * 100.0
+            );
+        }
+        if !player.items.is_empty() {
+            let items: Vec<String> = player
+                .items
+                .iter()
+                .map(|(token, count)| format!("{token}:{count}"))
+                .collect();
+            println!("    items        {}", items.join(" "));
+        }
+    }
+
+    if let Some(path) = &args.timeline {
+        let file = File::create(path)
+            .map_err(|err| format!("failed to create {}: {err}", path.display()))?;
+        let out = BufWriter::new(file);
+        let written = if path.extension().is_some_and(|ext| ext == "json") {
+            write_timeline_json(analysis.timeline(), out)
+        } else {
+            write_timeline_csv(analysis.timeline(), out)
+        };
+        written.map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = parse_args().and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("demo_player: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
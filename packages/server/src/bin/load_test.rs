@@ -0,0 +1,583 @@
+#![forbid(unsafe_code)]
+#![deny(rust_2018_idioms)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::panic)]
+
+//! Simulates many players against a server and reports how it holds up.
+//!
+//! Usage: load_test [--clients N] [--rooms N] [--seconds N] [--map NAME]
+//!                  [--ws URL | --udp ADDR | --maps DIR]
+//!
+//! Built only with the `load-test` feature:
+//! `cargo run --features load-test --bin load_test -- --clients 64`.
+//!
+//! Without `--ws` or `--udp` the sessions run in-process against a fresh
+//! `RoomManager`, loading maps from `--maps`. Each bot joins room
+//! `load-<i % rooms>`, sends 60 Hz input (wandering movement, aim and fire
+//! bursts) and pings once a second.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use physics_core::Ruleset;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use binary_protocol::{
+    encode_hello, encode_input, encode_join_room, encode_ping, MSG_JOIN_REJECTED, MSG_PONG,
+    MSG_SNAPSHOT,
+};
+use raster_arena_server::binary::{ClientMsg, JoinRole};
+use raster_arena_server::constants::{DEFAULT_MAP_DIR, DEFAULT_MAP_NAME};
+use raster_arena_server::resume::ResumeSlots;
+use raster_arena_server::room_manager::RoomManager;
+use raster_arena_server::session::{run_session, AppState, MemoryClient, MemoryTransport};
+use raster_arena_server::tick::MissedTickPolicy;
+use raster_arena_server::udp::{
+    decode_udp_packet, encode_udp_close, encode_udp_connect, encode_udp_data, encode_udp_hello,
+    Cookie, SessionTag, UdpPacket,
+};
+
+const INPUT_INTERVAL: Duration = Duration::from_micros(16_667);
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+const CONNECT_STAGGER: Duration = Duration::from_millis(10);
+
+enum Target {
+    InProcess { map_dir: PathBuf },
+    Ws(String),
+    Udp(SocketAddr),
+}
+
+struct Args {
+    clients: usize,
+    rooms: usize,
+    duration: Duration,
+    map: String,
+    target: Target,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut clients = 16;
+    let mut rooms = 2;
+    let mut seconds = 30;
+    let mut map = DEFAULT_MAP_NAME.to_string();
+    let mut map_dir = std::env::var("MAP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_MAP_DIR));
+    let mut target = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--clients" => clients = parse_count(&value()?)?,
+            "--rooms" => rooms = parse_count(&value()?)?,
+            "--seconds" => seconds = parse_count(&value()?)? as u64,
+            "--map" => map = value()?,
+            "--maps" => map_dir = value()?.into(),
+            "--ws" => target = Some(Target::Ws(value()?)),
+            "--udp" => {
+                let addr = value()?;
+                let addr = addr
+                    .parse()
+                    .map_err(|err| format!("bad --udp address '{addr}': {err}"))?;
+                target = Some(Target::Udp(addr));
+            }
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    Ok(Args {
+        clients,
+        rooms,
+        duration: Duration::from_secs(seconds),
+        map,
+        target: target.unwrap_or(Target::InProcess { map_dir }),
+    })
+}
+
+fn parse_count(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("expected a positive number, got '{value}'")),
+    }
+}
+
+/// A bot's connection, whichever transport it uses.
+enum Link {
+    Memory(MemoryClient),
+    Ws(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    Udp {
+        socket: UdpSocket,
+        tag: SessionTag,
+        /// The welcome, already read during the handshake.
+        welcome: Option<Bytes>,
+        buf: Vec<u8>,
+    },
+}
+
+impl Link {
+    async fn connect(target: &Target, state: Option<&Arc<AppState>>) -> Result<Self, String> {
+        match (target, state) {
+            (Target::InProcess { .. }, Some(state)) => {
+                let (transport, client) = MemoryTransport::pair(256);
+                let state = Arc::clone(state);
+                tokio::spawn(async move { run_session(&state, transport, None, None).await });
+                Ok(Link::Memory(client))
+            }
+            (Target::Ws(url), _) => {
+                let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+                    .await
+                    .map_err(|err| format!("websocket connect failed: {err}"))?;
+                Ok(Link::Ws(Box::new(socket)))
+            }
+            (Target::Udp(addr), _) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")
+                    .await
+                    .map_err(|err| format!("udp bind failed: {err}"))?;
+                socket
+                    .connect(addr)
+                    .await
+                    .map_err(|err| format!("udp connect failed: {err}"))?;
+                let (tag, welcome) = udp_handshake(&socket).await?;
+                Ok(Link::Udp {
+                    socket,
+                    tag,
+                    welcome: Some(welcome),
+                    buf: vec![0; 65536],
+                })
+            }
+            (Target::InProcess { .. }, None) => Err("in-process target without state".into()),
+        }
+    }
+
+    async fn send(&mut self, msg: ClientMsg) -> bool {
+        match self {
+            Link::Memory(client) => client.tx.send(msg).await.is_ok(),
+            Link::Ws(socket) => socket.send(Message::Binary(encode(&msg))).await.is_ok(),
+            Link::Udp { socket, tag, .. } => {
+                let packet = encode_udp_data(tag, &encode(&msg));
+                socket.send(&packet).await.is_ok()
+            }
+        }
+    }
+
+    /// The next server message, or `None` once the server has dropped us.
+    async fn recv(&mut self) -> Option<Bytes> {
+        match self {
+            Link::Memory(client) => client.rx.recv().await,
+            Link::Ws(socket) => loop {
+                match socket.next().await? {
+                    Ok(Message::Binary(data)) => return Some(Bytes::from(data)),
+                    Ok(Message::Close(_)) | Err(_) => return None,
+                    Ok(_) => {}
+                }
+            },
+            Link::Udp {
+                socket,
+                tag,
+                welcome,
+                buf,
+            } => loop {
+                if let Some(welcome) = welcome.take() {
+                    return Some(welcome);
+                }
+                let len = socket.recv(buf).await.ok()?;
+                match decode_udp_packet(&buf[..len]) {
+                    Some(UdpPacket::Data { tag: got, payload }) if got == *tag => {
+                        return Some(Bytes::copy_from_slice(payload))
+                    }
+                    Some(UdpPacket::Close { tag: got }) if got == *tag => return None,
+                    _ => {}
+                }
+            },
+        }
+    }
+
+    async fn close(self) {
+        match self {
+            Link::Memory(_) => {}
+            Link::Ws(mut socket) => {
+                let _ = socket.close().await;
+            }
+            Link::Udp { socket, tag, .. } => {
+                let _ = socket.send(&encode_udp_close(&tag)).await;
+            }
+        }
+    }
+}
+
+/// HELLO until a COOKIE comes back, then CONNECT with it until the welcome
+/// arrives as the first DATA, tagged like every later packet of the session.
+/// Any of these datagrams can be lost, so each step is retried.
+async fn udp_handshake(socket: &UdpSocket) -> Result<(SessionTag, Bytes), String> {
+    let mut buf = vec![0u8; 65536];
+    let mut cookie = None;
+    for _ in 0..5 {
+        let packet = match &cookie {
+            None => encode_udp_hello(),
+            Some(cookie) => encode_udp_connect(cookie, None, None),
+        };
+        let _ = socket.send(&packet).await;
+        let Ok(Ok(len)) = tokio::time::timeout(Duration::from_secs(1), socket.recv(&mut buf)).await
+        else {
+            continue;
+        };
+        let tag = cookie.as_ref().map(Cookie::session_tag);
+        match decode_udp_packet(&buf[..len]) {
+            Some(UdpPacket::Cookie(issued)) if cookie.is_none() => cookie = Some(issued),
+            Some(UdpPacket::Data { tag: got, payload }) if Some(got) == tag => {
+                return Ok((got, Bytes::copy_from_slice(payload)));
+            }
+            Some(UdpPacket::Close { tag: got }) if Some(got) == tag => {
+                return Err("udp connect rejected".into());
+            }
+            _ => {}
+        }
+    }
+    Err("udp handshake timed out".into())
+}
+
+fn encode(msg: &ClientMsg) -> Vec<u8> {
+    match msg {
+        ClientMsg::Hello { username } => encode_hello(username),
+        ClientMsg::JoinRoom {
+            room_id,
+            map,
+            ruleset,
+            role,
+        } => encode_join_room(
+            room_id.as_deref().unwrap_or(""),
+            map.as_deref().unwrap_or(""),
+            ruleset.as_deref().unwrap_or(""),
+            *role,
+        ),
+        ClientMsg::Input {
+            seq,
+            key_up,
+            key_down,
+            key_left,
+            key_right,
+            mouse_down,
+            weapon_switch,
+            weapon_scroll,
+            aim_angle,
+            facing_left,
+            fire_seq,
+            switch_seq,
+            switch_weapon,
+            // The encoder always writes the edges.
+            latched: _,
+        } => encode_input(
+            *seq,
+            *aim_angle,
+            *key_up,
+            *key_down,
+            *key_left,
+            *key_right,
+            *mouse_down,
+            *facing_left,
+            *weapon_switch as i8,
+            *weapon_scroll as i8,
+            *fire_seq,
+            *switch_seq,
+            *switch_weapon as i8,
+        ),
+        ClientMsg::Ping { client_time_ms } => encode_ping(*client_time_ms),
+        // Bots never watch demos.
+        ClientMsg::WatchDemo { .. } | ClientMsg::DemoControl { .. } => Vec::new(),
+    }
+}
+
+/// Roughly how a person plays: hold a direction for a while, hop now and
+/// then, sweep the aim and fire in bursts.
+struct InputPattern {
+    rng: ChaCha8Rng,
+    direction: i8,
+    hold_ticks: u32,
+    jumping: bool,
+    aim_angle: f32,
+    aim_speed: f32,
+    burst_ticks: u32,
+    fire_seq: u64,
+}
+
+impl InputPattern {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            direction: 0,
+            hold_ticks: 0,
+            jumping: false,
+            aim_angle: 0.0,
+            aim_speed: 0.0,
+            burst_ticks: 0,
+            fire_seq: 0,
+        }
+    }
+
+    fn next(&mut self, seq: u64) -> ClientMsg {
+        if self.hold_ticks == 0 {
+            self.direction = self.rng.gen_range(-1..=1);
+            self.hold_ticks = self.rng.gen_range(12..90);
+            self.jumping = self.rng.gen_bool(0.3);
+            self.aim_speed = self.rng.gen_range(-0.08..0.08);
+        }
+        self.hold_ticks -= 1;
+        self.aim_angle = (self.aim_angle + self.aim_speed).rem_euclid(std::f32::consts::TAU);
+
+        if self.burst_ticks == 0 && self.rng.gen_bool(0.02) {
+            self.burst_ticks = self.rng.gen_range(10..40);
+            self.fire_seq = seq;
+        }
+        let firing = self.burst_ticks > 0;
+        self.burst_ticks = self.burst_ticks.saturating_sub(1);
+
+        ClientMsg::Input {
+            seq,
+            key_up: self.jumping,
+            key_down: false,
+            key_left: self.direction < 0,
+            key_right: self.direction > 0,
+            mouse_down: firing,
+            weapon_switch: -1,
+            weapon_scroll: 0,
+            aim_angle: self.aim_angle,
+            facing_left: self.aim_angle > std::f32::consts::FRAC_PI_2
+                && self.aim_angle < 3.0 * std::f32::consts::FRAC_PI_2,
+            fire_seq: self.fire_seq,
+            switch_seq: 0,
+            switch_weapon: -1,
+            latched: true,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BotStats {
+    connected: bool,
+    dropped: bool,
+    rejected: bool,
+    inputs_sent: u64,
+    messages: u64,
+    bytes: u64,
+    snapshots: u64,
+    /// Round trips in microseconds.
+    rtts: Vec<u64>,
+}
+
+async fn run_bot(
+    index: usize,
+    args: Arc<Args>,
+    state: Option<Arc<AppState>>,
+    started: Instant,
+) -> BotStats {
+    let mut stats = BotStats::default();
+    let mut link = match Link::connect(&args.target, state.as_ref()).await {
+        Ok(link) => link,
+        Err(err) => {
+            eprintln!("bot{index}: {err}");
+            stats.dropped = true;
+            return stats;
+        }
+    };
+    stats.connected = true;
+
+    let join = [
+        ClientMsg::Hello {
+            username: format!("bot{index}"),
+        },
+        ClientMsg::JoinRoom {
+            room_id: Some(format!("load-{}", index % args.rooms)),
+            map: Some(args.map.clone()),
+            ruleset: None,
+            role: JoinRole::Player,
+        },
+    ];
+    for msg in join {
+        if !link.send(msg).await {
+            stats.dropped = true;
+            return stats;
+        }
+    }
+
+    let mut pattern = InputPattern::new(index as u64);
+    let mut seq = 0;
+    let mut input_timer = tokio::time::interval(INPUT_INTERVAL);
+    input_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut ping_timer = tokio::time::interval(PING_INTERVAL);
+    let deadline = tokio::time::sleep(args.duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            _ = input_timer.tick() => {
+                seq += 1;
+                if !link.send(pattern.next(seq)).await {
+                    stats.dropped = true;
+                    break;
+                }
+                stats.inputs_sent += 1;
+            }
+            _ = ping_timer.tick() => {
+                // The server echoes this back untouched, so microseconds work.
+                let client_time_ms = started.elapsed().as_micros() as u64;
+                if !link.send(ClientMsg::Ping { client_time_ms }).await {
+                    stats.dropped = true;
+                    break;
+                }
+            }
+            msg = link.recv() => {
+                let Some(msg) = msg else {
+                    stats.dropped = true;
+                    break;
+                };
+                stats.messages += 1;
+                stats.bytes += msg.len() as u64;
+                match msg.first().copied() {
+                    Some(MSG_SNAPSHOT) => stats.snapshots += 1,
+                    Some(MSG_PONG) if msg.len() >= 9 => {
+                        let mut sent = [0u8; 8];
+                        sent.copy_from_slice(&msg[1..9]);
+                        let sent = u64::from_le_bytes(sent);
+                        let now = started.elapsed().as_micros() as u64;
+                        stats.rtts.push(now.saturating_sub(sent));
+                    }
+                    Some(MSG_JOIN_REJECTED) => {
+                        stats.rejected = true;
+                        stats.dropped = true;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    link.close().await;
+    stats
+}
+
+fn in_process_state(map_dir: &Path, args: &Args) -> Arc<AppState> {
+    let players_per_room = args.clients.div_ceil(args.rooms);
+    Arc::new(AppState {
+        room_manager: Arc::new(RoomManager::new(
+            Instant::now(),
+            args.rooms,
+            players_per_room,
+        )),
+        next_player_id: AtomicU64::new(1),
+        map_dir: map_dir.to_path_buf(),
+        ruleset_dir: PathBuf::new(),
+        default_ruleset: Ruleset::default(),
+        demo_dir: None,
+        started_at: Instant::now(),
+        max_connections_per_ip: usize::MAX,
+        max_message_bytes: 65536,
+        max_players_per_room: players_per_room,
+        max_spectators_per_room: 0,
+        missed_tick_policy: MissedTickPolicy::default(),
+        ip_connections: Mutex::new(HashMap::new()),
+        resume_slots: Mutex::new(ResumeSlots::default()),
+        game_secret: None,
+    })
+}
+
+fn percentile(sorted: &[u64], pct: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let idx = (sorted.len() * pct / 100).min(sorted.len() - 1);
+    sorted[idx] as f64 / 1000.0
+}
+
+/// Rates are per second of each bot's run, which excludes connecting.
+fn report(args: &Args, bots: &[BotStats]) {
+    let secs = args.duration.as_secs_f64().max(f64::EPSILON);
+    let connected = bots.iter().filter(|bot| bot.connected).count();
+    let dropped = bots.iter().filter(|bot| bot.dropped).count();
+    let rejected = bots.iter().filter(|bot| bot.rejected).count();
+    let inputs: u64 = bots.iter().map(|bot| bot.inputs_sent).sum();
+    let messages: u64 = bots.iter().map(|bot| bot.messages).sum();
+    let bytes: u64 = bots.iter().map(|bot| bot.bytes).sum();
+    let snapshots: u64 = bots.iter().map(|bot| bot.snapshots).sum();
+    let mut rtts: Vec<u64> = bots
+        .iter()
+        .flat_map(|bot| bot.rtts.iter().copied())
+        .collect();
+    rtts.sort_unstable();
+
+    println!(
+        "{} clients in {} rooms for {:.1}s: {connected} connected, {dropped} dropped ({rejected} rejected)",
+        args.clients, args.rooms, secs
+    );
+    println!("inputs   {:>10.0}/s sent", inputs as f64 / secs);
+    println!(
+        "received {:>10.0} msg/s {:>10.1} KiB/s",
+        messages as f64 / secs,
+        bytes as f64 / 1024.0 / secs
+    );
+    println!(
+        "snapshots {:>9.1}/s per client",
+        snapshots as f64 / secs / connected.max(1) as f64
+    );
+    println!(
+        "rtt ms   p50 {:.2}  p90 {:.2}  p99 {:.2}  max {:.2}  ({} pongs)",
+        percentile(&rtts, 50),
+        percentile(&rtts, 90),
+        percentile(&rtts, 99),
+        rtts.last().map_or(0.0, |&max| max as f64 / 1000.0),
+        rtts.len()
+    );
+}
+
+async fn run(args: Args) -> Result<(), String> {
+    let state = match &args.target {
+        Target::InProcess { map_dir } => Some(in_process_state(map_dir, &args)),
+        Target::Ws(_) | Target::Udp(_) => None,
+    };
+    let args = Arc::new(args);
+    let started = Instant::now();
+    let mut bots = Vec::with_capacity(args.clients);
+    for index in 0..args.clients {
+        bots.push(tokio::spawn(run_bot(
+            index,
+            Arc::clone(&args),
+            state.clone(),
+            started,
+        )));
+        tokio::time::sleep(CONNECT_STAGGER).await;
+    }
+
+    let mut results = Vec::with_capacity(bots.len());
+    for bot in bots {
+        results.push(bot.await.map_err(|err| format!("bot task failed: {err}"))?);
+    }
+    report(&args, &results);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let result = match parse_args() {
+        Ok(args) => run(args).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("load_test: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -3,12 +3,12 @@ use bytes::{BufMut, Bytes, BytesMut};
 use crate::constants::SNAPSHOT_BUFFER_RING;
 
 pub use binary_protocol::{
-    decode_client_message, encode_join_rejected, encode_player_joined,
-    encode_player_left, encode_pong, encode_welcome, ClientMsg, EffectEvent,
-    ItemSnapshot, PlayerSnapshot, ProjectileSnapshot,
+    decode_client_message, encode_demo_state, encode_join_rejected, encode_player_joined,
+    encode_player_left, encode_pong, encode_welcome, restamp_snapshot, ClientMsg, DemoControl,
+    DroppedItemSnapshot, EffectEvent, ItemSnapshot, JoinRole, PlayerSnapshot, ProjectileSnapshot,
 };
 
-use binary_protocol::{write_event, write_player_record, MSG_SNAPSHOT};
+use binary_protocol::{write_dropped_item_record, write_event, write_player_record, MSG_SNAPSHOT};
 
 pub struct SnapshotEncoder {
     buffers: Vec<BytesMut>,
@@ -28,12 +28,14 @@ impl SnapshotEncoder {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn encode_snapshot(
         &mut self,
         tick: u64,
         server_time_ms: u64,
         players: &[PlayerSnapshot],
         items: &[ItemSnapshot],
+        dropped: &[DroppedItemSnapshot],
         projectiles: &[ProjectileSnapshot],
         events: &[EffectEvent],
     ) -> Bytes {
@@ -50,11 +52,13 @@ impl SnapshotEncoder {
         let item_count = items.len().min(255) as u8;
         let projectile_count = projectiles.len().min(u16::MAX as usize) as u16;
         let event_count = events.len().min(255) as u8;
+        let dropped_count = dropped.len().min(255) as u8;
 
         buffer.put_u8(player_count);
         buffer.put_u8(item_count);
         buffer.put_u16_le(projectile_count);
         buffer.put_u8(event_count);
+        buffer.put_u8(dropped_count);
 
         for snapshot in players {
             write_player_record(buffer, snapshot);
@@ -69,6 +73,10 @@ impl SnapshotEncoder {
             buffer.put_i16_le(item.respawn_timer);
         }
 
+        for item in dropped.iter().take(dropped_count as usize) {
+            write_dropped_item_record(buffer, item);
+        }
+
         for proj in projectiles {
             buffer.put_u64_le(proj.id);
             buffer.put_f32_le(proj.x);
@@ -109,8 +117,11 @@ pub fn player_snapshot_from_state(
         dead: state.dead,
         health: state.health,
         armor: state.armor,
+        armor_type: state.armor_type.as_u8(),
         current_weapon: state.current_weapon,
         fire_cooldown: state.fire_cooldown,
+        weapon_state: state.weapon_state.as_u8(),
+        weapon_timer: state.weapon_timer,
         weapons: state.weapons,
         ammo: state.ammo,
         last_input_seq,
@@ -118,13 +129,19 @@ pub fn player_snapshot_from_state(
         key_right: state.key_right,
         key_up: state.key_up,
         key_down: state.key_down,
+        quad: state.quad_damage,
+        regeneration: state.regen_timer > 0,
+        haste: state.has_haste(),
+        battle_suit: state.has_battle_suit(),
+        invisibility: state.invisibility_timer > 0,
     }
 }
 
 pub fn encode_room_state(
     room_id: &str,
     map_name: &str,
-    tick_rate: u64,
+    tick_millis: u64,
+    ruleset: &[u8],
     players: &[crate::room::PlayerConn],
     player_states: &[crate::physics::PlayerState],
 ) -> Vec<u8> {
@@ -143,10 +160,13 @@ pub fn encode_room_state(
             )
         })
         .collect();
+    let tick_millis = tick_millis.clamp(1, u16::MAX as u64);
     binary_protocol::encode_room_state(
         room_id,
         map_name,
-        tick_rate.min(u16::MAX as u64) as u16,
+        ((1000 + tick_millis / 2) / tick_millis) as u16,
+        tick_millis as u16,
+        ruleset,
         &players_data,
     )
 }
@@ -158,7 +178,9 @@ mod tests {
 
     #[test]
     fn input_binary_roundtrip() {
-        let bytes = encode_input(42, 1.25, true, false, true, false, true, true, 3, -1);
+        let bytes = encode_input(
+            42, 1.25, true, false, true, false, true, true, 3, -1, 40, 41, 5,
+        );
         let decoded = decode_client_message(&bytes);
         assert!(decoded.is_ok());
         let Ok(decoded) = decoded else {
@@ -176,6 +198,10 @@ mod tests {
             weapon_scroll,
             aim_angle,
             facing_left,
+            fire_seq,
+            switch_seq,
+            switch_weapon,
+            latched,
         } = decoded
         else {
             panic!("expected Input");
@@ -191,5 +217,17 @@ mod tests {
         assert_eq!(weapon_scroll, -1);
         assert!((aim_angle - 1.25).abs() < f32::EPSILON);
         assert!(facing_left);
+        assert_eq!((fire_seq, switch_seq, switch_weapon), (40, 41, 5));
+        assert!(latched);
+    }
+
+    #[test]
+    fn room_state_keeps_tick_rate_and_appends_tick_millis() {
+        let bytes = encode_room_state("room", "dm2", 16, &[], &[], &[]);
+
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 63);
+        // Header, names, empty ruleset, no players, then the trailer.
+        assert_eq!(bytes.len(), 6 + 4 + 3 + 2 + 2);
+        assert_eq!(u16::from_le_bytes([bytes[15], bytes[16]]), 16);
     }
 }
@@ -1,19 +1,16 @@
 pub use physics_core::constants::WEAPON_COUNT;
 pub use physics_core::constants::{
-    ARMOR_ABSORPTION, DAMAGE, DEFAULT_AMMO, FIRE_RATE, GAUNTLET_PLAYER_RADIUS, GAUNTLET_RANGE,
-    GRENADE_HIT_GRACE, HITSCAN_AABB_PADDING, MACHINE_RANGE, MAX_ARMOR,
-    MAX_HEALTH, MEGA_HEALTH, PICKUP_AMMO, PICKUP_RADIUS, PROJECTILE_AABB_RADIUS_SCALE,
-    QUAD_DURATION, QUAD_MULTIPLIER, RESPAWN_TIME,
-    SELF_DAMAGE_REDUCTION, SELF_HIT_GRACE, SHOTGUN_BONUS_BASE, SHOTGUN_BONUS_MAX, SHOTGUN_PELLETS,
-    SHOTGUN_RANGE, SHOTGUN_SPREAD, SPAWN_OFFSET_X, SPAWN_PROTECTION, WEAPON_ORIGIN_CROUCH_LIFT,
-    WEAPON_PUSH,
+    GAUNTLET_PLAYER_RADIUS, GRENADE_HIT_GRACE, HITSCAN_AABB_PADDING, PICKUP_RADIUS,
+    PROJECTILE_AABB_RADIUS_SCALE, SELF_HIT_GRACE, SHOTGUN_BONUS_BASE, SHOTGUN_BONUS_MAX,
+    SHOTGUN_PELLETS, SHOTGUN_SPREAD, SPAWN_OFFSET_X, WEAPON_ORIGIN_CROUCH_LIFT,
 };
-pub use physics_core::constants::{PLAYER_HALF_H, TILE_H, TILE_W};
+pub use physics_core::constants::{PLAYER_HALF_H, TICK_MILLIS, TILE_H, TILE_W};
 
 pub const DEFAULT_PORT: &str = "3001";
 pub const DEFAULT_ROOM_ID: &str = "room-1";
 pub const DEFAULT_MAP_NAME: &str = "dm2";
 pub const DEFAULT_MAP_DIR: &str = "apps/client/public/maps";
+pub const DEFAULT_RULESET_DIR: &str = "packages/server/rulesets";
 
 
 pub const SNAPSHOT_INTERVAL_TICKS: u64 = 2;
@@ -21,3 +18,27 @@ pub const OUTBOUND_CHANNEL_CAPACITY: usize = 64;
 pub const ROOM_COMMAND_CAPACITY: usize = 1024;
 
 pub const SNAPSHOT_BUFFER_RING: usize = 8;
+pub const MAX_DEMO_BYTES: usize = 64 * 1024 * 1024;
+
+/// Added to recorded player ids during demo playback so they never match a
+/// viewer's own id. Small enough to stay exact as a JS number.
+pub const DEMO_PLAYER_ID_OFFSET: u64 = 1 << 40;
+pub const MIN_DEMO_SPEED: f32 = 0.125;
+pub const MAX_DEMO_SPEED: f32 = 8.0;
+/// Wall time a playback spends per tick catching up to a seek target; long
+/// seeks are spread over several ticks instead of stalling the runtime.
+pub const DEMO_SEEK_BUDGET_MS: u64 = 4;
+/// Demo controls arriving sooner than this after the last one are dropped.
+pub const DEMO_CONTROL_INTERVAL_MS: u64 = 100;
+
+/// How long a dropped player's slot and state are kept for them to resume.
+pub const RESUME_GRACE_MS: u64 = 15_000;
+
+/// How long a UDP handshake cookie stays valid after it is issued.
+pub const UDP_COOKIE_TTL_MS: u64 = 5_000;
+/// UDP peers silent for this long are disconnected. Clients ping every second.
+pub const UDP_IDLE_TIMEOUT_MS: u64 = 10_000;
+
+/// Late ticks a room runs back to back under the burst policy before it
+/// drops the rest.
+pub const MAX_CATCH_UP_TICKS: u64 = 4;
@@ -0,0 +1,328 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bytes::{Buf, BufMut};
+use tracing::warn;
+
+use crate::constants::MAX_DEMO_BYTES;
+use crate::game::WeaponId;
+use crate::map::GameMap;
+use crate::room::PlayerInput;
+use physics_core::{Ruleset, RulesetError};
+
+const DEMO_MAGIC: &[u8; 4] = b"RADM";
+/// Bump when the header or any record layout changes.
+pub const DEMO_VERSION: u8 = 3;
+
+const RECORD_TICK: u8 = 0x01;
+const RECORD_JOIN: u8 = 0x02;
+const RECORD_LEAVE: u8 = 0x03;
+const RECORD_INPUT: u8 = 0x04;
+
+/// Everything besides the records needed to rebuild the room. The ruleset is
+/// stored as ruleset text so demos outlive changes to the rule list.
+#[derive(Clone, Debug)]
+pub struct DemoHeader {
+    pub room_name: String,
+    pub map_name: String,
+    /// `GameMap::content_hash` of the map as recorded.
+    pub map_hash: u64,
+    pub seed: u64,
+    pub tick_millis: u64,
+    pub max_players: usize,
+    pub rules: Ruleset,
+}
+
+/// Room commands in the order the room applied them. `Tick` marks each call
+/// to the simulation, so a replay interleaves commands and ticks exactly.
+#[derive(Clone, Debug)]
+pub enum DemoRecord {
+    Tick,
+    Join {
+        player_id: u64,
+        username: String,
+    },
+    Leave {
+        player_id: u64,
+    },
+    Input {
+        player_id: u64,
+        seq: u64,
+        input: PlayerInput,
+    },
+}
+
+#[derive(Debug)]
+pub enum DemoError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    Ruleset(RulesetError),
+    /// The map file no longer matches the one the demo was recorded on.
+    MapChanged,
+    UnknownRecord(u8),
+    Truncated,
+}
+
+impl fmt::Display for DemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadMagic => write!(f, "not a demo file"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported demo version {version}"),
+            Self::Ruleset(err) => write!(f, "recorded ruleset: {err}"),
+            Self::MapChanged => write!(f, "map changed since the demo was recorded"),
+            Self::UnknownRecord(tag) => write!(f, "unknown record type {tag:#04x}"),
+            Self::Truncated => write!(f, "demo ends mid-record"),
+        }
+    }
+}
+
+impl From<io::Error> for DemoError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<bytes::TryGetError> for DemoError {
+    fn from(_: bytes::TryGetError) -> Self {
+        Self::Truncated
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Demo {
+    pub header: DemoHeader,
+    pub records: Vec<DemoRecord>,
+}
+
+impl Demo {
+    /// Number of simulated ticks, counting ticks the room spent empty.
+    pub fn tick_count(&self) -> u64 {
+        self.records
+            .iter()
+            .filter(|record| matches!(record, DemoRecord::Tick))
+            .count() as u64
+    }
+
+    pub fn load(path: &Path) -> Result<Self, DemoError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    /// Replaying on a map other than the recorded one desyncs from the first tick.
+    pub fn check_map(&self, map: &GameMap) -> Result<(), DemoError> {
+        if map.content_hash() != self.header.map_hash {
+            return Err(DemoError::MapChanged);
+        }
+        Ok(())
+    }
+
+    pub fn decode(mut buf: &[u8]) -> Result<Self, DemoError> {
+        let mut magic = [0; 4];
+        buf.try_copy_to_slice(&mut magic)?;
+        if &magic != DEMO_MAGIC {
+            return Err(DemoError::BadMagic);
+        }
+        let version = buf.try_get_u8()?;
+        if version != DEMO_VERSION {
+            return Err(DemoError::UnsupportedVersion(version));
+        }
+        let seed = buf.try_get_u64_le()?;
+        let tick_millis = buf.try_get_u64_le()?;
+        let max_players = buf.try_get_u8()? as usize;
+        let room_name = read_str(&mut buf)?;
+        let map_name = read_str(&mut buf)?;
+        let map_hash = buf.try_get_u64_le()?;
+        let rules_len = buf.try_get_u32_le()? as usize;
+        if buf.remaining() < rules_len {
+            return Err(DemoError::Truncated);
+        }
+        let rules = Ruleset::parse(&String::from_utf8_lossy(&buf[..rules_len]))
+            .map_err(DemoError::Ruleset)?;
+        buf.advance(rules_len);
+
+        let mut records = Vec::new();
+        while buf.has_remaining() {
+            records.push(read_record(&mut buf)?);
+        }
+        Ok(Self {
+            header: DemoHeader {
+                room_name,
+                map_name,
+                map_hash,
+                seed,
+                tick_millis,
+                max_players,
+                rules,
+            },
+            records,
+        })
+    }
+}
+
+/// Buffers a room's demo in memory and writes it out when the room closes.
+/// Recording stops once the demo reaches `MAX_DEMO_BYTES`.
+pub struct DemoRecorder {
+    out: Vec<u8>,
+    path: PathBuf,
+    full: bool,
+}
+
+impl DemoRecorder {
+    pub fn new(dir: &Path, file_stem: &str, header: &DemoHeader) -> Self {
+        let mut out = Vec::with_capacity(64 * 1024);
+        out.put_slice(DEMO_MAGIC);
+        out.put_u8(DEMO_VERSION);
+        out.put_u64_le(header.seed);
+        out.put_u64_le(header.tick_millis);
+        out.put_u8(header.max_players.min(u8::MAX as usize) as u8);
+        write_str(&mut out, &header.room_name);
+        write_str(&mut out, &header.map_name);
+        out.put_u64_le(header.map_hash);
+        let rules = header.rules.to_text();
+        out.put_u32_le(rules.len() as u32);
+        out.put_slice(rules.as_bytes());
+        Self {
+            out,
+            path: dir.join(format!("{file_stem}.demo")),
+            full: false,
+        }
+    }
+
+    pub fn record(&mut self, record: &DemoRecord) {
+        if self.full {
+            return;
+        }
+        if self.out.len() >= MAX_DEMO_BYTES {
+            warn!(path = %self.path.display(), "demo size limit reached, recording stopped");
+            self.full = true;
+            return;
+        }
+        write_record(&mut self.out, record);
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.out
+    }
+
+    pub async fn save(self) -> io::Result<PathBuf> {
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.path, &self.out).await?;
+        Ok(self.path)
+    }
+}
+
+fn write_record(out: &mut Vec<u8>, record: &DemoRecord) {
+    match record {
+        DemoRecord::Tick => out.put_u8(RECORD_TICK),
+        DemoRecord::Join {
+            player_id,
+            username,
+        } => {
+            out.put_u8(RECORD_JOIN);
+            out.put_u64_le(*player_id);
+            write_str(out, username);
+        }
+        DemoRecord::Leave { player_id } => {
+            out.put_u8(RECORD_LEAVE);
+            out.put_u64_le(*player_id);
+        }
+        DemoRecord::Input {
+            player_id,
+            seq,
+            input,
+        } => {
+            out.put_u8(RECORD_INPUT);
+            out.put_u64_le(*player_id);
+            out.put_u64_le(*seq);
+            let flags = [
+                input.key_up,
+                input.key_down,
+                input.key_left,
+                input.key_right,
+                input.mouse_down,
+                input.facing_left,
+                input.latched,
+            ]
+            .iter()
+            .enumerate()
+            .fold(0u8, |bits, (idx, set)| bits | (u8::from(*set) << idx));
+            out.put_u8(flags);
+            out.put_i8(weapon_to_i8(input.weapon_switch));
+            out.put_i8(input.weapon_scroll);
+            out.put_f32_le(input.aim_angle);
+            out.put_u64_le(input.fire_seq);
+            out.put_u64_le(input.switch_seq);
+            out.put_i8(weapon_to_i8(input.switch_weapon));
+        }
+    }
+}
+
+fn read_record(buf: &mut &[u8]) -> Result<DemoRecord, DemoError> {
+    let record = match buf.try_get_u8()? {
+        RECORD_TICK => DemoRecord::Tick,
+        RECORD_JOIN => DemoRecord::Join {
+            player_id: buf.try_get_u64_le()?,
+            username: read_str(buf)?,
+        },
+        RECORD_LEAVE => DemoRecord::Leave {
+            player_id: buf.try_get_u64_le()?,
+        },
+        RECORD_INPUT => {
+            let player_id = buf.try_get_u64_le()?;
+            let seq = buf.try_get_u64_le()?;
+            let flags = buf.try_get_u8()?;
+            let bit = |idx: u8| flags & (1 << idx) != 0;
+            let input = PlayerInput {
+                key_up: bit(0),
+                key_down: bit(1),
+                key_left: bit(2),
+                key_right: bit(3),
+                mouse_down: bit(4),
+                facing_left: bit(5),
+                latched: bit(6),
+                weapon_switch: weapon_from_i8(buf.try_get_i8()?),
+                weapon_scroll: buf.try_get_i8()?,
+                aim_angle: buf.try_get_f32_le()?,
+                fire_seq: buf.try_get_u64_le()?,
+                switch_seq: buf.try_get_u64_le()?,
+                switch_weapon: weapon_from_i8(buf.try_get_i8()?),
+            };
+            DemoRecord::Input {
+                player_id,
+                seq,
+                input,
+            }
+        }
+        tag => return Err(DemoError::UnknownRecord(tag)),
+    };
+    Ok(record)
+}
+
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(u8::MAX as usize);
+    out.put_u8(len as u8);
+    out.put_slice(&bytes[..len]);
+}
+
+fn read_str(buf: &mut &[u8]) -> Result<String, DemoError> {
+    let len = buf.try_get_u8()? as usize;
+    if buf.remaining() < len {
+        return Err(DemoError::Truncated);
+    }
+    let value = String::from_utf8_lossy(&buf[..len]).into_owned();
+    buf.advance(len);
+    Ok(value)
+}
+
+fn weapon_to_i8(weapon: Option<WeaponId>) -> i8 {
+    weapon.map_or(-1, |weapon| weapon as i8)
+}
+
+fn weapon_from_i8(value: i8) -> Option<WeaponId> {
+    WeaponId::try_from(value as i32).ok()
+}
@@ -2,21 +2,22 @@ use rand::Rng;
 
 use crate::binary::EffectEvent;
 use crate::constants::{
-    ARMOR_ABSORPTION, DAMAGE, DEFAULT_AMMO, FIRE_RATE, GAUNTLET_PLAYER_RADIUS, GAUNTLET_RANGE,
-    GRENADE_HIT_GRACE, HITSCAN_AABB_PADDING, MACHINE_RANGE, MAX_ARMOR, MAX_HEALTH, MEGA_HEALTH,
-    PICKUP_AMMO, PICKUP_RADIUS, PLAYER_HALF_H, PROJECTILE_AABB_RADIUS_SCALE, QUAD_DURATION,
-    QUAD_MULTIPLIER, RESPAWN_TIME, SELF_DAMAGE_REDUCTION, SELF_HIT_GRACE, SHOTGUN_BONUS_BASE,
-    SHOTGUN_BONUS_MAX, SHOTGUN_PELLETS, SHOTGUN_RANGE, SHOTGUN_SPREAD, SPAWN_OFFSET_X,
-    SPAWN_PROTECTION, TILE_H, TILE_W, WEAPON_ORIGIN_CROUCH_LIFT, WEAPON_PUSH,
+    GAUNTLET_PLAYER_RADIUS, GRENADE_HIT_GRACE, HITSCAN_AABB_PADDING, PICKUP_RADIUS,
+    PROJECTILE_AABB_RADIUS_SCALE, SELF_HIT_GRACE, SHOTGUN_BONUS_BASE, SHOTGUN_BONUS_MAX,
+    SHOTGUN_PELLETS, SHOTGUN_SPREAD, TILE_H, TILE_W, WEAPON_ORIGIN_CROUCH_LIFT,
 };
-use crate::map::GameMap;
+use crate::map::{GameMap, ItemKind, MapItem};
 use crate::physics::PlayerState;
-use physics_core::{player_hitbox, segment_aabb_t};
+use crate::spawn::{overlaps, spawn_position, SpawnSelector};
+use physics_core::explosion::apply_radial_impulse;
+use physics_core::types::Aabb;
+use physics_core::{
+    player_hitbox, segment_aabb_t, segment_hit_zone, HitZone, KnockbackModel, Ruleset, WeaponState,
+};
 use smallvec::SmallVec;
 
 pub use physics_core::projectile::{Explosion, Projectile, ProjectileKind};
 
-pub use crate::constants::WEAPON_COUNT;
 pub type EventVec = SmallVec<[EffectEvent; 16]>;
 
 const PUSH_LATERAL_FACTOR: f32 = 5.0 / 6.0;
@@ -39,12 +40,62 @@ pub enum WeaponId {
 pub struct IdGen(u64);
 
 impl IdGen {
-    pub fn next(&mut self) -> u64 {
+    pub fn next_id(&mut self) -> u64 {
         self.0 = self.0.wrapping_add(1);
         self.0
     }
 }
 
+/// Weapon or powerup left behind by a dying player. `amount` is the ammo or
+/// powerup ticks it carries over to whoever picks it up.
+#[derive(Clone, Debug)]
+pub struct DroppedItem {
+    pub id: u64,
+    pub kind: ItemKind,
+    pub x: f32,
+    pub y: f32,
+    pub amount: i32,
+    pub despawn_timer: i32,
+}
+
+impl WeaponId {
+    pub const ALL: [Self; 9] = [
+        Self::Gauntlet,
+        Self::Machine,
+        Self::Shotgun,
+        Self::Grenade,
+        Self::Rocket,
+        Self::Rail,
+        Self::Plasma,
+        Self::Shaft,
+        Self::Bfg,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gauntlet => "gauntlet",
+            Self::Machine => "machinegun",
+            Self::Shotgun => "shotgun",
+            Self::Grenade => "grenade",
+            Self::Rocket => "rocket",
+            Self::Rail => "railgun",
+            Self::Plasma => "plasma",
+            Self::Shaft => "shaft",
+            Self::Bfg => "bfg",
+        }
+    }
+
+    /// Weapon that fired a projectile, for crediting its hits and splash.
+    pub fn for_projectile(kind: ProjectileKind) -> Self {
+        match kind {
+            ProjectileKind::Rocket => Self::Rocket,
+            ProjectileKind::Grenade => Self::Grenade,
+            ProjectileKind::Plasma => Self::Plasma,
+            ProjectileKind::Bfg => Self::Bfg,
+        }
+    }
+}
+
 impl TryFrom<i32> for WeaponId {
     type Error = ();
 
@@ -65,7 +116,7 @@ impl TryFrom<i32> for WeaponId {
 }
 
 pub fn can_fire(player: &PlayerState) -> bool {
-    if player.dead || player.fire_cooldown > 0 {
+    if player.dead || player.fire_cooldown > 0 || !player.weapon_ready() {
         return false;
     }
     let weapon = player.current_weapon as usize;
@@ -73,15 +124,27 @@ pub fn can_fire(player: &PlayerState) -> bool {
     ammo == -1 || ammo > 0
 }
 
+/// Where `try_fire` puts what a shot produces.
+pub struct FireOutput<'a> {
+    pub projectiles: &'a mut Vec<Projectile>,
+    pub id_gen: &'a mut IdGen,
+    pub hitscan_actions: &'a mut Vec<HitAction>,
+    pub events: &'a mut EventVec,
+}
+
 pub fn try_fire(
     player: &mut PlayerState,
-    projectiles: &mut Vec<Projectile>,
     map: &GameMap,
-    id_gen: &mut IdGen,
-    hitscan_actions: &mut Vec<HitAction>,
-    events: &mut EventVec,
+    out: FireOutput<'_>,
     rng: &mut impl Rng,
+    rules: &Ruleset,
 ) {
+    let FireOutput {
+        projectiles,
+        id_gen,
+        hitscan_actions,
+        events,
+    } = out;
     if !can_fire(player) {
         return;
     }
@@ -94,7 +157,11 @@ pub fn try_fire(
     if player.ammo[player.current_weapon as usize] != -1 {
         player.ammo[player.current_weapon as usize] -= 1;
     }
-    player.fire_cooldown = fire_rate(weapon);
+    player.fire_cooldown = rules.fire_rate_for(weapon as i32);
+    if player.has_haste() {
+        player.fire_cooldown = (player.fire_cooldown as f32 / rules.haste_factor).round() as i32;
+    }
+    player.weapon_state = WeaponState::Firing;
     events.push(EffectEvent::WeaponFired {
         player_id: player.id,
         weapon_id: player.current_weapon,
@@ -103,14 +170,14 @@ pub fn try_fire(
     match weapon {
         WeaponId::Gauntlet => {
             let (x, y) = get_weapon_origin(player);
-            let hit_x = x + player.aim_angle.cos() * GAUNTLET_RANGE;
-            let hit_y = y + player.aim_angle.sin() * GAUNTLET_RANGE;
+            let hit_x = x + player.aim_angle.cos() * rules.gauntlet_range;
+            let hit_y = y + player.aim_angle.sin() * rules.gauntlet_range;
             hitscan_actions.push(HitAction::Melee {
                 attacker_id: player.id,
                 weapon_id: weapon,
                 hit_x,
                 hit_y,
-                damage: damage_for(weapon),
+                damage: rules.damage_for(weapon as i32),
             });
             events.push(EffectEvent::Gauntlet { x: hit_x, y: hit_y });
         }
@@ -118,7 +185,7 @@ pub fn try_fire(
             let (x, y) = get_weapon_origin(player);
             for _ in 0..SHOTGUN_PELLETS {
                 let angle = player.aim_angle + (rng.gen::<f32>() - 0.5) * SHOTGUN_SPREAD;
-                let trace = physics_core::weapon::ray_trace(map, x, y, angle, SHOTGUN_RANGE);
+                let trace = physics_core::weapon::ray_trace(map, x, y, angle, rules.shotgun_range);
                 hitscan_actions.push(HitAction::Hitscan {
                     attacker_id: player.id,
                     weapon_id: weapon,
@@ -126,13 +193,14 @@ pub fn try_fire(
                     start_y: y,
                     trace_x: trace.x,
                     trace_y: trace.y,
-                    damage: damage_for(weapon),
+                    damage: rules.damage_for(weapon as i32),
                 });
             }
         }
         WeaponId::Machine | WeaponId::Rail | WeaponId::Shaft => {
-            let range =
-                physics_core::weapon::hitscan_range(player.current_weapon).unwrap_or(MACHINE_RANGE);
+            let range = rules
+                .hitscan_range(player.current_weapon)
+                .unwrap_or(rules.machine_range);
             let (x, y) = get_weapon_origin(player);
             let trace = physics_core::weapon::ray_trace(map, x, y, player.aim_angle, range);
             hitscan_actions.push(HitAction::Hitscan {
@@ -142,7 +210,7 @@ pub fn try_fire(
                 start_y: y,
                 trace_x: trace.x,
                 trace_y: trace.y,
-                damage: damage_for(weapon),
+                damage: rules.damage_for(weapon as i32),
             });
         }
         WeaponId::Grenade | WeaponId::Rocket | WeaponId::Plasma | WeaponId::Bfg => {
@@ -152,10 +220,11 @@ pub fn try_fire(
                 x,
                 y,
                 player.aim_angle,
+                rules,
             ) else {
                 return;
             };
-            let id = id_gen.next();
+            let id = id_gen.next_id();
             events.push(EffectEvent::ProjectileSpawn {
                 id,
                 kind: spawn.kind.as_u8(),
@@ -205,7 +274,10 @@ pub enum HitAction {
 pub fn apply_hit_actions(
     actions: &[HitAction],
     players: &mut [PlayerState],
+    projectiles: &mut [Projectile],
     events: &mut EventVec,
+    explosions: &mut Vec<Explosion>,
+    rules: &Ruleset,
 ) {
     for action in actions {
         match *action {
@@ -218,15 +290,50 @@ pub fn apply_hit_actions(
                 trace_y,
                 damage,
             } => {
-                let impact =
+                if weapon_id == WeaponId::Rail {
+                    if rules.projectile_interactions {
+                        shoot_projectiles_along(
+                            attacker_id,
+                            (start_x, start_y),
+                            (trace_x, trace_y),
+                            projectiles,
+                            events,
+                            explosions,
+                        );
+                    }
+                    apply_rail_hit(
+                        attacker_id,
+                        (start_x, start_y),
+                        (trace_x, trace_y),
+                        damage,
+                        players,
+                        events,
+                        rules,
+                    );
+                    continue;
+                }
+                let mut impact =
                     find_hitscan_impact(attacker_id, start_x, start_y, trace_x, trace_y, players);
+                if rules.projectile_interactions {
+                    if let Some(t) = shoot_first_projectile(
+                        attacker_id,
+                        (start_x, start_y),
+                        (trace_x, trace_y),
+                        impact.t,
+                        projectiles,
+                        events,
+                        explosions,
+                    ) {
+                        impact = HitscanImpact {
+                            x: start_x + (trace_x - start_x) * t,
+                            y: start_y + (trace_y - start_y) * t,
+                            t,
+                            target_id: None,
+                            zone: HitZone::Body,
+                        };
+                    }
+                }
                 match weapon_id {
-                    WeaponId::Rail => events.push(EffectEvent::Rail {
-                        start_x,
-                        start_y,
-                        end_x: impact.x,
-                        end_y: impact.y,
-                    }),
                     WeaponId::Shaft => events.push(EffectEvent::Shaft {
                         start_x,
                         start_y,
@@ -254,9 +361,34 @@ pub fn apply_hit_actions(
                         let bonus = (SHOTGUN_BONUS_BASE / dist).trunc().min(SHOTGUN_BONUS_MAX);
                         final_damage += bonus;
                     }
-                    apply_damage(attacker_id, target_id, final_damage, players, events);
+                    let zone = if rules.hit_zones {
+                        impact.zone
+                    } else {
+                        HitZone::Body
+                    };
+                    final_damage *= zone.damage_multiplier(weapon_id as i32, rules);
+                    apply_damage(
+                        Hit {
+                            attacker_id,
+                            target_id,
+                            damage: final_damage,
+                            weapon_id,
+                            zone,
+                        },
+                        players,
+                        events,
+                        rules,
+                    );
                     if let Some((sx, sy)) = get_player_pos(attacker_id, players) {
-                        apply_push_on_hit(attacker_id, target_id, weapon_id, sx, sy, players);
+                        apply_push_on_hit(
+                            attacker_id,
+                            target_id,
+                            weapon_id,
+                            sx,
+                            sy,
+                            players,
+                            rules,
+                        );
                     }
                 }
             }
@@ -268,9 +400,28 @@ pub fn apply_hit_actions(
                 damage,
             } => {
                 if let Some(target_id) = find_melee_target(attacker_id, hit_x, hit_y, players) {
-                    apply_damage(attacker_id, target_id, damage, players, events);
+                    apply_damage(
+                        Hit {
+                            attacker_id,
+                            target_id,
+                            damage,
+                            weapon_id,
+                            zone: HitZone::Body,
+                        },
+                        players,
+                        events,
+                        rules,
+                    );
                     if let Some((sx, sy)) = get_player_pos(attacker_id, players) {
-                        apply_push_on_hit(attacker_id, target_id, weapon_id, sx, sy, players);
+                        apply_push_on_hit(
+                            attacker_id,
+                            target_id,
+                            weapon_id,
+                            sx,
+                            sy,
+                            players,
+                            rules,
+                        );
                     }
                 }
             }
@@ -283,6 +434,7 @@ pub fn update_projectiles(
     projectiles: &mut Vec<Projectile>,
     events: &mut EventVec,
     explosions: &mut Vec<Explosion>,
+    rules: &Ruleset,
 ) {
     let bounds = physics_core::calculate_bounds(map.cols, map.rows);
 
@@ -290,7 +442,7 @@ pub fn update_projectiles(
         if !proj.active {
             continue;
         }
-        if let Some(explosion) = physics_core::step_projectile(proj, map, bounds) {
+        if let Some(explosion) = physics_core::step_projectile(proj, map, bounds, rules) {
             events.push(EffectEvent::ProjectileRemove {
                 id: proj.id,
                 x: explosion.x,
@@ -309,6 +461,7 @@ pub fn apply_projectile_hits(
     players: &mut [PlayerState],
     events: &mut EventVec,
     explosions: &mut Vec<Explosion>,
+    rules: &Ruleset,
 ) {
     for proj in projectiles.iter_mut() {
         if !proj.active {
@@ -336,11 +489,22 @@ pub fn apply_projectile_hits(
             let damage = match proj.kind {
                 ProjectileKind::Rocket => 0.0,
                 ProjectileKind::Grenade => 0.0,
-                ProjectileKind::Plasma => damage_for(WeaponId::Plasma),
+                ProjectileKind::Plasma => rules.damage_for(WeaponId::Plasma as i32),
                 ProjectileKind::Bfg => 0.0,
             };
             if damage > 0.0 {
-                apply_damage(proj.owner_id, target_id, damage, players, events);
+                apply_damage(
+                    Hit {
+                        attacker_id: proj.owner_id,
+                        target_id,
+                        damage,
+                        weapon_id: WeaponId::Plasma,
+                        zone: HitZone::Body,
+                    },
+                    players,
+                    events,
+                    rules,
+                );
                 apply_push_on_hit(
                     proj.owner_id,
                     target_id,
@@ -348,15 +512,40 @@ pub fn apply_projectile_hits(
                     proj.x,
                     proj.y,
                     players,
+                    rules,
                 );
             }
-            explode(proj, explosions);
-            events.push(EffectEvent::ProjectileRemove {
-                id: proj.id,
-                x: proj.x,
-                y: proj.y,
-                kind: proj.kind.as_u8(),
-            });
+            detonate(proj, events, explosions);
+        }
+    }
+
+    projectiles.retain(|p| p.active);
+}
+
+/// Projectile-vs-projectile contact: BFG balls absorb enemy plasma and
+/// enemy plasma bolts cancel each other out.
+pub fn resolve_projectile_collisions(projectiles: &mut Vec<Projectile>, events: &mut EventVec) {
+    for i in 0..projectiles.len() {
+        for j in (i + 1)..projectiles.len() {
+            let (a, b) = (&projectiles[i], &projectiles[j]);
+            if !a.active || !b.active || a.owner_id == b.owner_id {
+                continue;
+            }
+            let (remove_a, remove_b) = match (a.kind, b.kind) {
+                (ProjectileKind::Plasma, ProjectileKind::Plasma) => (true, true),
+                (ProjectileKind::Plasma, ProjectileKind::Bfg) => (true, false),
+                (ProjectileKind::Bfg, ProjectileKind::Plasma) => (false, true),
+                _ => continue,
+            };
+            if !aabb_overlap(a.hitbox(), b.hitbox()) {
+                continue;
+            }
+            if remove_a {
+                remove_with_event(&mut projectiles[i], events);
+            }
+            if remove_b {
+                remove_with_event(&mut projectiles[j], events);
+            }
         }
     }
 
@@ -364,26 +553,37 @@ pub fn apply_projectile_hits(
 }
 
 pub fn apply_explosions(
-    explosions: &[Explosion],
+    explosions: &mut Vec<Explosion>,
     players: &mut [PlayerState],
+    projectiles: &mut Vec<Projectile>,
     events: &mut EventVec,
-    pending_hits: &mut Vec<(u64, u64, f32)>,
+    pending_hits: &mut Vec<(u64, u64, f32, WeaponId)>,
+    rules: &Ruleset,
 ) {
-    for explosion in explosions {
-        let base_damage = physics_core::explosion::base_damage(explosion.kind);
+    if rules.projectile_interactions {
+        detonate_projectiles_in_explosions(explosions, projectiles, events, rules);
+    }
+
+    for explosion in explosions.iter() {
+        let base_damage = physics_core::explosion::base_damage(explosion.kind, rules);
 
         let attacker_quad = has_quad_damage(players, explosion.owner_id);
-        let knockback_scale = if attacker_quad { QUAD_MULTIPLIER } else { 1.0 };
+        let knockback_scale = if attacker_quad {
+            rules.quad_multiplier
+        } else {
+            1.0
+        };
 
         for player in players.iter_mut() {
             if player.dead {
                 continue;
             }
 
-            let damage = match physics_core::explosion::apply_knockback_with_scale(
+            let damage = match physics_core::explosion::apply_knockback_with_rules(
                 player,
                 explosion,
                 knockback_scale,
+                rules,
             ) {
                 Some(falloff) => {
                     physics_core::explosion::calculate_explosion_damage(falloff, base_damage)
@@ -391,22 +591,139 @@ pub fn apply_explosions(
                 None => continue,
             };
 
-            if damage > 0.0 {
-                pending_hits.push((explosion.owner_id, player.id, damage));
+            // The battle suit still lets explosions push, but blocks their damage.
+            if damage > 0.0 && !player.has_battle_suit() {
+                pending_hits.push((
+                    explosion.owner_id,
+                    player.id,
+                    damage,
+                    WeaponId::for_projectile(explosion.kind),
+                ));
             }
         }
     }
-    for (attacker_id, target_id, damage) in pending_hits.drain(..) {
-        apply_damage(attacker_id, target_id, damage, players, events);
+    for (attacker_id, target_id, damage, weapon_id) in pending_hits.drain(..) {
+        apply_damage(
+            Hit {
+                attacker_id,
+                target_id,
+                damage,
+                weapon_id,
+                zone: HitZone::Body,
+            },
+            players,
+            events,
+            rules,
+        );
     }
 }
 
-pub fn process_item_pickups(players: &mut [PlayerState], items: &mut [crate::map::MapItem]) {
-    for item in items.iter_mut() {
+/// Explosions set off enemy rockets and grenades inside their splash radius.
+/// Newly triggered explosions are appended and can chain further.
+fn detonate_projectiles_in_explosions(
+    explosions: &mut Vec<Explosion>,
+    projectiles: &mut Vec<Projectile>,
+    events: &mut EventVec,
+    rules: &Ruleset,
+) {
+    let mut idx = 0;
+    while idx < explosions.len() {
+        let Explosion {
+            x,
+            y,
+            kind,
+            owner_id,
+        } = explosions[idx];
+        let radius = physics_core::explosion::splash_radius(kind, rules);
+        for proj in projectiles.iter_mut() {
+            if !can_be_shot(proj, owner_id) {
+                continue;
+            }
+            let dx = proj.x - x;
+            let dy = proj.y - y;
+            if dx * dx + dy * dy < radius * radius {
+                detonate(proj, events, explosions);
+            }
+        }
+        idx += 1;
+    }
+
+    projectiles.retain(|p| p.active);
+}
+
+/// Leave the held weapon and any active powerups where each player killed
+/// this tick died. Gauntlet, machinegun and weapons with infinite ammo stay.
+pub fn drop_items_on_death(
+    events: &[EffectEvent],
+    players: &mut [PlayerState],
+    dropped: &mut Vec<DroppedItem>,
+    ids: &mut IdGen,
+    rules: &Ruleset,
+) {
+    if !rules.items_enabled {
+        return;
+    }
+    for event in events {
+        let EffectEvent::Damage {
+            target_id,
+            killed: true,
+            ..
+        } = event
+        else {
+            continue;
+        };
+        let Some(player) = players.iter_mut().find(|p| p.id == *target_id) else {
+            continue;
+        };
+        let (x, y) = (player.x, player.y);
+        let mut drop = |kind, amount| {
+            dropped.push(DroppedItem {
+                id: ids.next_id(),
+                kind,
+                x,
+                y,
+                amount,
+                despawn_timer: rules.dropped_item_lifetime,
+            });
+        };
+        if rules.drop_weapon {
+            if let Some(weapon) = droppable_weapon(player) {
+                drop(ItemKind::for_weapon(weapon), player.ammo[weapon as usize]);
+            }
+        }
+        if rules.drop_powerups {
+            for (kind, ticks) in active_powerups(player) {
+                if ticks > 0 {
+                    drop(kind, ticks);
+                }
+            }
+            player.clear_powerups();
+        }
+    }
+}
+
+pub fn process_item_pickups(
+    players: &mut [PlayerState],
+    items: &mut [MapItem],
+    dropped: &mut Vec<DroppedItem>,
+    events: &mut EventVec,
+    rules: &Ruleset,
+) {
+    if !rules.items_enabled {
+        for item in items.iter_mut() {
+            item.active = false;
+        }
+        dropped.clear();
+        return;
+    }
+    for (index, item) in items.iter_mut().enumerate() {
         if !item.active {
             item.respawn_timer -= 1;
             if item.respawn_timer <= 0 {
                 item.active = true;
+                events.push(EffectEvent::ItemRespawn {
+                    index: index as u16,
+                });
             }
             continue;
         }
@@ -417,73 +734,235 @@ pub fn process_item_pickups(players: &mut [PlayerState], items: &mut [crate::map
             if !is_player_near_item(player, item) {
                 continue;
             }
-            apply_item_effect(player, item);
+            if !apply_item_effect(player, item, rules) {
+                continue;
+            }
             item.active = false;
             item.respawn_timer = item.kind.respawn_time();
+            events.push(EffectEvent::ItemPickup {
+                player_id: player.id,
+                index: Some(index as u16),
+                kind: item.kind.to_char() as u8,
+            });
             break;
         }
     }
+    dropped.retain_mut(|item| {
+        item.despawn_timer -= 1;
+        if item.despawn_timer <= 0 {
+            return false;
+        }
+        let Some(player) = players
+            .iter_mut()
+            .find(|player| !player.dead && is_player_near(player, item.x, item.y))
+        else {
+            return true;
+        };
+        match item.kind.weapon() {
+            Some(weapon) => give_weapon(player, weapon, item.amount),
+            None => give_powerup(player, item.kind, item.amount),
+        }
+        events.push(EffectEvent::ItemPickup {
+            player_id: player.id,
+            index: None,
+            kind: item.kind.to_char() as u8,
+        });
+        false
+    });
 }
 
-pub fn respawn_if_ready_with_rng(player: &mut PlayerState, map: &GameMap, rng: &mut impl Rng) {
+/// Respawns `players[idx]` once its timer runs out, at a point picked by `spawns`.
+/// With telefrag enabled anyone standing on that point dies.
+pub fn respawn_if_ready_with_rng(
+    idx: usize,
+    players: &mut [PlayerState],
+    map: &GameMap,
+    spawns: &mut SpawnSelector,
+    rng: &mut impl Rng,
+    events: &mut EventVec,
+    rules: &Ruleset,
+) {
+    let player = &players[idx];
     if !player.dead || player.respawn_timer > 0 {
         return;
     }
-    let Some((row, col)) = map.random_respawn_with_rng(rng) else {
+    let player_id = player.id;
+    let Some((row, col)) = spawns.select(map, players, player_id, rules.telefrag, rng) else {
         return;
     };
-    let x = col as f32 * TILE_W + SPAWN_OFFSET_X;
-    let y = row as f32 * TILE_H - PLAYER_HALF_H;
+    let (x, y) = spawn_position(row, col);
+    if rules.telefrag {
+        telefrag(player_id, x, y, players, events, rules);
+    }
+    let player = &mut players[idx];
     player.set_xy(x, y, map);
     player.prev_x = player.x;
     player.prev_y = player.y;
-    player.health = MAX_HEALTH;
-    player.armor = 0;
     player.dead = false;
     player.velocity_x = 0.0;
     player.velocity_y = 0.0;
-    player.weapons = [true; WEAPON_COUNT];
-    player.ammo = DEFAULT_AMMO;
-    player.current_weapon = WeaponId::Rocket as i32;
-    player.quad_damage = false;
-    player.quad_timer = 0;
-    player.spawn_protection = SPAWN_PROTECTION;
+    apply_spawn_loadout(player, rules);
+    player.spawn_protection = rules.spawn_protection;
+    events.push(EffectEvent::PlayerRespawn {
+        player_id,
+        x: player.x,
+        y: player.y,
+    });
 }
 
-fn apply_damage(
+fn telefrag(
+    player_id: u64,
+    x: f32,
+    y: f32,
+    players: &mut [PlayerState],
+    events: &mut EventVec,
+    rules: &Ruleset,
+) {
+    for victim in players.iter_mut() {
+        if victim.id == player_id || victim.dead || !overlaps(victim, x, y) {
+            continue;
+        }
+        events.push(EffectEvent::Damage {
+            attacker_id: player_id,
+            target_id: victim.id,
+            amount: victim.health.max(1),
+            killed: true,
+            hit_zone: HitZone::Body.as_u8(),
+            weapon_id: -1,
+        });
+        victim.health = 0;
+        victim.dead = true;
+        victim.respawn_timer = rules.respawn_time;
+    }
+}
+
+/// Health, armor and weapons a player starts with, on join and after respawning.
+pub fn apply_spawn_loadout(player: &mut PlayerState, rules: &Ruleset) {
+    rules.loadout().apply(player);
+}
+
+/// Rails pierce: every living player along the trace up to the wall takes damage,
+/// reduced by `rail_pierce_falloff` for each body already passed through.
+/// With hit zones enabled each victim's damage also scales by the zone struck.
+fn apply_rail_hit(
     attacker_id: u64,
-    target_id: u64,
+    (start_x, start_y): (f32, f32),
+    (end_x, end_y): (f32, f32),
     damage: f32,
     players: &mut [PlayerState],
     events: &mut EventVec,
+    rules: &Ruleset,
 ) {
+    let impacts = find_piercing_impacts(attacker_id, start_x, start_y, end_x, end_y, players);
+    events.push(EffectEvent::Rail {
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+        impacts: impacts.iter().map(|impact| (impact.x, impact.y)).collect(),
+    });
+
+    let mut body_damage = damage;
+    let mut kills = 0_u8;
+    for impact in &impacts {
+        let Some(target_id) = impact.target_id else {
+            continue;
+        };
+        let zone = if rules.hit_zones {
+            impact.zone
+        } else {
+            HitZone::Body
+        };
+        let damage = body_damage * zone.damage_multiplier(WeaponId::Rail as i32, rules);
+        if apply_damage(
+            Hit {
+                attacker_id,
+                target_id,
+                damage,
+                weapon_id: WeaponId::Rail,
+                zone,
+            },
+            players,
+            events,
+            rules,
+        ) {
+            kills = kills.saturating_add(1);
+        }
+        if let Some((sx, sy)) = get_player_pos(attacker_id, players) {
+            apply_push_on_hit(
+                attacker_id,
+                target_id,
+                WeaponId::Rail,
+                sx,
+                sy,
+                players,
+                rules,
+            );
+        }
+        body_damage *= rules.rail_pierce_falloff;
+    }
+
+    if kills >= 2 {
+        events.push(EffectEvent::MultiKill {
+            attacker_id,
+            count: kills,
+        });
+    }
+}
+
+/// One landed hit, before quad, armor and self-damage scaling.
+struct Hit {
+    attacker_id: u64,
+    target_id: u64,
+    damage: f32,
+    weapon_id: WeaponId,
+    zone: HitZone,
+}
+
+/// Returns true if the hit killed the target.
+/// With a vampire ruleset the attacker heals by a share of the damage dealt to others.
+fn apply_damage(
+    hit: Hit,
+    players: &mut [PlayerState],
+    events: &mut EventVec,
+    rules: &Ruleset,
+) -> bool {
+    let Hit {
+        attacker_id,
+        target_id,
+        damage,
+        weapon_id,
+        zone: hit_zone,
+    } = hit;
     let attacker_quad = has_quad_damage(players, attacker_id);
-    let multiplier = if attacker_quad { QUAD_MULTIPLIER } else { 1.0 };
+    let multiplier = if attacker_quad {
+        rules.quad_multiplier
+    } else {
+        1.0
+    };
     let mut actual = damage * multiplier;
 
     let Some(player) = players.iter_mut().find(|p| p.id == target_id) else {
-        return;
+        return false;
     };
     if player.dead || player.spawn_protection > 0 {
-        return;
+        return false;
     }
     if attacker_id == target_id {
-        actual *= SELF_DAMAGE_REDUCTION;
+        actual *= rules.self_damage_reduction;
     }
-
-    if player.armor > 0 {
-        let armor_damage = (actual * ARMOR_ABSORPTION).floor() as i32;
-        let absorbed = armor_damage.min(player.armor);
-        player.armor -= absorbed;
-        actual -= absorbed as f32;
+    if player.has_battle_suit() {
+        actual *= rules.battle_suit_factor;
     }
 
+    actual = player.absorb_damage(actual, rules);
+
     let rounded = actual.floor() as i32;
     player.health -= rounded;
     let killed = player.health <= 0;
     if killed {
         player.dead = true;
-        player.respawn_timer = RESPAWN_TIME;
+        player.respawn_timer = rules.respawn_time;
     }
     if rounded > 0 {
         events.push(EffectEvent::Damage {
@@ -491,8 +970,19 @@ fn apply_damage(
             target_id,
             amount: rounded,
             killed,
+            hit_zone: hit_zone.as_u8(),
+            weapon_id: weapon_id as i32,
         });
     }
+    if attacker_id != target_id && rounded > 0 && rules.vampire > 0.0 {
+        let heal = (rounded as f32 * rules.vampire).floor() as i32;
+        if let Some(attacker) = players.iter_mut().find(|p| p.id == attacker_id && !p.dead) {
+            attacker.health = (attacker.health + heal)
+                .min(rules.mega_health)
+                .max(attacker.health);
+        }
+    }
+    killed
 }
 
 fn get_player_pos(player_id: u64, players: &[PlayerState]) -> Option<(f32, f32)> {
@@ -509,26 +999,37 @@ fn apply_push_on_hit(
     source_x: f32,
     source_y: f32,
     players: &mut [PlayerState],
+    rules: &Ruleset,
 ) {
-    let mut strength = WEAPON_PUSH[weapon_id as usize];
+    let mut strength = rules.weapon_push[weapon_id as usize];
     if strength <= 0.0 {
         return;
     }
     let attacker_quad = has_quad_damage(players, attacker_id);
     if attacker_quad {
-        strength *= QUAD_MULTIPLIER;
+        strength *= rules.quad_multiplier;
     }
     if let Some(target) = players
         .iter_mut()
         .find(|player| player.id == target_id && !player.dead)
     {
-        apply_push_impulse(target, source_x, source_y, strength);
+        apply_push_impulse(target, source_x, source_y, strength, rules.knockback);
     }
 }
 
-fn apply_push_impulse(player: &mut PlayerState, source_x: f32, source_y: f32, strength: f32) {
+fn apply_push_impulse(
+    player: &mut PlayerState,
+    source_x: f32,
+    source_y: f32,
+    strength: f32,
+    knockback: KnockbackModel,
+) {
     let dx = source_x - player.x;
     let dy = source_y - player.y;
+    if knockback == KnockbackModel::Radial {
+        apply_radial_impulse(player, -dx, -dy, strength);
+        return;
+    }
     if dx < -0.01 {
         player.velocity_x += strength;
     } else if dx > 0.01 {
@@ -539,77 +1040,100 @@ fn apply_push_impulse(player: &mut PlayerState, source_x: f32, source_y: f32, st
     }
 }
 
-fn is_player_near_item(player: &PlayerState, item: &crate::map::MapItem) -> bool {
+fn is_player_near_item(player: &PlayerState, item: &MapItem) -> bool {
     let x = item.col as f32 * TILE_W + TILE_W / 2.0;
     let y = item.row as f32 * TILE_H + TILE_H / 2.0;
+    is_player_near(player, x, y)
+}
+
+fn is_player_near(player: &PlayerState, x: f32, y: f32) -> bool {
     let dx = player.x - x;
     let dy = player.y - y;
     dx * dx + dy * dy <= PICKUP_RADIUS_SQ
 }
 
-fn apply_item_effect(player: &mut PlayerState, item: &crate::map::MapItem) {
-    use crate::map::ItemKind;
-
+/// Returns false when the player can't take the item and it should stay.
+fn apply_item_effect(player: &mut PlayerState, item: &MapItem, rules: &Ruleset) -> bool {
+    if let Some((armor_type, amount)) = item.kind.armor() {
+        return player.give_armor(armor_type, amount, rules);
+    }
     match item.kind {
         ItemKind::Health5 => {
-            player.health = (player.health + 5).min(MAX_HEALTH);
+            player.health = (player.health + 5).min(rules.max_health);
         }
         ItemKind::Health25 => {
-            player.health = (player.health + 25).min(MAX_HEALTH);
+            player.health = (player.health + 25).min(rules.max_health);
         }
         ItemKind::Health50 => {
-            player.health = (player.health + 50).min(MAX_HEALTH);
+            player.health = (player.health + 50).min(rules.max_health);
         }
         ItemKind::Health100 => {
-            player.health = (player.health + 100).min(MEGA_HEALTH);
-        }
-        ItemKind::Armor50 => {
-            player.armor = (player.armor + 50).min(MAX_ARMOR);
+            player.health = (player.health + 100).min(rules.mega_health);
         }
-        ItemKind::Armor100 => {
-            player.armor = (player.armor + 100).min(MAX_ARMOR);
+        ItemKind::Quad => give_powerup(player, item.kind, rules.quad_duration),
+        ItemKind::Regeneration => give_powerup(player, item.kind, rules.regen_duration),
+        ItemKind::Haste => give_powerup(player, item.kind, rules.haste_duration),
+        ItemKind::BattleSuit => give_powerup(player, item.kind, rules.battle_suit_duration),
+        ItemKind::Invisibility => give_powerup(player, item.kind, rules.invisibility_duration),
+        kind => {
+            if let Some(weapon) = kind.weapon() {
+                give_weapon(player, weapon, rules.pickup_ammo[weapon as usize]);
+            } else if let Some(weapon) = kind.ammo() {
+                give_ammo(player, weapon, rules.ammo_box[weapon as usize]);
+            }
         }
-        ItemKind::Quad => {
-            player.quad_damage = true;
-            player.quad_timer = QUAD_DURATION;
-        }
-        ItemKind::WeaponMachine => give_weapon(
-            player,
-            WeaponId::Machine,
-            PICKUP_AMMO[WeaponId::Machine as usize],
-        ),
-        ItemKind::WeaponShotgun => give_weapon(
-            player,
-            WeaponId::Shotgun,
-            PICKUP_AMMO[WeaponId::Shotgun as usize],
-        ),
-        ItemKind::WeaponGrenade => give_weapon(
-            player,
-            WeaponId::Grenade,
-            PICKUP_AMMO[WeaponId::Grenade as usize],
-        ),
-        ItemKind::WeaponRocket => give_weapon(
-            player,
-            WeaponId::Rocket,
-            PICKUP_AMMO[WeaponId::Rocket as usize],
-        ),
     }
+    true
 }
 
 fn give_weapon(player: &mut PlayerState, weapon: WeaponId, ammo: i32) {
+    player.weapons[weapon as usize] = true;
+    give_ammo(player, weapon, ammo);
+}
+
+fn give_ammo(player: &mut PlayerState, weapon: WeaponId, ammo: i32) {
     let idx = weapon as usize;
-    player.weapons[idx] = true;
     if player.ammo[idx] != -1 {
         player.ammo[idx] += ammo;
     }
 }
 
-fn damage_for(weapon: WeaponId) -> f32 {
-    DAMAGE[weapon as usize]
+/// A pickup resets the powerup's timer to what the item carries.
+fn give_powerup(player: &mut PlayerState, kind: ItemKind, ticks: i32) {
+    let timer = match kind {
+        ItemKind::Quad => {
+            player.quad_damage = true;
+            &mut player.quad_timer
+        }
+        ItemKind::Regeneration => &mut player.regen_timer,
+        ItemKind::Haste => &mut player.haste_timer,
+        ItemKind::BattleSuit => &mut player.battle_suit_timer,
+        ItemKind::Invisibility => &mut player.invisibility_timer,
+        _ => return,
+    };
+    *timer = ticks;
 }
 
-fn fire_rate(weapon: WeaponId) -> i32 {
-    FIRE_RATE[weapon as usize]
+fn droppable_weapon(player: &PlayerState) -> Option<WeaponId> {
+    let weapon = WeaponId::try_from(player.current_weapon).ok()?;
+    let droppable = !matches!(weapon, WeaponId::Gauntlet | WeaponId::Machine)
+        && player.ammo[weapon as usize] > 0;
+    droppable.then_some(weapon)
+}
+
+fn active_powerups(player: &PlayerState) -> [(ItemKind, i32); 5] {
+    let quad_ticks = if player.quad_damage {
+        player.quad_timer
+    } else {
+        0
+    };
+    [
+        (ItemKind::Quad, quad_ticks),
+        (ItemKind::Regeneration, player.regen_timer),
+        (ItemKind::Haste, player.haste_timer),
+        (ItemKind::BattleSuit, player.battle_suit_timer),
+        (ItemKind::Invisibility, player.invisibility_timer),
+    ]
 }
 
 fn get_weapon_origin(player: &PlayerState) -> (f32, f32) {
@@ -633,7 +1157,9 @@ fn has_quad_damage(players: &[PlayerState], player_id: u64) -> bool {
 struct HitscanImpact {
     x: f32,
     y: f32,
+    t: f32,
     target_id: Option<u64>,
+    zone: HitZone,
 }
 
 fn find_hitscan_impact(
@@ -649,19 +1175,27 @@ fn find_hitscan_impact(
 
     let mut closest_id = None;
     let mut closest_t = f32::INFINITY;
+    let mut closest_zone = HitZone::Body;
 
     for target in players {
         if target.dead || target.id == attacker_id {
             continue;
         }
         // Keep a little width on hitscan traces for gameplay feel after moving off radial tests.
-        let box_ = player_hitbox(target.x, target.y, target.crouch, HITSCAN_AABB_PADDING);
-        let Some(t) = segment_aabb_t(start_x, start_y, end_x, end_y, box_) else {
+        let Some((t, zone)) = segment_hit_zone(
+            (start_x, start_y),
+            (end_x, end_y),
+            target.x,
+            target.y,
+            target.crouch,
+            HITSCAN_AABB_PADDING,
+        ) else {
             continue;
         };
         if t < closest_t {
             closest_t = t;
             closest_id = Some(target.id);
+            closest_zone = zone;
         }
     }
 
@@ -671,17 +1205,113 @@ fn find_hitscan_impact(
         return HitscanImpact {
             x: impact_x,
             y: impact_y,
+            t: closest_t,
             target_id: Some(target_id),
+            zone: closest_zone,
         };
     }
 
     HitscanImpact {
         x: end_x,
         y: end_y,
+        t: 1.0,
         target_id: None,
+        zone: HitZone::Body,
     }
 }
 
+fn can_be_shot(proj: &Projectile, attacker_id: u64) -> bool {
+    proj.active && proj.kind.is_detonatable() && proj.owner_id != attacker_id
+}
+
+/// Detonates the nearest enemy rocket or grenade crossed by the segment at or
+/// before `max_t`. Returns the segment parameter of the hit.
+fn shoot_first_projectile(
+    attacker_id: u64,
+    (start_x, start_y): (f32, f32),
+    (end_x, end_y): (f32, f32),
+    max_t: f32,
+    projectiles: &mut [Projectile],
+    events: &mut EventVec,
+    explosions: &mut Vec<Explosion>,
+) -> Option<f32> {
+    let mut closest: Option<(usize, f32)> = None;
+    for (idx, proj) in projectiles.iter().enumerate() {
+        if !can_be_shot(proj, attacker_id) {
+            continue;
+        }
+        let Some(t) = segment_aabb_t(start_x, start_y, end_x, end_y, proj.hitbox()) else {
+            continue;
+        };
+        if t <= max_t && closest.is_none_or(|(_, best_t)| t < best_t) {
+            closest = Some((idx, t));
+        }
+    }
+    let (idx, t) = closest?;
+    detonate(&mut projectiles[idx], events, explosions);
+    Some(t)
+}
+
+/// Detonates every enemy rocket or grenade crossed by the segment.
+fn shoot_projectiles_along(
+    attacker_id: u64,
+    (start_x, start_y): (f32, f32),
+    (end_x, end_y): (f32, f32),
+    projectiles: &mut [Projectile],
+    events: &mut EventVec,
+    explosions: &mut Vec<Explosion>,
+) {
+    for proj in projectiles.iter_mut() {
+        if !can_be_shot(proj, attacker_id) {
+            continue;
+        }
+        if segment_aabb_t(start_x, start_y, end_x, end_y, proj.hitbox()).is_some() {
+            detonate(proj, events, explosions);
+        }
+    }
+}
+
+/// All players crossed by the segment, nearest first.
+fn find_piercing_impacts(
+    attacker_id: u64,
+    start_x: f32,
+    start_y: f32,
+    end_x: f32,
+    end_y: f32,
+    players: &[PlayerState],
+) -> SmallVec<[HitscanImpact; 4]> {
+    let dx = end_x - start_x;
+    let dy = end_y - start_y;
+
+    let mut hits: SmallVec<[(f32, u64, HitZone); 4]> = SmallVec::new();
+    for target in players {
+        if target.dead || target.id == attacker_id {
+            continue;
+        }
+        if let Some((t, zone)) = segment_hit_zone(
+            (start_x, start_y),
+            (end_x, end_y),
+            target.x,
+            target.y,
+            target.crouch,
+            HITSCAN_AABB_PADDING,
+        ) {
+            hits.push((t, target.id, zone));
+        }
+    }
+    hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    hits.into_iter()
+        .map(|(t, target_id, zone)| HitscanImpact {
+            x: start_x + dx * t,
+            y: start_y + dy * t,
+            t,
+            target_id: Some(target_id),
+            zone,
+        })
+        .collect()
+}
+
 fn find_melee_target(
     attacker_id: u64,
     hit_x: f32,
@@ -747,3 +1377,380 @@ fn explode(proj: &mut Projectile, explosions: &mut Vec<Explosion>) {
     });
 }
 
+fn detonate(proj: &mut Projectile, events: &mut EventVec, explosions: &mut Vec<Explosion>) {
+    explode(proj, explosions);
+    events.push(EffectEvent::ProjectileRemove {
+        id: proj.id,
+        x: proj.x,
+        y: proj.y,
+        kind: proj.kind.as_u8(),
+    });
+}
+
+/// Removes a projectile without an explosion.
+fn remove_with_event(proj: &mut Projectile, events: &mut EventVec) {
+    proj.active = false;
+    events.push(EffectEvent::ProjectileRemove {
+        id: proj.id,
+        x: proj.x,
+        y: proj.y,
+        kind: proj.kind.as_u8(),
+    });
+}
+
+fn aabb_overlap(a: Aabb, b: Aabb) -> bool {
+    a.min_x <= b.max_x && a.max_x >= b.min_x && a.min_y <= b.max_y && a.max_y >= b.min_y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_explosions, apply_hit_actions, apply_spawn_loadout, drop_items_on_death,
+        process_item_pickups, resolve_projectile_collisions, respawn_if_ready_with_rng, EventVec,
+        Explosion, HitAction, IdGen, Projectile, ProjectileKind, WeaponId,
+    };
+    use crate::binary::EffectEvent;
+    use crate::map::GameMap;
+    use crate::physics::PlayerState;
+    use crate::spawn::{spawn_position, SpawnSelector};
+    use physics_core::{HitZone, Mutator, Ruleset};
+
+    const INTERACTING: Ruleset = Ruleset {
+        projectile_interactions: true,
+        ..Ruleset::DEFAULT
+    };
+
+    fn player_at(id: u64, x: f32, y: f32) -> PlayerState {
+        let mut player = PlayerState::new(id);
+        player.x = x;
+        player.y = y;
+        player
+    }
+
+    #[test]
+    fn rail_pierces_every_player_on_the_trace() {
+        let mut players = vec![
+            player_at(1, 0.0, 0.0),
+            player_at(2, 200.0, 0.0),
+            player_at(3, 100.0, 0.0),
+        ];
+        players[1].health = 50;
+        players[2].health = 50;
+        let actions = [HitAction::Hitscan {
+            attacker_id: 1,
+            weapon_id: WeaponId::Rail,
+            start_x: 0.0,
+            start_y: 0.0,
+            trace_x: 500.0,
+            trace_y: 0.0,
+            damage: 100.0,
+        }];
+        let mut events = EventVec::new();
+
+        apply_hit_actions(
+            &actions,
+            &mut players,
+            &mut [],
+            &mut events,
+            &mut Vec::new(),
+            &Ruleset::DEFAULT,
+        );
+
+        assert!(players[1].dead);
+        assert!(players[2].dead);
+        let (end_x, impacts) = events
+            .iter()
+            .find_map(|event| match event {
+                EffectEvent::Rail { end_x, impacts, .. } => Some((*end_x, impacts.clone())),
+                _ => None,
+            })
+            .expect("expected rail event");
+        assert_eq!(end_x, 500.0);
+        assert_eq!(impacts.len(), 2);
+        assert!(impacts[0].0 < impacts[1].0);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            EffectEvent::MultiKill {
+                attacker_id: 1,
+                count: 2
+            }
+        )));
+    }
+
+    #[test]
+    fn rail_headshot_scales_damage_when_hit_zones_enabled() {
+        let mut players = vec![player_at(1, 0.0, -18.0), player_at(2, 200.0, 0.0)];
+        players[1].armor = 0;
+        players[1].health = 200;
+        let actions = [HitAction::Hitscan {
+            attacker_id: 1,
+            weapon_id: WeaponId::Rail,
+            start_x: 0.0,
+            start_y: -18.0,
+            trace_x: 500.0,
+            trace_y: -18.0,
+            damage: 100.0,
+        }];
+        let rules = Ruleset {
+            hit_zones: true,
+            ..Ruleset::DEFAULT
+        };
+        let mut events = EventVec::new();
+
+        apply_hit_actions(
+            &actions,
+            &mut players,
+            &mut [],
+            &mut events,
+            &mut Vec::new(),
+            &rules,
+        );
+
+        assert_eq!(players[1].health, 50);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            EffectEvent::Damage {
+                target_id: 2,
+                amount: 150,
+                hit_zone,
+                ..
+            } if *hit_zone == HitZone::Head.as_u8()
+        )));
+    }
+
+    #[test]
+    fn vampire_heals_attacker_by_damage_dealt() {
+        let mut players = vec![player_at(1, 0.0, 0.0), player_at(2, 200.0, 0.0)];
+        players[0].health = 40;
+        let actions = [HitAction::Hitscan {
+            attacker_id: 1,
+            weapon_id: WeaponId::Rail,
+            start_x: 0.0,
+            start_y: 0.0,
+            trace_x: 500.0,
+            trace_y: 0.0,
+            damage: 80.0,
+        }];
+        let rules = Ruleset::DEFAULT.with_mutators(&[Mutator::Vampire]);
+        let mut events = EventVec::new();
+
+        apply_hit_actions(
+            &actions,
+            &mut players,
+            &mut [],
+            &mut events,
+            &mut Vec::new(),
+            &rules,
+        );
+
+        assert_eq!(players[1].health, 20);
+        assert_eq!(players[0].health, 80);
+    }
+
+    #[test]
+    fn battle_suit_blocks_splash_but_not_knockback() {
+        let mut players = vec![player_at(1, 0.0, 0.0), player_at(2, 10.0, 0.0)];
+        players[1].battle_suit_timer = 100;
+        let mut explosions = vec![Explosion {
+            x: 0.0,
+            y: 0.0,
+            kind: ProjectileKind::Rocket,
+            owner_id: 1,
+        }];
+        let mut events = EventVec::new();
+
+        apply_explosions(
+            &mut explosions,
+            &mut players,
+            &mut Vec::new(),
+            &mut events,
+            &mut Vec::new(),
+            &Ruleset::DEFAULT,
+        );
+
+        assert_eq!(players[1].health, 100);
+        assert!(players[1].velocity_x > 0.0);
+        assert!(players[0].health < 100);
+    }
+
+    #[test]
+    fn instagib_spawns_with_rail_only() {
+        let mut player = PlayerState::new(1);
+        let rules = Ruleset::DEFAULT.with_mutators(&[Mutator::Instagib]);
+
+        apply_spawn_loadout(&mut player, &rules);
+
+        assert_eq!(player.current_weapon, WeaponId::Rail as i32);
+        assert!(!player.weapons[WeaponId::Rocket as usize]);
+        assert_eq!(player.ammo[WeaponId::Rail as usize], -1);
+    }
+
+    #[test]
+    fn hitscan_detonates_enemy_rocket_before_player() {
+        let mut players = vec![player_at(1, 0.0, 0.0), player_at(2, 300.0, 0.0)];
+        let mut projectiles = vec![Projectile::new(
+            7,
+            ProjectileKind::Rocket,
+            150.0,
+            0.0,
+            -7.0,
+            0.0,
+            2,
+        )];
+        let actions = [HitAction::Hitscan {
+            attacker_id: 1,
+            weapon_id: WeaponId::Machine,
+            start_x: 0.0,
+            start_y: 0.0,
+            trace_x: 500.0,
+            trace_y: 0.0,
+            damage: 7.0,
+        }];
+        let mut events = EventVec::new();
+        let mut explosions = Vec::new();
+
+        apply_hit_actions(
+            &actions,
+            &mut players,
+            &mut projectiles,
+            &mut events,
+            &mut explosions,
+            &INTERACTING,
+        );
+
+        assert!(!projectiles[0].active);
+        assert_eq!(explosions.len(), 1);
+        assert_eq!(players[1].health, 100);
+    }
+
+    #[test]
+    fn explosions_chain_into_enemy_grenades_only() {
+        let mut players = Vec::new();
+        let mut projectiles = vec![
+            Projectile::new(1, ProjectileKind::Grenade, 20.0, 0.0, 0.0, 0.0, 2),
+            Projectile::new(2, ProjectileKind::Grenade, 70.0, 0.0, 0.0, 0.0, 1),
+            Projectile::new(3, ProjectileKind::Plasma, 10.0, 0.0, 0.0, 0.0, 2),
+        ];
+        let mut explosions = vec![Explosion {
+            x: 0.0,
+            y: 0.0,
+            kind: ProjectileKind::Rocket,
+            owner_id: 1,
+        }];
+
+        apply_explosions(
+            &mut explosions,
+            &mut players,
+            &mut projectiles,
+            &mut EventVec::new(),
+            &mut Vec::new(),
+            &INTERACTING,
+        );
+
+        // Player 2's grenade goes off and its blast reaches player 1's grenade.
+        assert_eq!(explosions.len(), 3);
+        assert_eq!(projectiles.len(), 1);
+        assert_eq!(projectiles[0].kind, ProjectileKind::Plasma);
+    }
+
+    #[test]
+    fn bfg_absorbs_enemy_plasma() {
+        let mut projectiles = vec![
+            Projectile::new(1, ProjectileKind::Bfg, 0.0, 0.0, 8.0, 0.0, 1),
+            Projectile::new(2, ProjectileKind::Plasma, 10.0, 0.0, -8.0, 0.0, 2),
+        ];
+
+        resolve_projectile_collisions(&mut projectiles, &mut EventVec::new());
+
+        assert_eq!(projectiles.len(), 1);
+        assert_eq!(projectiles[0].kind, ProjectileKind::Bfg);
+    }
+
+    #[test]
+    fn killed_player_drops_weapon_and_quad_for_others() {
+        let rules = Ruleset::DEFAULT;
+        let mut players = vec![player_at(1, 0.0, 0.0), player_at(2, 500.0, 0.0)];
+        players[0].weapons[WeaponId::Rocket as usize] = true;
+        players[0].ammo[WeaponId::Rocket as usize] = 7;
+        players[0].ready_weapon(WeaponId::Rocket as i32);
+        players[0].quad_damage = true;
+        players[0].quad_timer = 300;
+        players[0].dead = true;
+        let events = [EffectEvent::Damage {
+            attacker_id: 2,
+            target_id: 1,
+            amount: 100,
+            killed: true,
+            hit_zone: HitZone::Body.as_u8(),
+            weapon_id: WeaponId::Rail as i32,
+        }];
+        let mut dropped = Vec::new();
+
+        drop_items_on_death(
+            &events,
+            &mut players,
+            &mut dropped,
+            &mut IdGen::default(),
+            &rules,
+        );
+
+        assert_eq!(dropped.len(), 2);
+        assert!(!players[0].quad_damage);
+
+        // A pickup sets the timer to what the item carries, even if it was longer.
+        players[1].x = 0.0;
+        players[1].quad_damage = true;
+        players[1].quad_timer = 900;
+        let mut pickups = EventVec::new();
+        process_item_pickups(&mut players, &mut [], &mut dropped, &mut pickups, &rules);
+
+        assert!(dropped.is_empty());
+        assert_eq!(pickups.len(), 2);
+        assert!(players[1].weapons[WeaponId::Rocket as usize]);
+        assert_eq!(players[1].ammo[WeaponId::Rocket as usize], 7);
+        assert!(players[1].quad_damage);
+        assert_eq!(players[1].quad_timer, 300);
+    }
+
+    #[test]
+    fn telefrag_kills_whoever_stands_on_the_only_spawn() {
+        let map = GameMap {
+            rows: 2,
+            cols: 2,
+            bricks: vec![0; 4],
+            respawns: vec![(1, 0)],
+            items: Vec::new(),
+            name: "test".to_string(),
+        };
+        let rules = Ruleset {
+            telefrag: true,
+            ..Ruleset::DEFAULT
+        };
+        let (x, y) = spawn_position(1, 0);
+        let mut players = vec![player_at(1, 0.0, 0.0), player_at(2, x, y)];
+        players[0].dead = true;
+        let mut events = EventVec::new();
+
+        respawn_if_ready_with_rng(
+            0,
+            &mut players,
+            &map,
+            &mut SpawnSelector::default(),
+            &mut rand::thread_rng(),
+            &mut events,
+            &rules,
+        );
+
+        assert!(!players[0].dead);
+        assert!(players[1].dead);
+        assert!(matches!(
+            events[0],
+            EffectEvent::Damage {
+                attacker_id: 1,
+                target_id: 2,
+                killed: true,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,20 @@
+#![forbid(unsafe_code)]
+#![deny(rust_2018_idioms)]
+#![deny(clippy::unwrap_used)]
+#![deny(clippy::panic)]
+
+pub mod analysis;
+pub mod binary;
+pub mod constants;
+pub mod demo;
+pub mod game;
+pub mod map;
+pub mod physics;
+pub mod playback;
+pub mod resume;
+pub mod room;
+pub mod room_manager;
+pub mod session;
+pub mod spawn;
+pub mod tick;
+pub mod udp;
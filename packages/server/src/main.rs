@@ -5,11 +5,10 @@
 
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
-use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::extract::{ConnectInfo, Query, State};
@@ -20,67 +19,56 @@ use axum::Router;
 use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, Mutex};
-use tracing::{error, info, warn};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::setting_engine::SettingEngine;
 use webrtc::api::APIBuilder;
 use webrtc::ice::mdns::MulticastDnsMode;
 use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
 use webrtc::ice_transport::ice_credential_type::RTCIceCredentialType;
 use webrtc::ice_transport::ice_server::RTCIceServer;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 
-mod binary;
-mod constants;
-mod game;
-mod map;
-mod physics;
-mod room;
-mod room_manager;
-
-use crate::binary::{decode_client_message, encode_pong, encode_welcome, ClientMsg};
-use crate::constants::{
-    DEFAULT_MAP_DIR, DEFAULT_MAP_NAME, DEFAULT_PORT, DEFAULT_ROOM_ID, OUTBOUND_CHANNEL_CAPACITY,
-    ROOM_COMMAND_CAPACITY,
+use physics_core::Ruleset;
+use raster_arena_server::binary::{decode_client_message, ClientMsg};
+use raster_arena_server::constants::{
+    DEFAULT_MAP_DIR, DEFAULT_PORT, DEFAULT_RULESET_DIR, ROOM_COMMAND_CAPACITY, UDP_IDLE_TIMEOUT_MS,
+};
+use raster_arena_server::resume::ResumeSlots;
+use raster_arena_server::room_manager::{RoomManager, ROOM_MAX_SPECTATORS_HARD_CAP};
+use raster_arena_server::session::{
+    load_ruleset, run_session, AppState, TicketClaims, Transport, TransportClosed,
+};
+use raster_arena_server::tick::{MissedTickPolicy, TickMetricsSnapshot};
+use raster_arena_server::udp::{
+    decode_udp_packet, encode_udp_close, encode_udp_cookie, encode_udp_data, CookieKey,
+    SessionTag, UdpPacket,
 };
-use crate::game::WeaponId;
-use crate::map::GameMap;
-use crate::room::{PlayerId, PlayerInput, RoomConfig, RoomHandle};
-use crate::room_manager::RoomManager;
-
-struct AppState {
-    room_manager: Arc<RoomManager>,
-    next_player_id: AtomicU64,
-    map_dir: PathBuf,
-    started_at: Instant,
-    max_connections_per_ip: usize,
-    max_message_bytes: usize,
-    max_players_per_room: usize,
-    ip_connections: tokio::sync::Mutex<HashMap<IpAddr, usize>>,
-    game_secret: Option<String>,
-}
 
-#[derive(Debug, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct TicketClaims {
-    room_id: String,
-    #[allow(dead_code)]
-    session_id: String,
-    exp: u64,
+#[derive(Debug, Deserialize)]
+struct RtcQuery {
+    ticket: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct RtcQuery {
+struct WsQuery {
     ticket: Option<String>,
+    /// Token from an earlier welcome, to take back that player's slot.
+    resume: Option<String>,
 }
 
-enum RtcCmd {
-    Client(ClientMsg),
-    Shutdown,
+/// A connected UDP peer, keyed by its address. Dropping it ends the session.
+struct UdpPeer {
+    /// Packets without it come from someone else spoofing the address.
+    tag: SessionTag,
+    inbound_tx: mpsc::Sender<ClientMsg>,
+    welcome: Arc<OnceLock<Bytes>>,
+    last_seen: Instant,
 }
 
 #[derive(Debug, Deserialize)]
@@ -88,6 +76,9 @@ struct RtcSignalIn {
     #[serde(rename = "type")]
     msg_type: String,
     sdp: Option<String>,
+    /// Token from an earlier welcome, to take back that player's slot.
+    #[serde(default)]
+    resume: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -121,16 +112,50 @@ async fn main() -> std::io::Result<()> {
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(8);
+    let max_spectators_per_room: usize = std::env::var("MAX_SPECTATORS_PER_ROOM")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8)
+        .min(ROOM_MAX_SPECTATORS_HARD_CAP);
     let max_message_bytes: usize = std::env::var("MAX_MESSAGE_BYTES")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(65536);
+    // How rooms catch up after a slow tick: burst (default), skip or slow.
+    let missed_tick_policy = match std::env::var("MISSED_TICK_POLICY") {
+        Ok(name) => match MissedTickPolicy::from_name(&name) {
+            Some(policy) => policy,
+            None => return Err(std::io::Error::other(format!("bad MISSED_TICK_POLICY '{name}'"))),
+        },
+        Err(_) => MissedTickPolicy::default(),
+    };
+
+    let ruleset_dir = std::env::var("RULESET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_RULESET_DIR));
+    // Rooms created without an explicit ruleset use DEFAULT_RULESET, or the built-in values.
+    let default_ruleset = match std::env::var("DEFAULT_RULESET") {
+        Ok(name) => match load_ruleset(&ruleset_dir, &Ruleset::default(), &name) {
+            Some(rules) => rules,
+            None => return Err(std::io::Error::other(format!("bad DEFAULT_RULESET '{name}'"))),
+        },
+        Err(_) => Ruleset::default(),
+    };
+
+    // Rooms record demos only when DEMO_DIR is set.
+    let demo_dir = std::env::var("DEMO_DIR")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    if let Some(dir) = &demo_dir {
+        info!("recording demos to {}", dir.display());
+    }
 
     let game_secret = std::env::var("GAME_SECRET").ok().filter(|s| !s.is_empty());
     if game_secret.is_some() {
-        info!("GAME_SECRET configured: ticket validation enabled for /rtc");
+        info!("GAME_SECRET configured: ticket validation enabled for /rtc, /ws and UDP");
     } else {
-        info!("GAME_SECRET not set: /rtc open to all connections");
+        info!("GAME_SECRET not set: game transports open to all connections");
     }
 
     let state = Arc::new(AppState {
@@ -141,17 +166,29 @@ async fn main() -> std::io::Result<()> {
         )),
         next_player_id: AtomicU64::new(1),
         map_dir,
+        ruleset_dir,
+        default_ruleset,
+        demo_dir,
         started_at: Instant::now(),
         max_connections_per_ip,
         max_message_bytes,
         max_players_per_room,
+        max_spectators_per_room,
+        missed_tick_policy,
         ip_connections: tokio::sync::Mutex::new(HashMap::new()),
+        resume_slots: tokio::sync::Mutex::new(ResumeSlots::default()),
         game_secret,
     });
 
-    let app = Router::new()
-        .route("/rtc", get(rtc_ws_handler))
-        .with_state(state);
+    // Native clients and bots can skip WebRTC when UDP_PORT is set.
+    if let Ok(udp_port) = std::env::var("UDP_PORT") {
+        let udp_addr = format!("0.0.0.0:{udp_port}");
+        let socket = tokio::net::UdpSocket::bind(&udp_addr).await?;
+        info!("udp listening on {udp_addr}");
+        tokio::spawn(run_udp_listener(Arc::clone(&state), Arc::new(socket)));
+    }
+
+    let app = router(state);
 
     let port = std::env::var("PORT").unwrap_or_else(|_| DEFAULT_PORT.to_string());
     let addr = format!("0.0.0.0:{port}");
@@ -166,6 +203,13 @@ async fn main() -> std::io::Result<()> {
     .await
 }
 
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/rtc", get(rtc_ws_handler))
+        .route("/ws", get(game_ws_handler))
+        .route("/metrics", get(metrics_handler))
+        .with_state(state)
+}
 
 async fn rtc_ws_handler(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
@@ -173,31 +217,85 @@ async fn rtc_ws_handler(
     Query(query): Query<RtcQuery>,
     ws: WebSocketUpgrade,
 ) -> axum::response::Response {
-    let claimed_room_id = if let Some(secret) = &state.game_secret {
-        let Some(ticket) = &query.ticket else {
-            return StatusCode::UNAUTHORIZED.into_response();
-        };
-        match verify_game_ticket(ticket, secret) {
-            Some(c) => Some(c.room_id),
-            None => return StatusCode::UNAUTHORIZED.into_response(),
-        }
-    } else {
-        None
+    let ticket = match authorize_ticket(&state, query.ticket.as_deref()) {
+        Ok(ticket) => ticket,
+        Err(status) => return status.into_response(),
+    };
+
+    let client_ip = addr.ip();
+    ws.on_upgrade(move |socket| handle_rtc_socket(state, socket, client_ip, ticket))
+        .into_response()
+}
+
+/// Plays over the WebSocket itself, for clients whose WebRTC can't connect.
+async fn game_ws_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WsQuery>,
+    ws: WebSocketUpgrade,
+) -> axum::response::Response {
+    let ticket = match authorize_ticket(&state, query.ticket.as_deref()) {
+        Ok(ticket) => ticket,
+        Err(status) => return status.into_response(),
     };
 
     let client_ip = addr.ip();
-    ws.on_upgrade(move |socket| handle_rtc_socket(state, socket, client_ip, claimed_room_id))
+    ws.on_upgrade(move |socket| handle_game_socket(state, socket, client_ip, ticket, query.resume))
         .into_response()
 }
 
+/// Room counters and per-room tick timing in the Prometheus text format.
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    let counters = &state.room_manager.metrics;
+    for (name, counter) in [
+        ("rooms_created_total", &counters.rooms_created_total),
+        ("players_joined_total", &counters.players_joined_total),
+        ("players_left_total", &counters.players_left_total),
+    ] {
+        let _ = writeln!(out, "# TYPE raster_{name} counter");
+        let _ = writeln!(out, "raster_{name} {}", counter.load(Ordering::Relaxed));
+    }
+
+    let rooms = state.room_manager.room_tick_metrics().await;
+    type Field = fn(&TickMetricsSnapshot) -> f64;
+    let families: [(&str, &str, Field); 5] = [
+        ("room_ticks_total", "counter", |m| m.ticks_total as f64),
+        ("room_tick_overruns_total", "counter", |m| m.overruns_total as f64),
+        ("room_dropped_ticks_total", "counter", |m| m.dropped_ticks_total as f64),
+        ("room_last_tick_seconds", "gauge", |m| m.last_tick_micros as f64 / 1e6),
+        ("room_max_tick_seconds", "gauge", |m| m.max_tick_micros as f64 / 1e6),
+    ];
+    for (name, kind, value) in families {
+        let _ = writeln!(out, "# TYPE raster_{name} {kind}");
+        for (room, metrics) in &rooms {
+            // Room names come from clients; escape them as label values.
+            let room = room.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            let _ = writeln!(out, "raster_{name}{{room=\"{room}\"}} {}", value(metrics));
+        }
+    }
+    out
+}
+
+/// Tickets are required only when a game secret is configured.
+fn authorize_ticket(state: &AppState, ticket: Option<&str>) -> Result<Option<TicketClaims>, StatusCode> {
+    let Some(secret) = &state.game_secret else {
+        return Ok(None);
+    };
+    let ticket = ticket.ok_or(StatusCode::UNAUTHORIZED)?;
+    verify_game_ticket(ticket, secret)
+        .map(Some)
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
 fn verify_game_ticket(token: &str, secret: &str) -> Option<TicketClaims> {
     use base64::Engine as _;
     use hmac::Mac as _;
     let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
 
-    let mut parts = token.splitn(2, '.');
-    let encoded = parts.next()?;
-    let sig_b64 = parts.next()?;
+    let (encoded, sig_b64) = token.split_once('.')?;
 
     let sig_bytes = engine.decode(sig_b64).ok()?;
 
@@ -221,36 +319,285 @@ fn verify_game_ticket(token: &str, secret: &str) -> Option<TicketClaims> {
 }
 
 /// Outer handler: enforces per-IP connection limit, then delegates to inner.
-async fn handle_rtc_socket(state: Arc<AppState>, socket: WebSocket, client_ip: IpAddr, claimed_room_id: Option<String>) {
-    {
-        let mut ip_conns = state.ip_connections.lock().await;
-        let count = ip_conns.entry(client_ip).or_insert(0);
-        if *count >= state.max_connections_per_ip {
-            warn!(
-                %client_ip,
-                limit = state.max_connections_per_ip,
-                "connection rejected: per-IP limit reached"
-            );
-            return;
+async fn handle_rtc_socket(state: Arc<AppState>, socket: WebSocket, client_ip: IpAddr, ticket: Option<TicketClaims>) {
+    if !acquire_ip_connection(&state, client_ip).await {
+        return;
+    }
+    handle_rtc_socket_inner(Arc::clone(&state), socket, ticket).await;
+    release_ip_connection(&state, client_ip).await;
+}
+
+/// Outer handler for `/ws`, with the same per-IP limit as `/rtc`.
+async fn handle_game_socket(
+    state: Arc<AppState>,
+    socket: WebSocket,
+    client_ip: IpAddr,
+    ticket: Option<TicketClaims>,
+    resume: Option<String>,
+) {
+    if !acquire_ip_connection(&state, client_ip).await {
+        return;
+    }
+    handle_game_socket_inner(Arc::clone(&state), socket, ticket, resume).await;
+    release_ip_connection(&state, client_ip).await;
+}
+
+async fn acquire_ip_connection(state: &AppState, client_ip: IpAddr) -> bool {
+    let mut ip_conns = state.ip_connections.lock().await;
+    let count = ip_conns.entry(client_ip).or_insert(0);
+    if *count >= state.max_connections_per_ip {
+        warn!(
+            %client_ip,
+            limit = state.max_connections_per_ip,
+            "connection rejected: per-IP limit reached"
+        );
+        return false;
+    }
+    *count += 1;
+    true
+}
+
+async fn release_ip_connection(state: &AppState, client_ip: IpAddr) {
+    let mut ip_conns = state.ip_connections.lock().await;
+    if let Some(count) = ip_conns.get_mut(&client_ip) {
+        if *count <= 1 {
+            ip_conns.remove(&client_ip);
+        } else {
+            *count -= 1;
+        }
+    }
+}
+
+/// The `/ws` transport: binary protocol messages as WebSocket binary frames.
+struct WsTransport {
+    socket: WebSocket,
+    max_message_bytes: usize,
+}
+
+impl Transport for WsTransport {
+    async fn recv(&mut self) -> Option<ClientMsg> {
+        loop {
+            match self.socket.recv().await {
+                Some(Ok(Message::Binary(data))) => {
+                    if data.len() > self.max_message_bytes {
+                        continue;
+                    }
+                    if let Ok(client_msg) = decode_client_message(&data) {
+                        return Some(client_msg);
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return None,
+                Some(Ok(_)) => {}
+            }
         }
-        *count += 1;
     }
 
-    handle_rtc_socket_inner(Arc::clone(&state), socket, claimed_room_id).await;
+    async fn send(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        self.socket
+            .send(Message::Binary(payload.to_vec()))
+            .await
+            .map_err(|_| TransportClosed)
+    }
+}
+
+async fn handle_game_socket_inner(
+    state: Arc<AppState>,
+    socket: WebSocket,
+    ticket: Option<TicketClaims>,
+    resume: Option<String>,
+) {
+    let transport = WsTransport {
+        socket,
+        max_message_bytes: state.max_message_bytes,
+    };
+    run_session(&state, transport, ticket, resume.as_deref()).await;
+}
+
+/// A UDP peer's transport. The listener decodes datagrams and forwards them
+/// here; replies go straight out of the shared socket.
+struct UdpTransport {
+    socket: Arc<tokio::net::UdpSocket>,
+    peer: SocketAddr,
+    tag: SessionTag,
+    inbound: mpsc::Receiver<ClientMsg>,
+    welcome: Arc<OnceLock<Bytes>>,
+}
+
+impl Transport for UdpTransport {
+    async fn recv(&mut self) -> Option<ClientMsg> {
+        self.inbound.recv().await
+    }
+
+    async fn send(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        // Datagrams can be dropped anyway; only a closed peer ends the session.
+        let _ = self
+            .socket
+            .send_to(&encode_udp_data(&self.tag, &payload), self.peer)
+            .await;
+        Ok(())
+    }
+
+    async fn send_welcome(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        // Kept so a repeated CONNECT can be answered if this one is lost.
+        let _ = self.welcome.set(payload.clone());
+        self.send(payload).await
+    }
+}
+
+async fn run_udp_listener(state: Arc<AppState>, socket: Arc<tokio::net::UdpSocket>) {
+    let cookie_key = CookieKey::random();
+    let mut peers: HashMap<SocketAddr, UdpPeer> = HashMap::new();
+    let mut sweep = tokio::time::interval(Duration::from_secs(1));
+    let idle_timeout = Duration::from_millis(UDP_IDLE_TIMEOUT_MS);
+    let mut buf = vec![0u8; 65536];
+
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let Ok((len, peer)) = received else {
+                    continue;
+                };
+                handle_udp_packet(&state, &socket, &cookie_key, &mut peers, peer, &buf[..len]).await;
+            }
+            _ = sweep.tick() => {
+                let now = Instant::now();
+                peers.retain(|_, peer| now.duration_since(peer.last_seen) < idle_timeout);
+            }
+        }
+    }
+}
+
+async fn handle_udp_packet(
+    state: &Arc<AppState>,
+    socket: &Arc<tokio::net::UdpSocket>,
+    cookie_key: &CookieKey,
+    peers: &mut HashMap<SocketAddr, UdpPeer>,
+    peer: SocketAddr,
+    data: &[u8],
+) {
+    let now_ms = state.started_at.elapsed().as_millis() as u64;
+    // A session that ended on its own leaves a closed entry behind.
+    if peers.get(&peer).is_some_and(|p| p.inbound_tx.is_closed()) {
+        peers.remove(&peer);
+    }
+
+    match decode_udp_packet(data) {
+        Some(UdpPacket::Hello) => {
+            let cookie = cookie_key.issue(peer, now_ms);
+            let _ = socket.send_to(&encode_udp_cookie(&cookie), peer).await;
+        }
+        Some(UdpPacket::Connect { cookie, ticket, resume }) => {
+            let tag = cookie.session_tag();
+            // The welcome may have been lost; send it again.
+            if let Some(existing) = peers.get_mut(&peer) {
+                if existing.tag != tag {
+                    return;
+                }
+                existing.last_seen = Instant::now();
+                if let Some(welcome) = existing.welcome.get() {
+                    let _ = socket.send_to(&encode_udp_data(&tag, welcome), peer).await;
+                }
+                return;
+            }
+            if !cookie_key.verify(peer, &cookie, now_ms) {
+                return;
+            }
+            let Ok(ticket) = authorize_ticket(state, ticket) else {
+                let _ = socket.send_to(&encode_udp_close(&tag), peer).await;
+                return;
+            };
+            if !acquire_ip_connection(state, peer.ip()).await {
+                let _ = socket.send_to(&encode_udp_close(&tag), peer).await;
+                return;
+            }
 
-    {
-        let mut ip_conns = state.ip_connections.lock().await;
-        if let Some(count) = ip_conns.get_mut(&client_ip) {
-            if *count <= 1 {
-                ip_conns.remove(&client_ip);
-            } else {
-                *count -= 1;
+            let (inbound_tx, inbound) = mpsc::channel(ROOM_COMMAND_CAPACITY);
+            let welcome = Arc::new(OnceLock::new());
+            peers.insert(
+                peer,
+                UdpPeer {
+                    tag,
+                    inbound_tx,
+                    welcome: Arc::clone(&welcome),
+                    last_seen: Instant::now(),
+                },
+            );
+            let transport = UdpTransport {
+                socket: Arc::clone(socket),
+                peer,
+                tag,
+                inbound,
+                welcome,
+            };
+            let state = Arc::clone(state);
+            let resume = resume.map(str::to_string);
+            tokio::spawn(async move {
+                run_session(&state, transport, ticket, resume.as_deref()).await;
+                release_ip_connection(&state, peer.ip()).await;
+            });
+        }
+        Some(UdpPacket::Data { tag, payload }) => {
+            let Some(existing) = peers.get_mut(&peer) else {
+                // Tell stale clients to handshake again.
+                let _ = socket.send_to(&encode_udp_close(&tag), peer).await;
+                return;
+            };
+            if existing.tag != tag {
+                return;
+            }
+            existing.last_seen = Instant::now();
+            if payload.len() > state.max_message_bytes {
+                return;
+            }
+            let Ok(client_msg) = decode_client_message(payload) else {
+                return;
+            };
+            let _ = existing.inbound_tx.try_send(client_msg);
+        }
+        Some(UdpPacket::Close { tag }) => {
+            if peers.get(&peer).is_some_and(|existing| existing.tag == tag) {
+                peers.remove(&peer);
             }
         }
+        Some(UdpPacket::Cookie(_)) | None => {}
+    }
+}
+
+/// The `/rtc` transport. Both data channels feed `inbound`; the welcome goes
+/// out on the reliable control channel and everything else on the game one.
+struct RtcTransport {
+    control: Arc<RTCDataChannel>,
+    game: Arc<RTCDataChannel>,
+    inbound: mpsc::Receiver<ClientMsg>,
+    closed: mpsc::Receiver<()>,
+}
+
+impl Transport for RtcTransport {
+    async fn recv(&mut self) -> Option<ClientMsg> {
+        tokio::select! {
+            client_msg = self.inbound.recv() => client_msg,
+            _ = self.closed.recv() => None,
+        }
+    }
+
+    async fn send(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        self.game
+            .send(&payload)
+            .await
+            .map(drop)
+            .map_err(|_| TransportClosed)
+    }
+
+    async fn send_welcome(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        self.control
+            .send(&payload)
+            .await
+            .map(drop)
+            .map_err(|_| TransportClosed)
     }
 }
 
-async fn handle_rtc_socket_inner(state: Arc<AppState>, socket: WebSocket, claimed_room_id: Option<String>) {
+async fn handle_rtc_socket_inner(state: Arc<AppState>, socket: WebSocket, ticket: Option<TicketClaims>) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     let Some(Ok(Message::Text(offer_text))) = ws_receiver.next().await else {
@@ -325,44 +672,6 @@ async fn handle_rtc_socket_inner(state: Arc<AppState>, socket: WebSocket, claime
         Err(_) => return,
     };
     let (pc_closed_tx, mut pc_closed_rx) = mpsc::channel::<()>(1);
-
-    let (game_outbound_tx, game_outbound_rx) = mpsc::channel::<Bytes>(OUTBOUND_CHANNEL_CAPACITY);
-    let game_outbound_rx = Arc::new(Mutex::new(Some(game_outbound_rx)));
-    let (rtc_cmd_tx, mut rtc_cmd_rx) = mpsc::channel::<RtcCmd>(ROOM_COMMAND_CAPACITY);
-
-    let player_id = PlayerId(state.next_player_id.fetch_add(1, Ordering::Relaxed));
-    let state_for_session = Arc::clone(&state);
-    let game_outbound_tx_for_session = game_outbound_tx.clone();
-    let session_task = tokio::spawn(async move {
-        let mut current_room: Option<Arc<RoomHandle>> = None;
-        let mut username = format!("player{}", player_id.0);
-
-        while let Some(cmd) = rtc_cmd_rx.recv().await {
-            match cmd {
-                RtcCmd::Client(client_msg) => {
-                    let keep_running = handle_client_msg(
-                        &state_for_session,
-                        &mut current_room,
-                        &mut username,
-                        player_id,
-                        client_msg,
-                        &game_outbound_tx_for_session,
-                        claimed_room_id.as_deref(),
-                    )
-                    .await;
-                    if !keep_running {
-                        break;
-                    }
-                }
-                RtcCmd::Shutdown => break,
-            }
-        }
-
-        if let Some(room) = current_room.take() {
-            room.leave(player_id);
-        }
-    });
-
     peer_connection.on_peer_connection_state_change(Box::new(move |state| {
         let pc_closed_tx2 = pc_closed_tx.clone();
         Box::pin(async move {
@@ -376,58 +685,32 @@ async fn handle_rtc_socket_inner(state: Arc<AppState>, socket: WebSocket, claime
     }));
 
     let max_message_bytes = state.max_message_bytes;
-    let game_outbound_rx_for_dc = Arc::clone(&game_outbound_rx);
-    let rtc_cmd_tx_for_dc = rtc_cmd_tx.clone();
+    let (inbound_tx, inbound) = mpsc::channel::<ClientMsg>(ROOM_COMMAND_CAPACITY);
+    let (open_tx, mut open_rx) = mpsc::channel::<Arc<RTCDataChannel>>(2);
     peer_connection.on_data_channel(Box::new(move |dc| {
         let label = dc.label().to_string();
         if label != "control" && label != "game" {
             return Box::pin(async {});
         }
-        let game_outbound_rx_for_msg = Arc::clone(&game_outbound_rx_for_dc);
-        let rtc_cmd_tx_for_msg = rtc_cmd_tx_for_dc.clone();
+        let inbound_tx_for_msg = inbound_tx.clone();
+        let open_tx_for_dc = open_tx.clone();
 
         Box::pin(async move {
-            if label == "control" {
-                let dc_for_open = Arc::clone(&dc);
-                dc.on_open(Box::new(move || {
-                    let dc_for_open2 = Arc::clone(&dc_for_open);
-                    Box::pin(async move {
-                        let _ = dc_for_open2
-                            .send(&Bytes::from(encode_welcome(player_id.0)))
-                            .await;
-                    })
-                }));
-            }
-
-            dc.on_message(Box::new(move |msg: DataChannelMessage| {
-                let rtc_cmd_tx_for_msg2 = rtc_cmd_tx_for_msg.clone();
-                Box::pin(async move {
-                    if msg.is_string {
-                        return;
-                    }
-                    if msg.data.len() > max_message_bytes {
-                        return;
-                    }
-                    let Ok(client_msg) = decode_client_message(&msg.data) else {
-                        return;
-                    };
-                    let _ = rtc_cmd_tx_for_msg2.try_send(RtcCmd::Client(client_msg));
-                })
+            let dc_for_open = Arc::clone(&dc);
+            dc.on_open(Box::new(move || {
+                let _ = open_tx_for_dc.try_send(dc_for_open);
+                Box::pin(async {})
             }));
 
-            if label == "game" {
-                let mut maybe_rx = game_outbound_rx_for_msg.lock().await;
-                if let Some(mut rx) = maybe_rx.take() {
-                    let dc_for_send = Arc::clone(&dc);
-                    tokio::spawn(async move {
-                        while let Some(payload) = rx.recv().await {
-                            if dc_for_send.send(&payload).await.is_err() {
-                                break;
-                            }
-                        }
-                    });
+            dc.on_message(Box::new(move |msg: DataChannelMessage| {
+                if msg.is_string || msg.data.len() > max_message_bytes {
+                    return Box::pin(async {});
                 }
-            }
+                if let Ok(client_msg) = decode_client_message(&msg.data) {
+                    let _ = inbound_tx_for_msg.try_send(client_msg);
+                }
+                Box::pin(async {})
+            }));
         })
     }));
 
@@ -466,150 +749,33 @@ async fn handle_rtc_socket_inner(state: Arc<AppState>, socket: WebSocket, claime
 
     drop(ws_sender);
     drop(ws_receiver);
-    let _ = pc_closed_rx.recv().await;
-
-    let _ = rtc_cmd_tx.try_send(RtcCmd::Shutdown);
-    if let Err(err) = session_task.await {
-        error!(
-            player_id = player_id.0,
-            "rtc session task join error: {err}"
-        );
-    }
-    let _ = peer_connection.close().await;
-}
 
-async fn handle_client_msg(
-    state: &Arc<AppState>,
-    current_room: &mut Option<Arc<crate::room::RoomHandle>>,
-    username: &mut String,
-    player_id: PlayerId,
-    msg: ClientMsg,
-    outbound_tx: &mpsc::Sender<Bytes>,
-    claimed_room_id: Option<&str>,
-) -> bool {
-    match msg {
-        ClientMsg::Hello {
-            username: requested_name,
-        } => {
-            if current_room.is_some() {
-                info!(player_id = player_id.0, "ignoring hello after room join");
-                return true;
-            }
-            if !requested_name.is_empty() {
-                *username = requested_name;
-            }
-            true
-        }
-        ClientMsg::JoinRoom { room_id, map } => {
-            let room_ref = if let Some(claimed) = claimed_room_id {
-                if let Some(ref client_room) = room_id {
-                    if client_room != claimed {
-                        warn!(
-                            player_id = player_id.0,
-                            client_room_id = client_room,
-                            ticket_room_id = claimed,
-                            "JoinRoom: client room_id overridden by ticket"
-                        );
-                    }
-                }
-                claimed.to_string()
-            } else {
-                room_id.unwrap_or_else(|| DEFAULT_ROOM_ID.to_string())
-            };
-            let map_name = map.unwrap_or_else(|| DEFAULT_MAP_NAME.to_string());
-            let Some(game_map) = load_map(&state.map_dir, &map_name) else {
-                warn!(
-                    player_id = player_id.0,
-                    room_ref, "join rejected: room map unavailable"
-                );
-                return true;
-            };
-            let config = RoomConfig {
-                name: room_ref.clone(),
-                max_players: state.max_players_per_room,
-                tick_rate: 60,
-            };
-            let Ok(target_room) = state
-                .room_manager
-                .get_or_create_room(config, game_map)
-                .await
-            else {
-                warn!(
-                    player_id = player_id.0,
-                    room_ref, "join rejected: room create failed"
-                );
-                return true;
-            };
-
-            match state
-                .room_manager
-                .join_room(
-                    player_id,
-                    username.clone(),
-                    Arc::clone(&target_room),
-                    outbound_tx.clone(),
-                )
-                .await
-            {
-                Ok(success) => {
-                    if outbound_tx.try_send(success.room_state).is_err() {
-                        return false;
-                    }
-                    *current_room = Some(success.room);
-                    true
-                }
-                Err(rejection) => {
-                    let _ = outbound_tx.try_send(rejection);
-                    true
+    // The session starts once both channels are open, so the welcome can't be lost.
+    let mut control = None;
+    let mut game = None;
+    while control.is_none() || game.is_none() {
+        tokio::select! {
+            Some(dc) = open_rx.recv() => {
+                if dc.label() == "control" {
+                    control = Some(dc);
+                } else {
+                    game = Some(dc);
                 }
             }
-        }
-        ClientMsg::Input {
-            seq,
-            key_up,
-            key_down,
-            key_left,
-            key_right,
-            mouse_down,
-            weapon_switch,
-            weapon_scroll,
-            aim_angle,
-            facing_left,
-        } => {
-            let Some(room) = current_room.as_ref() else {
-                return true;
-            };
-            let input = PlayerInput {
-                key_up,
-                key_down,
-                key_left,
-                key_right,
-                mouse_down,
-                weapon_switch: WeaponId::try_from(weapon_switch).ok(),
-                weapon_scroll: weapon_scroll as i8,
-                aim_angle,
-                facing_left,
-            };
-            room.set_input(player_id, seq, input);
-            true
-        }
-        ClientMsg::Ping { client_time_ms } => {
-            let server_time_ms = state.started_at.elapsed().as_millis() as u64;
-            let _ = outbound_tx.try_send(Bytes::from(encode_pong(client_time_ms, server_time_ms)));
-            true
+            _ = pc_closed_rx.recv() => break,
         }
     }
-}
-
 
-fn load_map(map_dir: &Path, map_name: &str) -> Option<GameMap> {
-    match GameMap::load(map_dir, map_name) {
-        Ok(map) => Some(map),
-        Err(primary_err) => {
-            error!("failed to load map '{map_name}': {primary_err}");
-            None
-        }
+    if let (Some(control), Some(game)) = (control, game) {
+        let transport = RtcTransport {
+            control,
+            game,
+            inbound,
+            closed: pc_closed_rx,
+        };
+        run_session(&state, transport, ticket, signal.resume.as_deref()).await;
     }
+    let _ = peer_connection.close().await;
 }
 
 fn load_turn_server() -> Option<RTCIceServer> {
@@ -630,3 +796,116 @@ fn load_turn_server() -> Option<RTCIceServer> {
 
     Some(server)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use base64::Engine as _;
+    use futures_util::StreamExt;
+    use hmac::Mac as _;
+    use physics_core::Ruleset;
+    use raster_arena_server::resume::ResumeSlots;
+    use raster_arena_server::room_manager::RoomManager;
+    use raster_arena_server::session::AppState;
+    use raster_arena_server::tick::MissedTickPolicy;
+    use tokio_tungstenite::tungstenite::Error as WsError;
+
+    use super::router;
+
+    const SECRET: &str = "test-secret";
+
+    fn ticket(secret: &str) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let exp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis() as u64 + 60_000);
+        let claims = format!(r#"{{"roomId":"test","sessionId":"s1","exp":{exp}}}"#);
+        let encoded = engine.encode(claims);
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes())
+            .expect("hmac takes any key length");
+        mac.update(encoded.as_bytes());
+        let sig = engine.encode(mac.finalize().into_bytes());
+        format!("{encoded}.{sig}")
+    }
+
+    async fn serve(max_connections_per_ip: usize) -> (Arc<AppState>, SocketAddr) {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let state = Arc::new(AppState {
+            room_manager: Arc::new(RoomManager::new(Instant::now(), 4, 4)),
+            next_player_id: AtomicU64::new(1),
+            map_dir: manifest_dir.join("../../apps/client/public/maps"),
+            ruleset_dir: manifest_dir.join("rulesets"),
+            default_ruleset: Ruleset::default(),
+            demo_dir: None,
+            started_at: Instant::now(),
+            max_connections_per_ip,
+            max_message_bytes: 65536,
+            max_players_per_room: 4,
+            max_spectators_per_room: 4,
+            missed_tick_policy: MissedTickPolicy::default(),
+            ip_connections: tokio::sync::Mutex::new(HashMap::new()),
+            resume_slots: tokio::sync::Mutex::new(ResumeSlots::default()),
+            game_secret: Some(SECRET.to_string()),
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("loopback bind should succeed");
+        let addr = listener
+            .local_addr()
+            .expect("bound listener has an address");
+        let app = router(Arc::clone(&state));
+        tokio::spawn(async move {
+            let _ = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await;
+        });
+        (state, addr)
+    }
+
+    async fn open_connections(state: &AppState) -> usize {
+        state.ip_connections.lock().await.values().sum()
+    }
+
+    #[tokio::test]
+    async fn ws_requires_a_ticket_and_counts_against_the_ip_limit() {
+        let (state, addr) = serve(1).await;
+        let url = |query: &str| format!("ws://{addr}/ws{query}");
+
+        for query in ["", "?ticket=garbage", &format!("?ticket={}", ticket("other"))] {
+            let rejected = tokio_tungstenite::connect_async(url(query)).await;
+            let Err(WsError::Http(response)) = rejected else {
+                panic!("{query:?} should be refused before the upgrade");
+            };
+            assert_eq!(response.status(), 401);
+        }
+        assert_eq!(open_connections(&state).await, 0);
+
+        let valid = url(&format!("?ticket={}", ticket(SECRET)));
+        let (_first, _) = tokio_tungstenite::connect_async(valid.as_str())
+            .await
+            .expect("a valid ticket should upgrade");
+        for _ in 0..100 {
+            if open_connections(&state).await == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(open_connections(&state).await, 1);
+
+        // Over the limit the upgrade completes but the server hangs up at once.
+        let (mut second, _) = tokio_tungstenite::connect_async(valid.as_str())
+            .await
+            .expect("a valid ticket should upgrade");
+        let next = tokio::time::timeout(Duration::from_secs(2), second.next()).await;
+        assert!(matches!(next, Ok(None | Some(Err(_)))));
+        assert_eq!(open_connections(&state).await, 1);
+    }
+}
@@ -1,7 +1,9 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use rand::Rng;
+use physics_core::ArmorType;
+
+use crate::game::WeaponId;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ItemKind {
@@ -11,14 +13,33 @@ pub enum ItemKind {
     Health100,
     Armor50,
     Armor100,
+    ArmorRed,
     Quad,
+    Regeneration,
+    Haste,
+    BattleSuit,
+    Invisibility,
+    WeaponGauntlet,
     WeaponMachine,
     WeaponShotgun,
     WeaponGrenade,
     WeaponRocket,
+    WeaponRail,
+    WeaponPlasma,
+    WeaponShaft,
+    WeaponBfg,
+    AmmoMachine,
+    AmmoShotgun,
+    AmmoGrenade,
+    AmmoRocket,
+    AmmoRail,
+    AmmoPlasma,
+    AmmoShaft,
+    AmmoBfg,
 }
 
 impl ItemKind {
+    /// Weapons use upper case (or their slot digit), ammo boxes the lower case letter.
     pub fn from_char(ch: char) -> Option<Self> {
         match ch {
             'H' => Some(Self::Health100),
@@ -27,25 +48,135 @@ impl ItemKind {
             '6' => Some(Self::Health50),
             'A' => Some(Self::Armor100),
             'a' => Some(Self::Armor50),
+            'V' => Some(Self::ArmorRed),
             'Q' => Some(Self::Quad),
+            'E' => Some(Self::Regeneration),
+            'F' => Some(Self::Haste),
+            'U' => Some(Self::BattleSuit),
+            'I' => Some(Self::Invisibility),
+            'G' => Some(Self::WeaponGauntlet),
             'M' => Some(Self::WeaponMachine),
             'T' => Some(Self::WeaponShotgun),
             '3' => Some(Self::WeaponGrenade),
             '4' => Some(Self::WeaponRocket),
+            'L' => Some(Self::WeaponRail),
+            'P' => Some(Self::WeaponPlasma),
+            'S' => Some(Self::WeaponShaft),
+            'B' => Some(Self::WeaponBfg),
+            'm' => Some(Self::AmmoMachine),
+            't' => Some(Self::AmmoShotgun),
+            'g' => Some(Self::AmmoGrenade),
+            'k' => Some(Self::AmmoRocket),
+            'l' => Some(Self::AmmoRail),
+            'p' => Some(Self::AmmoPlasma),
+            's' => Some(Self::AmmoShaft),
+            'b' => Some(Self::AmmoBfg),
             _ => None,
         }
     }
 
+    /// Map token for this item; the inverse of `from_char`.
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Health100 => 'H',
+            Self::Health25 => 'h',
+            Self::Health5 => '5',
+            Self::Health50 => '6',
+            Self::Armor100 => 'A',
+            Self::Armor50 => 'a',
+            Self::ArmorRed => 'V',
+            Self::Quad => 'Q',
+            Self::Regeneration => 'E',
+            Self::Haste => 'F',
+            Self::BattleSuit => 'U',
+            Self::Invisibility => 'I',
+            Self::WeaponGauntlet => 'G',
+            Self::WeaponMachine => 'M',
+            Self::WeaponShotgun => 'T',
+            Self::WeaponGrenade => '3',
+            Self::WeaponRocket => '4',
+            Self::WeaponRail => 'L',
+            Self::WeaponPlasma => 'P',
+            Self::WeaponShaft => 'S',
+            Self::WeaponBfg => 'B',
+            Self::AmmoMachine => 'm',
+            Self::AmmoShotgun => 't',
+            Self::AmmoGrenade => 'g',
+            Self::AmmoRocket => 'k',
+            Self::AmmoRail => 'l',
+            Self::AmmoPlasma => 'p',
+            Self::AmmoShaft => 's',
+            Self::AmmoBfg => 'b',
+        }
+    }
+
+    /// Pickup that grants `weapon`.
+    pub fn for_weapon(weapon: WeaponId) -> Self {
+        match weapon {
+            WeaponId::Gauntlet => Self::WeaponGauntlet,
+            WeaponId::Machine => Self::WeaponMachine,
+            WeaponId::Shotgun => Self::WeaponShotgun,
+            WeaponId::Grenade => Self::WeaponGrenade,
+            WeaponId::Rocket => Self::WeaponRocket,
+            WeaponId::Rail => Self::WeaponRail,
+            WeaponId::Plasma => Self::WeaponPlasma,
+            WeaponId::Shaft => Self::WeaponShaft,
+            WeaponId::Bfg => Self::WeaponBfg,
+        }
+    }
+
     pub fn respawn_time(self) -> i32 {
         match self {
             Self::Health5 | Self::Health25 => 300,
             Self::Health50 | Self::Armor50 => 600,
-            Self::Health100 | Self::Armor100 => 900,
-            Self::Quad => 1200,
-            Self::WeaponMachine
-            | Self::WeaponShotgun
-            | Self::WeaponGrenade
-            | Self::WeaponRocket => 600,
+            Self::Health100 | Self::Armor100 | Self::ArmorRed => 900,
+            Self::Quad
+            | Self::Regeneration
+            | Self::Haste
+            | Self::BattleSuit
+            | Self::Invisibility => 1200,
+            _ => 600,
+        }
+    }
+
+    /// Armor tier and points granted by an armor pickup.
+    pub fn armor(self) -> Option<(ArmorType, i32)> {
+        match self {
+            Self::Armor50 => Some((ArmorType::Green, 50)),
+            Self::Armor100 => Some((ArmorType::Yellow, 100)),
+            Self::ArmorRed => Some((ArmorType::Red, 200)),
+            _ => None,
+        }
+    }
+
+    /// Weapon granted by a weapon pickup.
+    pub fn weapon(self) -> Option<WeaponId> {
+        match self {
+            Self::WeaponGauntlet => Some(WeaponId::Gauntlet),
+            Self::WeaponMachine => Some(WeaponId::Machine),
+            Self::WeaponShotgun => Some(WeaponId::Shotgun),
+            Self::WeaponGrenade => Some(WeaponId::Grenade),
+            Self::WeaponRocket => Some(WeaponId::Rocket),
+            Self::WeaponRail => Some(WeaponId::Rail),
+            Self::WeaponPlasma => Some(WeaponId::Plasma),
+            Self::WeaponShaft => Some(WeaponId::Shaft),
+            Self::WeaponBfg => Some(WeaponId::Bfg),
+            _ => None,
+        }
+    }
+
+    /// Weapon whose ammo an ammo box refills.
+    pub fn ammo(self) -> Option<WeaponId> {
+        match self {
+            Self::AmmoMachine => Some(WeaponId::Machine),
+            Self::AmmoShotgun => Some(WeaponId::Shotgun),
+            Self::AmmoGrenade => Some(WeaponId::Grenade),
+            Self::AmmoRocket => Some(WeaponId::Rocket),
+            Self::AmmoRail => Some(WeaponId::Rail),
+            Self::AmmoPlasma => Some(WeaponId::Plasma),
+            Self::AmmoShaft => Some(WeaponId::Shaft),
+            Self::AmmoBfg => Some(WeaponId::Bfg),
+            _ => None,
         }
     }
 }
@@ -89,17 +220,33 @@ impl GameMap {
         self.bricks[self.idx(col, row)] != 0
     }
 
-    pub fn random_respawn_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<(i32, i32)> {
-        if self.respawns.is_empty() {
-            return None;
-        }
-        let idx = rng.gen_range(0..self.respawns.len());
-        self.respawns.get(idx).copied()
-    }
-
     pub fn take_items(&mut self) -> Vec<MapItem> {
         std::mem::take(&mut self.items)
     }
+
+    /// FNV-1a over the bricks, spawn points and item placements. Stable across
+    /// builds, so a demo can tell whether its map file changed since recording.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        feed(&self.rows.to_le_bytes());
+        feed(&self.cols.to_le_bytes());
+        feed(&self.bricks);
+        for (row, col) in &self.respawns {
+            feed(&row.to_le_bytes());
+            feed(&col.to_le_bytes());
+        }
+        for item in &self.items {
+            feed(&[item.kind.to_char() as u8]);
+            feed(&item.row.to_le_bytes());
+            feed(&item.col.to_le_bytes());
+        }
+        hash
+    }
 }
 
 impl physics_core::tilemap::TileMap for GameMap {
@@ -164,7 +311,8 @@ fn parse_map(map_text: &str, map_name: &str) -> GameMap {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_map;
+    use super::{parse_map, ItemKind};
+    use crate::game::WeaponId;
 
     #[test]
     fn parse_map_ignores_trailing_newline() {
@@ -175,4 +323,38 @@ mod tests {
         assert!(map.is_brick(0, 1));
         assert_eq!(map.respawns, vec![(0, 0)]);
     }
+
+    #[test]
+    fn content_hash_follows_layout_not_name() {
+        let hash = |text: &str, name: &str| parse_map(text, name).content_hash();
+
+        assert_eq!(hash("R0\n00\n", "test"), hash("R0\n00\n", "copy"));
+        assert_ne!(hash("R0\n00\n", "test"), hash("0R\n00\n", "test"));
+        assert_ne!(hash("R0\n00\n", "test"), hash("RQ\n00\n", "test"));
+    }
+
+    #[test]
+    fn parse_map_reads_weapon_ammo_and_powerup_items() {
+        let map = parse_map("LlU\n000\n", "test");
+        let kinds: Vec<ItemKind> = map.items.iter().map(|item| item.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ItemKind::WeaponRail,
+                ItemKind::AmmoRail,
+                ItemKind::BattleSuit
+            ]
+        );
+        assert_eq!(kinds[0].weapon(), Some(WeaponId::Rail));
+        assert_eq!(kinds[1].ammo(), Some(WeaponId::Rail));
+    }
+
+    #[test]
+    fn item_tokens_roundtrip() {
+        for ch in "Hh56AaVQEFUIGMT34LPSBmtgklpsb".chars() {
+            let kind = ItemKind::from_char(ch);
+            assert_eq!(kind.map(ItemKind::to_char), Some(ch));
+        }
+    }
 }
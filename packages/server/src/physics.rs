@@ -3,8 +3,9 @@ use physics_core::step::step_player as core_step_player;
 use crate::map::GameMap;
 
 pub use physics_core::types::PlayerState;
+use physics_core::Ruleset;
 
-pub fn step_player(player: &mut PlayerState, map: &GameMap) {
+pub fn step_player(player: &mut PlayerState, map: &GameMap, rules: &Ruleset) {
     let input = physics_core::types::PlayerInput {
         key_up: player.key_up,
         key_down: player.key_down,
@@ -12,5 +13,5 @@ pub fn step_player(player: &mut PlayerState, map: &GameMap) {
         key_right: player.key_right,
     };
 
-    core_step_player(player, input, map);
+    core_step_player(player, input, map, rules);
 }
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::interval;
+use tracing::{debug, info};
+
+use crate::binary::{encode_demo_state, encode_player_left, restamp_snapshot, DemoControl};
+use crate::constants::{
+    DEMO_CONTROL_INTERVAL_MS, DEMO_PLAYER_ID_OFFSET, DEMO_SEEK_BUDGET_MS, MAX_DEMO_SPEED,
+    MIN_DEMO_SPEED, ROOM_COMMAND_CAPACITY,
+};
+use crate::demo::{Demo, DemoRecord};
+use crate::map::GameMap;
+use crate::room::{DemoReplay, PlayerId};
+use binary_protocol::MSG_SNAPSHOT;
+
+enum PlaybackCmd {
+    Watch {
+        viewer_id: PlayerId,
+        tx: mpsc::Sender<Bytes>,
+        response: oneshot::Sender<()>,
+    },
+    Unwatch {
+        viewer_id: PlayerId,
+    },
+    Control {
+        viewer_id: PlayerId,
+        control: DemoControl,
+    },
+}
+
+/// Read-only room replaying a recorded demo to viewers as if it were live.
+/// Viewers share one playback, so pausing or seeking affects all of them.
+pub struct PlaybackHandle {
+    name: String,
+    tx: mpsc::Sender<PlaybackCmd>,
+}
+
+impl PlaybackHandle {
+    pub fn new(name: String, demo: Demo, map: GameMap) -> Arc<Self> {
+        let (tx, rx) = mpsc::channel(ROOM_COMMAND_CAPACITY);
+        let handle = Arc::new(Self {
+            name: name.clone(),
+            tx,
+        });
+        tokio::spawn(async move {
+            let mut task = PlaybackTask::new(demo, map, rx);
+            task.run().await;
+            info!(demo = name, "demo playback closed");
+        });
+        handle
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns false if the playback shut down before the viewer was added.
+    pub async fn watch(&self, viewer_id: PlayerId, tx: mpsc::Sender<Bytes>) -> bool {
+        let (response, response_rx) = oneshot::channel();
+        let cmd = PlaybackCmd::Watch {
+            viewer_id,
+            tx,
+            response,
+        };
+        if self.tx.send(cmd).await.is_err() {
+            return false;
+        }
+        response_rx.await.is_ok()
+    }
+
+    pub fn unwatch(&self, viewer_id: PlayerId) {
+        let _ = self.tx.try_send(PlaybackCmd::Unwatch { viewer_id });
+    }
+
+    pub fn control(&self, viewer_id: PlayerId, control: DemoControl) {
+        let _ = self
+            .tx
+            .try_send(PlaybackCmd::Control { viewer_id, control });
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+struct PlaybackTask {
+    /// Kept to restart the replay when seeking backwards.
+    demo: Demo,
+    map: GameMap,
+    replay: DemoReplay,
+    broadcasts: mpsc::Receiver<Bytes>,
+    rx: mpsc::Receiver<PlaybackCmd>,
+    viewers: Vec<(PlayerId, mpsc::Sender<Bytes>)>,
+    /// Set by the first viewer; the playback closes once the last one leaves.
+    watched: bool,
+    end_tick: u64,
+    paused: bool,
+    speed: f32,
+    /// Steps owed at the current speed, carried between ticks.
+    budget: f32,
+    /// Position a seek is still catching up to.
+    seek_target: Option<u64>,
+    /// Players present when the pending seek started, told to leave if the
+    /// target lies before they joined.
+    seek_from: Vec<u64>,
+    /// When each viewer last changed the playback, so one can't flood it
+    /// while others still get a say.
+    last_control: HashMap<PlayerId, Instant>,
+    /// Tick stamped on outgoing snapshots. It only moves forward so clients
+    /// don't drop snapshots as stale after a seek.
+    stream_tick: u64,
+    started_at: Instant,
+}
+
+impl PlaybackTask {
+    fn new(mut demo: Demo, map: GameMap, rx: mpsc::Receiver<PlaybackCmd>) -> Self {
+        offset_player_ids(&mut demo);
+        let mut replay = DemoReplay::new(demo.clone(), map.clone());
+        let broadcasts = replay.stream_broadcasts();
+        Self {
+            end_tick: demo.tick_count(),
+            demo,
+            map,
+            replay,
+            broadcasts,
+            rx,
+            viewers: Vec::new(),
+            watched: false,
+            paused: false,
+            speed: 1.0,
+            budget: 0.0,
+            seek_target: None,
+            seek_from: Vec::new(),
+            last_control: HashMap::new(),
+            stream_tick: 0,
+            started_at: Instant::now(),
+        }
+    }
+
+    async fn run(&mut self) {
+        let tick_millis = self.demo.header.tick_millis.max(1);
+        let mut tick_interval = interval(Duration::from_millis(tick_millis));
+        loop {
+            tokio::select! {
+                maybe_cmd = self.rx.recv() => {
+                    let Some(cmd) = maybe_cmd else {
+                        break;
+                    };
+                    self.handle_cmd(cmd);
+                }
+                _ = tick_interval.tick() => self.advance(),
+            }
+            if self.watched && self.viewers.is_empty() {
+                break;
+            }
+        }
+    }
+
+    fn handle_cmd(&mut self, cmd: PlaybackCmd) {
+        match cmd {
+            PlaybackCmd::Watch {
+                viewer_id,
+                tx,
+                response,
+            } => {
+                let _ = tx.try_send(self.replay.room_state());
+                let _ = tx.try_send(self.demo_state());
+                self.viewers.retain(|(id, _)| *id != viewer_id);
+                self.viewers.push((viewer_id, tx));
+                self.watched = true;
+                let _ = response.send(());
+            }
+            PlaybackCmd::Unwatch { viewer_id } => {
+                self.viewers.retain(|(id, _)| *id != viewer_id);
+                self.last_control.remove(&viewer_id);
+            }
+            PlaybackCmd::Control { viewer_id, control } => {
+                let now = Instant::now();
+                let min_interval = Duration::from_millis(DEMO_CONTROL_INTERVAL_MS);
+                if self
+                    .last_control
+                    .get(&viewer_id)
+                    .is_some_and(|last| now.duration_since(*last) < min_interval)
+                {
+                    return;
+                }
+                self.last_control.insert(viewer_id, now);
+                match control {
+                    DemoControl::Pause => self.paused = true,
+                    DemoControl::Resume => {
+                        if self.replay.position() >= self.end_tick {
+                            self.seek(0);
+                        }
+                        self.paused = false;
+                    }
+                    DemoControl::Seek { tick } => self.seek(tick),
+                    DemoControl::Speed { speed } => {
+                        if speed.is_finite() {
+                            self.speed = speed.clamp(MIN_DEMO_SPEED, MAX_DEMO_SPEED);
+                        }
+                    }
+                }
+                let state = self.demo_state();
+                self.send_all(state);
+            }
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.seek_target.is_some() {
+            self.catch_up();
+            return;
+        }
+        if self.paused || self.viewers.is_empty() {
+            return;
+        }
+        self.budget += self.speed;
+        while self.budget >= 1.0 {
+            self.budget -= 1.0;
+            if !self.replay.step() {
+                self.paused = true;
+                self.budget = 0.0;
+                let state = self.demo_state();
+                self.send_all(state);
+                return;
+            }
+            self.stream_tick += 1;
+            self.forward_broadcasts();
+            if self
+                .replay
+                .position()
+                .is_multiple_of((1000 / self.demo.header.tick_millis.max(1)).max(1))
+            {
+                let state = self.demo_state();
+                self.send_all(state);
+            }
+        }
+    }
+
+    fn forward_broadcasts(&mut self) {
+        while let Ok(payload) = self.broadcasts.try_recv() {
+            let payload = if payload.first() == Some(&MSG_SNAPSHOT) {
+                let mut restamped = BytesMut::from(&payload[..]);
+                let server_time_ms = self.started_at.elapsed().as_millis() as u64;
+                restamp_snapshot(&mut restamped, self.stream_tick, server_time_ms);
+                restamped.freeze()
+            } else {
+                payload
+            };
+            self.send_all(payload);
+        }
+    }
+
+    /// Replays silently up to `tick`, restarting from the beginning when it
+    /// lies behind. Catching up runs for at most `DEMO_SEEK_BUDGET_MS` per
+    /// tick; viewers get a fresh room state once it is done.
+    fn seek(&mut self, tick: u64) {
+        let tick = tick.min(self.end_tick);
+        if self.seek_target.is_none() {
+            self.seek_from = self
+                .replay
+                .player_states()
+                .iter()
+                .map(|state| state.id)
+                .collect();
+        }
+        if tick < self.replay.position() {
+            self.replay = DemoReplay::new(self.demo.clone(), self.map.clone());
+            self.broadcasts = self.replay.stream_broadcasts();
+        }
+        self.seek_target = Some(tick);
+        self.budget = 0.0;
+        self.catch_up();
+    }
+
+    fn catch_up(&mut self) {
+        let Some(tick) = self.seek_target else {
+            return;
+        };
+        let deadline = Instant::now() + Duration::from_millis(DEMO_SEEK_BUDGET_MS);
+        while self.replay.position() < tick {
+            if !self.replay.step() {
+                break;
+            }
+            while self.broadcasts.try_recv().is_ok() {}
+            if Instant::now() >= deadline {
+                return;
+            }
+        }
+        self.seek_target = None;
+
+        for player_id in std::mem::take(&mut self.seek_from) {
+            let still_here = self
+                .replay
+                .player_states()
+                .iter()
+                .any(|state| state.id == player_id);
+            if !still_here {
+                self.send_all(Bytes::from(encode_player_left(player_id)));
+            }
+        }
+        let room_state = self.replay.room_state();
+        self.send_all(room_state);
+        let state = self.demo_state();
+        self.send_all(state);
+    }
+
+    fn demo_state(&self) -> Bytes {
+        Bytes::from(encode_demo_state(
+            self.replay.position(),
+            self.end_tick,
+            self.paused,
+            self.speed,
+        ))
+    }
+
+    fn send_all(&mut self, payload: Bytes) {
+        self.viewers.retain(|(viewer_id, tx)| {
+            let sent = tx.try_send(payload.clone()).is_ok();
+            if !sent {
+                debug!(viewer_id = viewer_id.0, "dropping demo viewer");
+            }
+            sent
+        });
+    }
+}
+
+fn offset_player_ids(demo: &mut Demo) {
+    for record in &mut demo.records {
+        match record {
+            DemoRecord::Tick => {}
+            DemoRecord::Join { player_id, .. }
+            | DemoRecord::Leave { player_id }
+            | DemoRecord::Input { player_id, .. } => *player_id += DEMO_PLAYER_ID_OFFSET,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use tokio::sync::{mpsc, oneshot};
+
+    use super::{PlaybackCmd, PlaybackTask};
+    use crate::binary::DemoControl;
+    use crate::constants::TICK_MILLIS;
+    use crate::demo::{Demo, DemoHeader, DemoRecord};
+    use crate::map::GameMap;
+    use crate::room::PlayerId;
+    use binary_protocol::MSG_SNAPSHOT;
+    use physics_core::Ruleset;
+
+    fn demo() -> Demo {
+        let mut records = vec![DemoRecord::Join {
+            player_id: 1,
+            username: "alice".to_string(),
+        }];
+        records.extend(std::iter::repeat_n(DemoRecord::Tick, 40));
+        Demo {
+            header: DemoHeader {
+                room_name: "test".to_string(),
+                map_name: "test".to_string(),
+                map_hash: 0,
+                seed: 0,
+                tick_millis: TICK_MILLIS,
+                max_players: 2,
+                rules: Ruleset::DEFAULT,
+            },
+            records,
+        }
+    }
+
+    fn map() -> GameMap {
+        GameMap {
+            rows: 1,
+            cols: 1,
+            bricks: vec![0],
+            respawns: vec![(0, 0)],
+            items: Vec::new(),
+            name: "test".to_string(),
+        }
+    }
+
+    fn snapshot_ticks(rx: &mut mpsc::Receiver<Bytes>) -> Vec<u64> {
+        let mut ticks = Vec::new();
+        while let Ok(payload) = rx.try_recv() {
+            if payload.first() == Some(&MSG_SNAPSHOT) {
+                let mut tick = [0; 8];
+                tick.copy_from_slice(&payload[1..9]);
+                ticks.push(u64::from_le_bytes(tick));
+            }
+        }
+        ticks
+    }
+
+    #[test]
+    fn snapshot_ticks_keep_rising_after_seeking_back() {
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut task = PlaybackTask::new(demo(), map(), cmd_rx);
+        let (tx, mut rx) = mpsc::channel(256);
+        let (response, _) = oneshot::channel();
+        task.handle_cmd(PlaybackCmd::Watch {
+            viewer_id: PlayerId(1),
+            tx,
+            response,
+        });
+
+        for _ in 0..20 {
+            task.advance();
+        }
+        let mut ticks = snapshot_ticks(&mut rx);
+        assert_eq!(task.replay.position(), 20);
+        assert!(task.replay.player_states().iter().all(|state| state.id > 1));
+
+        task.seek(5);
+        assert_eq!(task.replay.position(), 5);
+        for _ in 0..20 {
+            task.advance();
+        }
+        ticks.extend(snapshot_ticks(&mut rx));
+
+        assert!(ticks.len() > 10);
+        assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn control_bursts_are_dropped_per_viewer() {
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut task = PlaybackTask::new(demo(), map(), cmd_rx);
+        let control = |viewer, control| PlaybackCmd::Control {
+            viewer_id: PlayerId(viewer),
+            control,
+        };
+
+        task.handle_cmd(control(1, DemoControl::Pause));
+        task.handle_cmd(control(1, DemoControl::Speed { speed: 2.0 }));
+        assert!(task.paused);
+        assert_eq!(task.speed, 1.0);
+
+        task.handle_cmd(control(2, DemoControl::Speed { speed: 2.0 }));
+        assert_eq!(task.speed, 2.0);
+    }
+}
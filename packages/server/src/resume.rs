@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::room::PlayerId;
+
+struct ResumeSlot {
+    player_id: PlayerId,
+    session_id: Option<String>,
+    /// Bumped on every claim so a stale connection can't release the slot
+    /// out from under the one that replaced it.
+    connection: u64,
+    /// `None` while a connection holds the slot.
+    expires_at: Option<Instant>,
+}
+
+/// A player id handed to a connection, with what it needs to release it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumeClaim {
+    pub player_id: PlayerId,
+    pub token: String,
+    connection: u64,
+}
+
+/// Player ids kept for a grace period after their connection drops, keyed by
+/// the resume token sent in the welcome message.
+#[derive(Default)]
+pub struct ResumeSlots {
+    slots: HashMap<String, ResumeSlot>,
+}
+
+impl ResumeSlots {
+    /// Takes over the slot for `token`, or else a released slot issued to the
+    /// same ticket `session_id`. The token proves ownership, so it can claim a
+    /// slot whose old connection hasn't noticed it is gone; a ticket can be
+    /// reused by a second tab and never takes over a live connection.
+    pub fn claim(
+        &mut self,
+        token: Option<&str>,
+        session_id: Option<&str>,
+        now: Instant,
+    ) -> Option<ResumeClaim> {
+        self.slots
+            .retain(|_, slot| slot.expires_at.is_none_or(|at| at > now));
+        let token = match token.filter(|token| self.slots.contains_key(*token)) {
+            Some(token) => token.to_string(),
+            None => {
+                let session_id = session_id?;
+                self.slots
+                    .iter()
+                    .find(|(_, slot)| {
+                        slot.expires_at.is_some() && slot.session_id.as_deref() == Some(session_id)
+                    })
+                    .map(|(token, _)| token.clone())?
+            }
+        };
+        let slot = self.slots.get_mut(&token)?;
+        slot.connection += 1;
+        slot.expires_at = None;
+        Some(ResumeClaim {
+            player_id: slot.player_id,
+            token,
+            connection: slot.connection,
+        })
+    }
+
+    pub fn issue(&mut self, player_id: PlayerId, session_id: Option<String>) -> ResumeClaim {
+        let token = Uuid::new_v4().simple().to_string();
+        self.slots.insert(
+            token.clone(),
+            ResumeSlot {
+                player_id,
+                session_id,
+                connection: 0,
+                expires_at: None,
+            },
+        );
+        ResumeClaim {
+            player_id,
+            token,
+            connection: 0,
+        }
+    }
+
+    /// Starts the grace period once the claiming connection has ended.
+    pub fn release(&mut self, claim: &ResumeClaim, grace: Duration, now: Instant) {
+        if let Some(slot) = self.slots.get_mut(&claim.token) {
+            if slot.connection == claim.connection {
+                slot.expires_at = Some(now + grace);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ResumeSlots;
+    use crate::room::PlayerId;
+
+    #[test]
+    fn slots_resume_by_token_or_session_until_grace_ends() {
+        let mut slots = ResumeSlots::default();
+        let grace = Duration::from_secs(10);
+        let now = Instant::now();
+        let first = slots.issue(PlayerId(7), Some("session-a".to_string()));
+        slots.release(&first, grace, now);
+
+        let by_token = slots
+            .claim(Some(&first.token), None, now)
+            .expect("token should resume the slot");
+        assert_eq!(by_token.player_id, PlayerId(7));
+
+        // The replaced connection ending must not start the grace period.
+        slots.release(&first, grace, now);
+        slots.release(&by_token, grace, now);
+        let by_session = slots.claim(None, Some("session-a"), now + grace / 2);
+        assert_eq!(by_session.map(|claim| claim.player_id), Some(PlayerId(7)));
+
+        assert_eq!(slots.claim(Some("unknown"), Some("session-b"), now), None);
+        let last = slots
+            .claim(Some(&first.token), None, now)
+            .expect("token should resume the slot");
+        slots.release(&last, grace, now);
+        assert_eq!(slots.claim(Some(&first.token), None, now + grace), None);
+    }
+
+    #[test]
+    fn session_only_claims_released_slots() {
+        let mut slots = ResumeSlots::default();
+        let grace = Duration::from_secs(10);
+        let now = Instant::now();
+        let live = slots.issue(PlayerId(3), Some("session-a".to_string()));
+
+        assert_eq!(slots.claim(None, Some("session-a"), now), None);
+
+        slots.release(&live, grace, now);
+        let resumed = slots.claim(None, Some("session-a"), now);
+        assert_eq!(resumed.map(|claim| claim.player_id), Some(PlayerId(3)));
+        assert_eq!(slots.claim(None, Some("session-a"), now), None);
+    }
+}
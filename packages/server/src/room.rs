@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -7,24 +8,28 @@ use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
 use smallvec::SmallVec;
 use tokio::sync::{mpsc, oneshot};
-use tokio::time::interval;
 use tracing::{debug, info, warn};
 
 use crate::binary::{
     encode_player_joined, encode_player_left, encode_room_state,
-    player_snapshot_from_state, ItemSnapshot, SnapshotEncoder,
+    player_snapshot_from_state, DroppedItemSnapshot, ItemSnapshot, SnapshotEncoder,
 };
-use crate::binary::{EffectEvent, PlayerSnapshot};
+use crate::binary::{EffectEvent, JoinRole, PlayerSnapshot};
 use crate::constants::{
-    PLAYER_HALF_H, ROOM_COMMAND_CAPACITY, SNAPSHOT_INTERVAL_TICKS, SPAWN_OFFSET_X, TILE_H, TILE_W,
+    OUTBOUND_CHANNEL_CAPACITY, RESUME_GRACE_MS, ROOM_COMMAND_CAPACITY, SNAPSHOT_INTERVAL_TICKS,
 };
+use crate::demo::{Demo, DemoHeader, DemoRecord, DemoRecorder};
 use crate::game::{
-    apply_explosions, apply_hit_actions, apply_projectile_hits, process_item_pickups,
-    respawn_if_ready_with_rng, try_fire, update_projectiles, EventVec, Explosion, HitAction, IdGen,
-    Projectile, WeaponId,
+    apply_explosions, apply_hit_actions, apply_projectile_hits, apply_spawn_loadout,
+    drop_items_on_death, process_item_pickups, resolve_projectile_collisions,
+    respawn_if_ready_with_rng, try_fire, update_projectiles, DroppedItem, EventVec, Explosion,
+    FireOutput, HitAction, IdGen, Projectile, WeaponId,
 };
 use crate::map::{GameMap, MapItem};
 use crate::physics::{step_player, PlayerState};
+use crate::spawn::{spawn_position, SpawnSelector};
+use crate::tick::{MissedTickPolicy, TickMetrics, TickMetricsSnapshot, TickScheduler};
+use physics_core::Ruleset;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct PlayerId(pub u64);
@@ -63,13 +68,21 @@ pub enum RoomStatus {
 pub struct RoomConfig {
     pub name: String,
     pub max_players: usize,
-    pub tick_rate: u64,
+    /// Spectators have their own cap and don't take player slots.
+    pub max_spectators: usize,
+    /// Length of one simulation step; physics assumes `TICK_MILLIS`.
+    pub tick_millis: u64,
+    pub missed_tick_policy: MissedTickPolicy,
+    pub rules: Ruleset,
+    /// Record a demo and write it here when the room closes.
+    pub demo_dir: Option<PathBuf>,
 }
 
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum JoinError {
     RoomFull,
+    SpectatorsFull,
     RoomClosing,
 }
 
@@ -77,6 +90,7 @@ impl JoinError {
     pub fn reason(self) -> &'static str {
         match self {
             Self::RoomFull => "room_full",
+            Self::SpectatorsFull => "spectators_full",
             Self::RoomClosing => "room_closing",
         }
     }
@@ -85,7 +99,7 @@ impl JoinError {
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Tick(pub u64);
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct PlayerInput {
     pub key_up: bool,
     pub key_down: bool,
@@ -96,6 +110,15 @@ pub struct PlayerInput {
     pub weapon_scroll: i8,
     pub aim_angle: f32,
     pub facing_left: bool,
+    /// Edge-triggered fire and weapon switch, stamped with the input seq they
+    /// happened at. Every packet repeats the latest ones, so a lost or
+    /// reordered packet can't drop or double them.
+    pub fire_seq: u64,
+    pub switch_seq: u64,
+    pub switch_weapon: Option<WeaponId>,
+    /// The client sent the edges above, so `weapon_switch` and
+    /// `weapon_scroll` are only there for older servers and are ignored.
+    pub latched: bool,
 }
 
 #[derive(Clone)]
@@ -105,18 +128,66 @@ pub struct PlayerConn {
     pub tx: mpsc::Sender<Bytes>,
     pub input: PlayerInput,
     pub last_input_seq: u64,
+    pub last_fire_seq: u64,
+    pub last_switch_seq: u64,
+    /// Edges received since the last tick, consumed exactly once by it.
+    pub fire_latched: bool,
+    pub switch_latched: Option<WeaponId>,
+    /// Set while the client is disconnected: the slot is kept until this tick
+    /// in case it resumes.
+    pub held_until: Option<Tick>,
+}
+
+/// Receives broadcasts like a player but has no `PlayerState`; which player
+/// to follow is up to the client's camera.
+#[derive(Clone)]
+pub struct SpectatorConn {
+    pub id: PlayerId,
+    pub username: String,
+    pub tx: mpsc::Sender<Bytes>,
+}
+
+impl PlayerConn {
+    /// A new connection numbers its inputs and edges from scratch, so
+    /// nothing from the previous one may carry over.
+    fn reset_input(&mut self) {
+        self.input = PlayerInput::default();
+        self.last_input_seq = 0;
+        self.last_fire_seq = 0;
+        self.last_switch_seq = 0;
+        self.fire_latched = false;
+        self.switch_latched = None;
+    }
+
+    fn latch_edges(&mut self, input: &PlayerInput) {
+        if input.fire_seq > self.last_fire_seq {
+            self.last_fire_seq = input.fire_seq;
+            self.fire_latched = true;
+        }
+        if input.switch_seq > self.last_switch_seq {
+            self.last_switch_seq = input.switch_seq;
+            self.switch_latched = input.switch_weapon;
+        }
+    }
 }
 
 enum RoomCmd {
     Join {
         player_id: PlayerId,
         username: String,
+        role: JoinRole,
         tx: mpsc::Sender<Bytes>,
         response: oneshot::Sender<Result<Bytes, JoinError>>,
     },
     Leave {
         player_id: PlayerId,
     },
+    /// The connection dropped; `tx` tells a stale connection apart from one
+    /// that has already resumed the slot.
+    Disconnect {
+        player_id: PlayerId,
+        tx: mpsc::Sender<Bytes>,
+    },
     Input {
         player_id: PlayerId,
         seq: u64,
@@ -132,6 +203,7 @@ enum RoomCmd {
 pub struct RoomHandle {
     id: RoomId,
     tx: mpsc::Sender<RoomCmd>,
+    tick_metrics: Arc<TickMetrics>,
 }
 
 impl RoomHandle {
@@ -142,13 +214,20 @@ impl RoomHandle {
         server_started_at: Instant,
     ) -> Arc<Self> {
         let (tx, rx) = mpsc::channel(ROOM_COMMAND_CAPACITY);
-        let handle = Arc::new(Self { id, tx });
+        let handle = Arc::new(Self {
+            id,
+            tx,
+            tick_metrics: Arc::new(TickMetrics::default()),
+        });
 
         let task_handle = Arc::clone(&handle);
         tokio::spawn(async move {
-            let mut task =
-                RoomTask::new(task_handle.id.clone(), map, config, rx, server_started_at);
-            task.run().await;
+            let room_id = task_handle.id.clone();
+            let seed = room_id.as_str().bytes().fold(0_u64, |acc, byte| {
+                acc.wrapping_mul(31).wrapping_add(byte as u64)
+            });
+            let mut task = RoomTask::new(room_id, map, config, rx, server_started_at, seed);
+            task.run(&task_handle.tick_metrics).await;
         });
 
         handle
@@ -158,16 +237,22 @@ impl RoomHandle {
         &self.id
     }
 
+    pub fn tick_metrics(&self) -> TickMetricsSnapshot {
+        self.tick_metrics.snapshot()
+    }
+
     pub async fn join(
         &self,
         player_id: PlayerId,
         username: String,
+        role: JoinRole,
         tx: mpsc::Sender<Bytes>,
     ) -> Result<Bytes, JoinError> {
         let (response_tx, response_rx) = oneshot::channel();
         let cmd = RoomCmd::Join {
             player_id,
             username,
+            role,
             tx,
             response: response_tx,
         };
@@ -181,6 +266,11 @@ impl RoomHandle {
         let _ = self.tx.try_send(RoomCmd::Leave { player_id });
     }
 
+    /// Like `leave`, but players keep their slot for `RESUME_GRACE_MS`.
+    pub fn disconnect(&self, player_id: PlayerId, tx: mpsc::Sender<Bytes>) {
+        let _ = self.tx.try_send(RoomCmd::Disconnect { player_id, tx });
+    }
+
     pub fn set_input(&self, player_id: PlayerId, seq: u64, input: PlayerInput) {
         let _ = self.tx.try_send(RoomCmd::Input {
             player_id,
@@ -216,6 +306,9 @@ struct RoomTask {
     rx: mpsc::Receiver<RoomCmd>,
     tick: Tick,
     items: Vec<MapItem>,
+    dropped_items: Vec<DroppedItem>,
+    next_dropped_item_id: IdGen,
+    spawns: SpawnSelector,
     projectiles: Vec<Projectile>,
     next_projectile_id: IdGen,
     player_store: PlayerStore,
@@ -223,18 +316,25 @@ struct RoomTask {
     rng: ChaCha8Rng,
     scratch_player_snapshots: Vec<PlayerSnapshot>,
     scratch_item_snapshots: Vec<ItemSnapshot>,
+    scratch_dropped_item_snapshots: Vec<DroppedItemSnapshot>,
     scratch_events: EventVec,
     pending_snapshot_events: EventVec,
     scratch_hit_actions: Vec<HitAction>,
     scratch_explosions: Vec<Explosion>,
-    scratch_pending_hits: Vec<(u64, u64, f32)>,
+    scratch_pending_hits: Vec<(u64, u64, f32, WeaponId)>,
+    scratch_quad_holders: Vec<bool>,
     scratch_disconnected: SmallVec<[PlayerId; 4]>,
+    recorder: Option<DemoRecorder>,
+    /// Gets a copy of everything broadcast to players; demo playback streams
+    /// the replayed room to viewers through it.
+    observer: Option<mpsc::Sender<Bytes>>,
 }
 
 struct PlayerStore {
     conns: Vec<PlayerConn>,
     states: Vec<PlayerState>,
     player_index: HashMap<PlayerId, usize>,
+    spectators: Vec<SpectatorConn>,
 }
 
 impl PlayerStore {
@@ -243,6 +343,7 @@ impl PlayerStore {
             conns: Vec::new(),
             states: Vec::new(),
             player_index: HashMap::new(),
+            spectators: Vec::new(),
         }
     }
 
@@ -254,6 +355,11 @@ impl PlayerStore {
         self.conns.is_empty()
     }
 
+    fn player_by_id(&self, player_id: PlayerId) -> Option<&PlayerConn> {
+        let idx = self.player_index.get(&player_id).copied()?;
+        Some(&self.conns[idx])
+    }
+
     fn player_mut_by_id(&mut self, player_id: PlayerId) -> Option<&mut PlayerConn> {
         let idx = self.player_index.get(&player_id).copied()?;
         Some(&mut self.conns[idx])
@@ -271,6 +377,10 @@ impl PlayerStore {
         &self.conns
     }
 
+    fn conns_mut(&mut self) -> &mut [PlayerConn] {
+        &mut self.conns
+    }
+
     fn contains(&self, player_id: PlayerId) -> bool {
         self.player_index.contains_key(&player_id)
     }
@@ -299,6 +409,27 @@ impl PlayerStore {
         true
     }
 
+    fn spectators(&self) -> &[SpectatorConn] {
+        &self.spectators
+    }
+
+    fn contains_spectator(&self, player_id: PlayerId) -> bool {
+        self.spectators
+            .iter()
+            .any(|spectator| spectator.id == player_id)
+    }
+
+    fn insert_spectator(&mut self, spectator: SpectatorConn) {
+        self.remove_spectator(spectator.id);
+        self.spectators.push(spectator);
+    }
+
+    fn remove_spectator(&mut self, player_id: PlayerId) -> bool {
+        let before = self.spectators.len();
+        self.spectators.retain(|spectator| spectator.id != player_id);
+        self.spectators.len() != before
+    }
+
     fn validate(&self) {
         debug_assert_eq!(self.conns.len(), self.states.len());
         debug_assert_eq!(self.conns.len(), self.player_index.len());
@@ -315,14 +446,27 @@ impl RoomTask {
         config: RoomConfig,
         rx: mpsc::Receiver<RoomCmd>,
         server_started_at: Instant,
+        seed: u64,
     ) -> Self {
-        let seed = room_id.as_str().bytes().fold(0_u64, |acc, byte| {
-            acc.wrapping_mul(31).wrapping_add(byte as u64)
+        let recorder = config.demo_dir.as_ref().map(|dir| {
+            let header = DemoHeader {
+                room_name: config.name.clone(),
+                map_name: map.name.clone(),
+                map_hash: map.content_hash(),
+                seed,
+                tick_millis: config.tick_millis,
+                max_players: config.max_players,
+                rules: config.rules.clone(),
+            };
+            DemoRecorder::new(dir, room_id.as_str(), &header)
         });
 
         Self {
             room_id,
             items: map.take_items(),
+            dropped_items: Vec::new(),
+            next_dropped_item_id: IdGen::default(),
+            spawns: SpawnSelector::default(),
             map: Arc::new(map),
             config,
             status: RoomStatus::Created,
@@ -336,19 +480,25 @@ impl RoomTask {
             rng: ChaCha8Rng::seed_from_u64(seed),
             scratch_player_snapshots: Vec::new(),
             scratch_item_snapshots: Vec::new(),
+            scratch_dropped_item_snapshots: Vec::new(),
             scratch_events: EventVec::new(),
             pending_snapshot_events: EventVec::new(),
             scratch_hit_actions: Vec::new(),
             scratch_explosions: Vec::new(),
             scratch_pending_hits: Vec::new(),
+            scratch_quad_holders: Vec::new(),
             scratch_disconnected: SmallVec::new(),
+            recorder,
+            observer: None,
         }
     }
 
-    async fn run(&mut self) {
-        let tick_hz = self.config.tick_rate.max(1);
-        let tick_period = Duration::from_secs_f64(1.0 / tick_hz as f64);
-        let mut tick_interval = interval(tick_period);
+    async fn run(&mut self, metrics: &TickMetrics) {
+        let tick_period = Duration::from_millis(self.config.tick_millis.max(1));
+        let mut scheduler =
+            TickScheduler::new(tick_period, Instant::now(), self.config.missed_tick_policy);
+        let next_tick = tokio::time::sleep_until(scheduler.deadline().into());
+        tokio::pin!(next_tick);
         loop {
             tokio::select! {
                 maybe_cmd = self.rx.recv() => {
@@ -360,9 +510,17 @@ impl RoomTask {
                     }
                     self.drain_commands();
                 }
-                _ = tick_interval.tick() => {
+                _ = &mut next_tick => {
+                    let started = Instant::now();
                     self.drain_commands();
                     self.simulate_tick();
+                    let finished = Instant::now();
+                    let dropped = scheduler.advance(finished);
+                    metrics.observe(finished - started, tick_period, dropped);
+                    if dropped > 0 {
+                        debug!(room_id = self.room_id.as_str(), dropped, "room fell behind");
+                    }
+                    next_tick.as_mut().reset(scheduler.deadline().into());
                     if self.status == RoomStatus::Closing {
                         break;
                     }
@@ -371,6 +529,21 @@ impl RoomTask {
         }
         self.status = RoomStatus::Closed;
         info!(room_id = self.room_id.as_str(), "room closed");
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.save().await {
+                Ok(path) => info!(path = %path.display(), "demo saved"),
+                Err(err) => warn!(
+                    room_id = self.room_id.as_str(),
+                    "failed to save demo: {err}"
+                ),
+            }
+        }
+    }
+
+    fn record(&mut self, record: DemoRecord) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&record);
+        }
     }
 
     fn drain_commands(&mut self) {
@@ -382,10 +555,62 @@ impl RoomTask {
     }
 
     fn handle_cmd(&mut self, cmd: RoomCmd) -> bool {
+        if self.recorder.is_some() {
+            let record = match &cmd {
+                // Spectators never touch the simulation; a player turning
+                // spectator is recorded as leaving by `handle_spectate`, once
+                // the switch has gone through.
+                RoomCmd::Join {
+                    role: JoinRole::Spectator,
+                    ..
+                } => None,
+                RoomCmd::Join {
+                    player_id,
+                    username,
+                    ..
+                } => Some(DemoRecord::Join {
+                    player_id: player_id.0,
+                    username: username.clone(),
+                }),
+                RoomCmd::Leave { player_id } | RoomCmd::Input { player_id, .. }
+                    if self.player_store.contains_spectator(*player_id) =>
+                {
+                    None
+                }
+                RoomCmd::Leave { player_id } => Some(DemoRecord::Leave {
+                    player_id: player_id.0,
+                }),
+                // A held player stands still; that's all the simulation sees.
+                RoomCmd::Disconnect { player_id, tx } => self
+                    .player_store
+                    .player_by_id(*player_id)
+                    .filter(|player| player.tx.same_channel(tx))
+                    .map(|player| DemoRecord::Input {
+                        player_id: player_id.0,
+                        seq: player.last_input_seq,
+                        input: PlayerInput::default(),
+                    }),
+                RoomCmd::Input {
+                    player_id,
+                    seq,
+                    input,
+                } => Some(DemoRecord::Input {
+                    player_id: player_id.0,
+                    seq: *seq,
+                    input: *input,
+                }),
+                #[cfg(test)]
+                RoomCmd::ContainsPlayer { .. } => None,
+            };
+            if let Some(record) = record {
+                self.record(record);
+            }
+        }
         match cmd {
             RoomCmd::Join {
                 player_id,
                 username,
+                role,
                 tx,
                 response,
             } => {
@@ -393,6 +618,10 @@ impl RoomTask {
                     let _ = response.send(Err(JoinError::RoomClosing));
                     return false;
                 }
+                if role == JoinRole::Spectator {
+                    let _ = response.send(self.handle_spectate(player_id, username, tx));
+                    return false;
+                }
                 if self.player_store.len() >= self.config.max_players
                     && !self.player_store.contains(player_id)
                 {
@@ -415,6 +644,31 @@ impl RoomTask {
                 if self.remove_player(player_id) {
                     self.broadcast(encode_player_left(player_id.0).into());
                     self.transition_empty_if_needed();
+                } else if self.player_store.remove_spectator(player_id) {
+                    self.transition_empty_if_needed();
+                }
+            }
+            RoomCmd::Disconnect { player_id, tx } => {
+                let grace_ticks = RESUME_GRACE_MS / self.config.tick_millis.max(1);
+                let held_until = Tick(self.tick.0 + grace_ticks);
+                if let Some(player) = self.player_store.player_mut_by_id(player_id) {
+                    if player.tx.same_channel(&tx) {
+                        player.held_until = Some(held_until);
+                        player.input = PlayerInput::default();
+                        info!(
+                            player_id = player_id.0,
+                            room_id = self.room_id.as_str(),
+                            "holding slot for disconnected player"
+                        );
+                    }
+                } else if self
+                    .player_store
+                    .spectators()
+                    .iter()
+                    .any(|spectator| spectator.id == player_id && spectator.tx.same_channel(&tx))
+                {
+                    self.player_store.remove_spectator(player_id);
+                    self.transition_empty_if_needed();
                 }
             }
             RoomCmd::Input {
@@ -423,6 +677,7 @@ impl RoomTask {
                 input,
             } => {
                 if let Some(player) = self.player_store.player_mut_by_id(player_id) {
+                    player.latch_edges(&input);
                     if seq >= player.last_input_seq {
                         player.last_input_seq = seq;
                         player.input = input;
@@ -434,7 +689,10 @@ impl RoomTask {
                 player_id,
                 response,
             } => {
-                let _ = response.send(self.player_store.contains(player_id));
+                let _ = response.send(
+                    self.player_store.contains(player_id)
+                        || self.player_store.contains_spectator(player_id),
+                );
             }
         }
         false
@@ -446,16 +704,26 @@ impl RoomTask {
         username: String,
         tx: mpsc::Sender<Bytes>,
     ) -> JoinResult {
+        self.player_store.remove_spectator(player_id);
         let joined_name = username.clone();
         let broadcast_join = if let Some(player) = self.player_store.player_mut_by_id(player_id) {
             player.username = username;
             player.tx = tx;
+            player.held_until = None;
+            player.reset_input();
             false
         } else {
             let mut state = PlayerState::new(player_id.0);
-            if let Some((row, col)) = self.map.random_respawn_with_rng(&mut self.rng) {
-                let x = col as f32 * TILE_W + SPAWN_OFFSET_X;
-                let y = row as f32 * TILE_H - PLAYER_HALF_H;
+            apply_spawn_loadout(&mut state, &self.config.rules);
+            let spawn = self.spawns.select(
+                self.map.as_ref(),
+                self.player_store.states(),
+                player_id.0,
+                false,
+                &mut self.rng,
+            );
+            if let Some((row, col)) = spawn {
+                let (x, y) = spawn_position(row, col);
                 state.set_xy(x, y, self.map.as_ref());
                 state.prev_x = state.x;
                 state.prev_y = state.y;
@@ -468,6 +736,11 @@ impl RoomTask {
                     tx,
                     input: PlayerInput::default(),
                     last_input_seq: 0,
+                    last_fire_seq: 0,
+                    last_switch_seq: 0,
+                    fire_latched: false,
+                    switch_latched: None,
+                    held_until: None,
                 },
                 state,
             );
@@ -476,14 +749,7 @@ impl RoomTask {
         };
 
         self.status = RoomStatus::Running;
-
-        let room_state = Bytes::from(encode_room_state(
-            self.room_id.as_str(),
-            self.map.name.as_str(),
-            self.config.tick_rate,
-            self.player_store.conns(),
-            self.player_store.states(),
-        ));
+        let room_state = self.room_state();
 
         JoinResult {
             player_id,
@@ -493,6 +759,43 @@ impl RoomTask {
         }
     }
 
+    fn handle_spectate(
+        &mut self,
+        player_id: PlayerId,
+        username: String,
+        tx: mpsc::Sender<Bytes>,
+    ) -> Result<Bytes, JoinError> {
+        if !self.player_store.contains_spectator(player_id)
+            && self.player_store.spectators().len() >= self.config.max_spectators
+        {
+            return Err(JoinError::SpectatorsFull);
+        }
+        if self.remove_player(player_id) {
+            self.record(DemoRecord::Leave {
+                player_id: player_id.0,
+            });
+            self.broadcast(encode_player_left(player_id.0).into());
+        }
+        self.player_store.insert_spectator(SpectatorConn {
+            id: player_id,
+            username,
+            tx,
+        });
+        self.status = RoomStatus::Running;
+        Ok(self.room_state())
+    }
+
+    fn room_state(&self) -> Bytes {
+        Bytes::from(encode_room_state(
+            self.room_id.as_str(),
+            self.map.name.as_str(),
+            self.config.tick_millis,
+            &self.config.rules.to_bytes(),
+            self.player_store.conns(),
+            self.player_store.states(),
+        ))
+    }
+
     fn remove_player(&mut self, player_id: PlayerId) -> bool {
         let removed = self.player_store.remove(player_id);
         if removed {
@@ -501,48 +804,103 @@ impl RoomTask {
         removed
     }
 
+    fn expire_held_players(&mut self) {
+        let expired: SmallVec<[PlayerId; 4]> = self
+            .player_store
+            .conns()
+            .iter()
+            .filter(|player| player.held_until.is_some_and(|until| until.0 <= self.tick.0))
+            .map(|player| player.id)
+            .collect();
+        for player_id in expired {
+            info!(
+                player_id = player_id.0,
+                room_id = self.room_id.as_str(),
+                "resume grace expired"
+            );
+            self.record(DemoRecord::Leave {
+                player_id: player_id.0,
+            });
+            if self.remove_player(player_id) {
+                self.broadcast(encode_player_left(player_id.0).into());
+            }
+        }
+        self.transition_empty_if_needed();
+    }
+
     fn transition_empty_if_needed(&mut self) {
-        if self.player_store.is_empty() {
+        if self.player_store.is_empty() && self.player_store.spectators().is_empty() {
             self.status = RoomStatus::Closing;
         }
     }
 
     fn simulate_tick(&mut self) {
+        // Before the tick is recorded, so a replay sees the leave in the same place.
+        self.expire_held_players();
+        self.record(DemoRecord::Tick);
+        self.scratch_events.clear();
         if self.player_store.is_empty() || self.status != RoomStatus::Running {
             return;
         }
 
         self.tick.0 = self.tick.0.wrapping_add(1);
         let map = self.map.as_ref();
-        self.scratch_events.clear();
+        let rules = &self.config.rules;
         self.scratch_hit_actions.clear();
         self.scratch_explosions.clear();
+        self.scratch_quad_holders.clear();
+        self.scratch_quad_holders.extend(
+            self.player_store
+                .states()
+                .iter()
+                .map(|state| state.quad_damage),
+        );
 
         for idx in 0..self.player_store.len() {
-            let input = self.player_store.conns()[idx].input;
+            let conn = &mut self.player_store.conns_mut()[idx];
+            let input = conn.input;
+            let fire_latched = std::mem::take(&mut conn.fire_latched);
+            let switch_latched = conn.switch_latched.take();
             let state = &mut self.player_store.states_mut()[idx];
             apply_input_to_state(&input, state);
+            if let Some(weapon) = switch_latched {
+                state.request_weapon(weapon as i32);
+            }
 
-            if !state.dead && input.mouse_down {
+            if !state.dead && (input.mouse_down || fire_latched) {
                 try_fire(
                     state,
-                    &mut self.projectiles,
                     map,
-                    &mut self.next_projectile_id,
-                    &mut self.scratch_hit_actions,
-                    &mut self.scratch_events,
+                    FireOutput {
+                        projectiles: &mut self.projectiles,
+                        id_gen: &mut self.next_projectile_id,
+                        hitscan_actions: &mut self.scratch_hit_actions,
+                        events: &mut self.scratch_events,
+                    },
                     &mut self.rng,
+                    rules,
                 );
             }
 
-            step_player(state, map);
-            respawn_if_ready_with_rng(state, map, &mut self.rng);
+            step_player(state, map, rules);
+            respawn_if_ready_with_rng(
+                idx,
+                self.player_store.states_mut(),
+                map,
+                &mut self.spawns,
+                &mut self.rng,
+                &mut self.scratch_events,
+                rules,
+            );
         }
 
         apply_hit_actions(
             &self.scratch_hit_actions,
             self.player_store.states_mut(),
+            &mut self.projectiles,
             &mut self.scratch_events,
+            &mut self.scratch_explosions,
+            rules,
         );
 
         update_projectiles(
@@ -550,18 +908,25 @@ impl RoomTask {
             &mut self.projectiles,
             &mut self.scratch_events,
             &mut self.scratch_explosions,
+            rules,
         );
+        if rules.projectile_interactions {
+            resolve_projectile_collisions(&mut self.projectiles, &mut self.scratch_events);
+        }
         apply_projectile_hits(
             &mut self.projectiles,
             self.player_store.states_mut(),
             &mut self.scratch_events,
             &mut self.scratch_explosions,
+            rules,
         );
         apply_explosions(
-            &self.scratch_explosions,
+            &mut self.scratch_explosions,
             self.player_store.states_mut(),
+            &mut self.projectiles,
             &mut self.scratch_events,
             &mut self.scratch_pending_hits,
+            rules,
         );
 
         for explosion in &self.scratch_explosions {
@@ -572,12 +937,27 @@ impl RoomTask {
             });
         }
 
-        process_item_pickups(self.player_store.states_mut(), &mut self.items);
+        drop_items_on_death(
+            &self.scratch_events,
+            self.player_store.states_mut(),
+            &mut self.dropped_items,
+            &mut self.next_dropped_item_id,
+            rules,
+        );
+        process_item_pickups(
+            self.player_store.states_mut(),
+            &mut self.items,
+            &mut self.dropped_items,
+            &mut self.scratch_events,
+            rules,
+        );
+        self.push_quad_events();
 
+        // Kept until the next tick so a demo replay can read this tick's events.
         self.pending_snapshot_events
-            .extend(self.scratch_events.drain(..));
+            .extend(self.scratch_events.iter().cloned());
 
-        if self.tick.0 % SNAPSHOT_INTERVAL_TICKS != 0 {
+        if !self.tick.0.is_multiple_of(SNAPSHOT_INTERVAL_TICKS) {
             return;
         }
 
@@ -588,6 +968,7 @@ impl RoomTask {
             server_time_ms,
             &self.scratch_player_snapshots,
             &self.scratch_item_snapshots,
+            &self.scratch_dropped_item_snapshots,
             &[],
             &self.pending_snapshot_events,
         );
@@ -595,9 +976,27 @@ impl RoomTask {
         self.broadcast(payload);
     }
 
+    /// Quad can start or end through pickups, expiry, death and respawn; compare
+    /// against who held it when the tick began instead of hooking each path.
+    fn push_quad_events(&mut self) {
+        let states = self.player_store.states();
+        for (state, had_quad) in states.iter().zip(&self.scratch_quad_holders) {
+            match (*had_quad, state.quad_damage) {
+                (false, true) => self.scratch_events.push(EffectEvent::QuadStart {
+                    player_id: state.id,
+                }),
+                (true, false) => self.scratch_events.push(EffectEvent::QuadExpire {
+                    player_id: state.id,
+                }),
+                _ => {}
+            }
+        }
+    }
+
     fn build_snapshot_buffers(&mut self) {
         self.scratch_player_snapshots.clear();
         self.scratch_item_snapshots.clear();
+        self.scratch_dropped_item_snapshots.clear();
 
         self.scratch_player_snapshots
             .reserve(self.player_store.len());
@@ -616,11 +1015,26 @@ impl RoomTask {
                 respawn_timer: item.respawn_timer as i16,
             });
         }
+
+        self.scratch_dropped_item_snapshots
+            .reserve(self.dropped_items.len());
+        for item in &self.dropped_items {
+            self.scratch_dropped_item_snapshots.push(DroppedItemSnapshot {
+                id: item.id,
+                kind: item.kind.to_char() as u8,
+                x: item.x,
+                y: item.y,
+            });
+        }
     }
 
     fn broadcast(&mut self, payload: Bytes) {
+        self.notify_observer(&payload);
         self.scratch_disconnected.clear();
         for player in self.player_store.conns() {
+            if player.held_until.is_some() {
+                continue;
+            }
             match player.tx.try_send(payload.clone()) {
                 Ok(()) => {}
                 Err(err) => {
@@ -645,8 +1059,23 @@ impl RoomTask {
                 }
             }
         }
+        self.player_store
+            .spectators
+            .retain(|spectator| match spectator.tx.try_send(payload.clone()) {
+                Ok(()) => true,
+                Err(_) => {
+                    debug!(
+                        player_id = spectator.id.0,
+                        "dropping spectator: outbound channel unavailable"
+                    );
+                    false
+                }
+            });
 
         while let Some(disconnected_id) = self.scratch_disconnected.pop() {
+            self.record(DemoRecord::Leave {
+                player_id: disconnected_id.0,
+            });
             if self.remove_player(disconnected_id) {
                 let left_payload = Bytes::from(encode_player_left(disconnected_id.0));
                 self.broadcast_after_disconnect(left_payload);
@@ -657,18 +1086,32 @@ impl RoomTask {
     }
 
     fn broadcast_after_disconnect(&mut self, payload: Bytes) {
+        self.notify_observer(&payload);
         for player in self.player_store.conns() {
             let _ = player.tx.try_send(payload.clone());
         }
+        for spectator in self.player_store.spectators() {
+            let _ = spectator.tx.try_send(payload.clone());
+        }
     }
 
     fn broadcast_except(&mut self, payload: Bytes, skip_player_id: PlayerId) {
+        self.notify_observer(&payload);
         for player in self.player_store.conns() {
             if player.id == skip_player_id {
                 continue;
             }
             let _ = player.tx.try_send(payload.clone());
         }
+        for spectator in self.player_store.spectators() {
+            let _ = spectator.tx.try_send(payload.clone());
+        }
+    }
+
+    fn notify_observer(&self, payload: &Bytes) {
+        if let Some(observer) = &self.observer {
+            let _ = observer.try_send(payload.clone());
+        }
     }
 }
 
@@ -687,17 +1130,17 @@ fn apply_input_to_state(input: &PlayerInput, state: &mut PlayerState) {
     state.aim_angle = input.aim_angle;
     state.facing_left = input.facing_left;
 
+    if input.latched {
+        return;
+    }
     if let Some(weapon) = input.weapon_switch {
-        let idx = weapon as usize;
-        if state.weapons[idx] {
-            state.current_weapon = weapon as i32;
-        }
+        state.request_weapon(weapon as i32);
     } else if input.weapon_scroll != 0 {
         let dir = if input.weapon_scroll < 0 { -1 } else { 1 };
         let total = crate::constants::WEAPON_COUNT as i32;
 
         for step in 1..=total {
-            let mut next = state.current_weapon + dir * step;
+            let mut next = state.pending_weapon + dir * step;
             if next < 0 {
                 next += total;
             }
@@ -705,13 +1148,146 @@ fn apply_input_to_state(input: &PlayerInput, state: &mut PlayerState) {
                 next -= total;
             }
             if state.weapons[next as usize] {
-                state.current_weapon = next;
+                state.request_weapon(next);
                 break;
             }
         }
     }
 }
 
+/// Re-runs a recorded demo through a room without any networking. Outbound
+/// messages go to channels drained after every record.
+pub struct DemoReplay {
+    task: RoomTask,
+    records: std::vec::IntoIter<DemoRecord>,
+    outbound: Vec<mpsc::Receiver<Bytes>>,
+    position: u64,
+}
+
+impl DemoReplay {
+    pub fn new(demo: Demo, map: GameMap) -> Self {
+        let header = demo.header;
+        let config = RoomConfig {
+            name: header.room_name,
+            max_players: header.max_players,
+            max_spectators: 0,
+            tick_millis: header.tick_millis,
+            missed_tick_policy: MissedTickPolicy::default(),
+            rules: header.rules,
+            demo_dir: None,
+        };
+        let (_tx, rx) = mpsc::channel(1);
+        let task = RoomTask::new(
+            RoomId::from("replay"),
+            map,
+            config,
+            rx,
+            Instant::now(),
+            header.seed,
+        );
+        Self {
+            task,
+            records: demo.records.into_iter(),
+            outbound: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Applies records up to and including the next simulated tick.
+    /// Returns false once the demo has run out.
+    pub fn step(&mut self) -> bool {
+        for record in self.records.by_ref() {
+            match record {
+                DemoRecord::Tick => {
+                    self.task.simulate_tick();
+                    drain_outbound(&mut self.outbound);
+                    self.position += 1;
+                    return true;
+                }
+                DemoRecord::Join {
+                    player_id,
+                    username,
+                } => {
+                    let (tx, rx) = mpsc::channel(OUTBOUND_CHANNEL_CAPACITY);
+                    self.outbound.push(rx);
+                    let (response, _) = oneshot::channel();
+                    self.task.handle_cmd(RoomCmd::Join {
+                        player_id: PlayerId(player_id),
+                        username,
+                        role: JoinRole::Player,
+                        tx,
+                        response,
+                    });
+                }
+                DemoRecord::Leave { player_id } => {
+                    self.task.handle_cmd(RoomCmd::Leave {
+                        player_id: PlayerId(player_id),
+                    });
+                }
+                DemoRecord::Input {
+                    player_id,
+                    seq,
+                    input,
+                } => {
+                    self.task.handle_cmd(RoomCmd::Input {
+                        player_id: PlayerId(player_id),
+                        seq,
+                        input,
+                    });
+                }
+            }
+            drain_outbound(&mut self.outbound);
+        }
+        false
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.task.tick.0
+    }
+
+    /// Steps taken so far, the position `Demo::tick_count` is measured in.
+    /// Unlike `tick` this also counts ticks the room spent empty.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Everything the room broadcasts from now on, as players would have
+    /// received it.
+    pub fn stream_broadcasts(&mut self) -> mpsc::Receiver<Bytes> {
+        let (tx, rx) = mpsc::channel(OUTBOUND_CHANNEL_CAPACITY);
+        self.task.observer = Some(tx);
+        rx
+    }
+
+    /// Room state as a player joining right now would receive it.
+    pub fn room_state(&self) -> Bytes {
+        let task = &self.task;
+        Bytes::from(encode_room_state(
+            task.config.name.as_str(),
+            task.map.name.as_str(),
+            task.config.tick_millis,
+            &task.config.rules.to_bytes(),
+            task.player_store.conns(),
+            task.player_store.states(),
+        ))
+    }
+
+    pub fn player_states(&self) -> &[PlayerState] {
+        self.task.player_store.states()
+    }
+
+    /// Events from the last step; empty if the room was idle that tick.
+    pub fn events(&self) -> &[EffectEvent] {
+        &self.task.scratch_events
+    }
+}
+
+fn drain_outbound(outbound: &mut [mpsc::Receiver<Bytes>]) {
+    for rx in outbound {
+        while rx.try_recv().is_ok() {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Instant;
@@ -719,8 +1295,17 @@ mod tests {
     use bytes::Bytes;
     use tokio::sync::mpsc;
 
-    use super::{PlayerId, RoomConfig, RoomHandle, RoomId};
-    use crate::map::GameMap;
+    use super::{
+        apply_input_to_state, DemoReplay, JoinError, JoinRole, PlayerConn, PlayerId, PlayerInput,
+        PlayerState, RoomCmd, RoomConfig, RoomHandle, RoomId, RoomStatus, RoomTask, Ruleset,
+    };
+    use crate::binary::EffectEvent;
+    use crate::constants::{RESUME_GRACE_MS, SNAPSHOT_INTERVAL_TICKS, TICK_MILLIS};
+    use crate::demo::Demo;
+    use crate::game::WeaponId;
+    use crate::map::{GameMap, ItemKind, MapItem};
+    use crate::spawn::spawn_position;
+    use crate::tick::MissedTickPolicy;
 
     fn simple_map() -> GameMap {
         GameMap {
@@ -737,7 +1322,11 @@ mod tests {
         RoomConfig {
             name: name.to_string(),
             max_players,
-            tick_rate: 60,
+            max_spectators: 2,
+            tick_millis: TICK_MILLIS,
+            missed_tick_policy: MissedTickPolicy::default(),
+            rules: Ruleset::default(),
+            demo_dir: None,
         }
     }
 
@@ -752,12 +1341,19 @@ mod tests {
         let (tx, _rx) = mpsc::channel::<Bytes>(4);
 
         let first = room
-            .join(PlayerId(10), "alice".to_string(), tx.clone())
+            .join(
+                PlayerId(10),
+                "alice".to_string(),
+                JoinRole::Player,
+                tx.clone(),
+            )
             .await;
         assert!(first.is_ok());
         assert!(room.contains_player(PlayerId(10)).await);
 
-        let second = room.join(PlayerId(10), "alice".to_string(), tx).await;
+        let second = room
+            .join(PlayerId(10), "alice".to_string(), JoinRole::Player, tx)
+            .await;
         assert!(second.is_ok());
         assert!(room.contains_player(PlayerId(10)).await);
 
@@ -765,4 +1361,550 @@ mod tests {
         tokio::task::yield_now().await;
         assert!(!room.contains_player(PlayerId(10)).await);
     }
+
+    #[test]
+    fn latched_edges_are_taken_once_per_seq() {
+        let (tx, _rx) = mpsc::channel::<Bytes>(1);
+        let mut conn = PlayerConn {
+            id: PlayerId(1),
+            username: "alice".to_string(),
+            tx,
+            input: PlayerInput::default(),
+            last_input_seq: 0,
+            last_fire_seq: 0,
+            last_switch_seq: 0,
+            fire_latched: false,
+            switch_latched: None,
+            held_until: None,
+        };
+        let input = PlayerInput {
+            fire_seq: 5,
+            switch_seq: 4,
+            switch_weapon: Some(WeaponId::Rail),
+            ..PlayerInput::default()
+        };
+
+        conn.latch_edges(&input);
+        assert!(std::mem::take(&mut conn.fire_latched));
+        assert_eq!(conn.switch_latched.take(), Some(WeaponId::Rail));
+
+        // The next packet repeats the same edges; an older one arrives late.
+        conn.latch_edges(&input);
+        conn.latch_edges(&PlayerInput {
+            fire_seq: 3,
+            ..input
+        });
+        assert!(!conn.fire_latched);
+        assert_eq!(conn.switch_latched, None);
+    }
+
+    #[test]
+    fn latched_inputs_ignore_level_weapon_fields() {
+        let mut state = PlayerState::new(1);
+        state.weapons = [true; 9];
+        state.ready_weapon(WeaponId::Machine as i32);
+        let legacy = PlayerInput {
+            weapon_switch: Some(WeaponId::Rail),
+            ..PlayerInput::default()
+        };
+
+        apply_input_to_state(
+            &PlayerInput {
+                latched: true,
+                ..legacy
+            },
+            &mut state,
+        );
+        apply_input_to_state(
+            &PlayerInput {
+                weapon_switch: None,
+                weapon_scroll: 1,
+                latched: true,
+                ..legacy
+            },
+            &mut state,
+        );
+        assert_eq!(state.pending_weapon, WeaponId::Machine as i32);
+
+        apply_input_to_state(&legacy, &mut state);
+        assert_eq!(state.pending_weapon, WeaponId::Rail as i32);
+    }
+
+    #[test]
+    fn demo_replay_reproduces_the_match() {
+        let mut bricks = vec![0; 30];
+        bricks[20..].fill(1);
+        let map = GameMap {
+            rows: 3,
+            cols: 10,
+            bricks,
+            respawns: vec![(1, 2), (1, 7)],
+            items: Vec::new(),
+            name: "floor".to_string(),
+        };
+        let config = RoomConfig {
+            demo_dir: Some("demos".into()),
+            ..cfg("demo", 4)
+        };
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut live = RoomTask::new(
+            RoomId::from("live"),
+            map.clone(),
+            config,
+            cmd_rx,
+            Instant::now(),
+            42,
+        );
+        let mut outbound = Vec::new();
+        let joins = [
+            (1, "alice", JoinRole::Player),
+            (2, "bob", JoinRole::Player),
+            (3, "carol", JoinRole::Spectator),
+        ];
+        for (id, name, role) in joins {
+            let (tx, rx) = mpsc::channel::<Bytes>(64);
+            outbound.push(rx);
+            let (response, _) = tokio::sync::oneshot::channel();
+            live.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(id),
+                username: name.to_string(),
+                role,
+                tx,
+                response,
+            });
+        }
+        for seq in 1..=120_u64 {
+            let input = PlayerInput {
+                key_right: seq < 60,
+                key_up: seq.is_multiple_of(40),
+                fire_seq: seq / 30,
+                aim_angle: std::f32::consts::PI,
+                ..PlayerInput::default()
+            };
+            live.handle_cmd(RoomCmd::Input {
+                player_id: PlayerId(1 + seq % 2),
+                seq,
+                input,
+            });
+            live.simulate_tick();
+            for rx in &mut outbound {
+                while rx.try_recv().is_ok() {}
+            }
+        }
+
+        let bytes = live
+            .recorder
+            .as_ref()
+            .map(|recorder| recorder.bytes().to_vec())
+            .expect("recording should be on");
+        let demo = Demo::decode(&bytes).expect("demo should decode");
+        assert!(demo.check_map(&map).is_ok());
+        let mut moved = map.clone();
+        moved.respawns.reverse();
+        assert!(demo.check_map(&moved).is_err());
+        let mut replay = DemoReplay::new(demo, map);
+        while replay.step() {}
+
+        assert_eq!(replay.tick(), live.tick.0);
+        let replayed = replay.player_states();
+        let recorded = live.player_store.states();
+        assert_eq!(replayed.len(), recorded.len());
+        for (a, b) in replayed.iter().zip(recorded) {
+            assert_eq!((a.id, a.x, a.y), (b.id, b.x, b.y));
+            assert_eq!((a.health, a.ammo), (b.health, b.ammo));
+        }
+    }
+
+    #[test]
+    fn refused_spectate_leaves_the_demo_untouched() {
+        let mut bricks = vec![0; 30];
+        bricks[20..].fill(1);
+        let map = GameMap {
+            rows: 3,
+            cols: 10,
+            bricks,
+            respawns: vec![(1, 2), (1, 7)],
+            items: Vec::new(),
+            name: "floor".to_string(),
+        };
+        let config = RoomConfig {
+            max_spectators: 1,
+            demo_dir: Some("demos".into()),
+            ..cfg("full", 4)
+        };
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut live = RoomTask::new(
+            RoomId::from("full"),
+            map.clone(),
+            config,
+            cmd_rx,
+            Instant::now(),
+            7,
+        );
+        let mut outbound = Vec::new();
+        let joins = [
+            (1, "alice", JoinRole::Player),
+            (2, "bob", JoinRole::Player),
+            (3, "carol", JoinRole::Spectator),
+            (1, "alice", JoinRole::Spectator),
+        ];
+        let mut results = Vec::new();
+        for (id, name, role) in joins {
+            let (tx, rx) = mpsc::channel::<Bytes>(64);
+            outbound.push(rx);
+            let (response, mut result) = tokio::sync::oneshot::channel();
+            live.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(id),
+                username: name.to_string(),
+                role,
+                tx,
+                response,
+            });
+            results.push(result.try_recv().map(|joined| joined.err()));
+        }
+        assert_eq!(results[3], Ok(Some(JoinError::SpectatorsFull)));
+        assert!(live.player_store.contains(PlayerId(1)));
+
+        for seq in 1..=40_u64 {
+            let input = PlayerInput {
+                key_left: seq < 20,
+                ..PlayerInput::default()
+            };
+            live.handle_cmd(RoomCmd::Input {
+                player_id: PlayerId(1),
+                seq,
+                input,
+            });
+            live.simulate_tick();
+            for rx in &mut outbound {
+                while rx.try_recv().is_ok() {}
+            }
+        }
+
+        let bytes = live
+            .recorder
+            .as_ref()
+            .map(|recorder| recorder.bytes().to_vec())
+            .expect("recording should be on");
+        let demo = Demo::decode(&bytes).expect("demo should decode");
+        let mut replay = DemoReplay::new(demo, map);
+        while replay.step() {}
+
+        let replayed = replay.player_states();
+        let recorded = live.player_store.states();
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed.len(), recorded.len());
+        for (a, b) in replayed.iter().zip(recorded) {
+            assert_eq!((a.id, a.x, a.y), (b.id, b.x, b.y));
+            assert_eq!((a.health, a.ammo), (b.health, b.ammo));
+        }
+    }
+
+    #[test]
+    fn disconnected_players_keep_their_slot_until_grace_expires() {
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut room = RoomTask::new(
+            RoomId::from("resume"),
+            simple_map(),
+            cfg("resume", 2),
+            cmd_rx,
+            Instant::now(),
+            1,
+        );
+        let join = |room: &mut RoomTask, id: u64, tx: mpsc::Sender<Bytes>| {
+            let (response, _) = tokio::sync::oneshot::channel();
+            room.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(id),
+                username: format!("p{id}"),
+                role: JoinRole::Player,
+                tx,
+                response,
+            });
+        };
+        let left = |rx: &mut mpsc::Receiver<Bytes>| {
+            let mut left = false;
+            while let Ok(payload) = rx.try_recv() {
+                left |= payload.first() == Some(&binary_protocol::MSG_PLAYER_LEFT);
+            }
+            left
+        };
+        let (old_tx, _old_rx) = mpsc::channel::<Bytes>(64);
+        let (other_tx, mut other_rx) = mpsc::channel::<Bytes>(1024);
+        join(&mut room, 1, old_tx.clone());
+        join(&mut room, 2, other_tx);
+        room.player_store.states_mut()[0].health = 42;
+
+        room.handle_cmd(RoomCmd::Disconnect {
+            player_id: PlayerId(1),
+            tx: old_tx.clone(),
+        });
+        for _ in 0..10 {
+            room.simulate_tick();
+        }
+        assert!(!left(&mut other_rx));
+
+        let (new_tx, _new_rx) = mpsc::channel::<Bytes>(1024);
+        join(&mut room, 1, new_tx.clone());
+        room.handle_cmd(RoomCmd::Disconnect {
+            player_id: PlayerId(1),
+            tx: old_tx,
+        });
+        let resumed = room.player_store.player_by_id(PlayerId(1));
+        assert!(resumed.is_some_and(|player| player.held_until.is_none()));
+        assert_eq!(room.player_store.states()[0].health, 42);
+
+        room.handle_cmd(RoomCmd::Disconnect {
+            player_id: PlayerId(1),
+            tx: new_tx,
+        });
+        let grace_ticks = RESUME_GRACE_MS / TICK_MILLIS;
+        for _ in 0..=grace_ticks {
+            room.simulate_tick();
+        }
+        assert!(left(&mut other_rx));
+        assert!(!room.player_store.contains(PlayerId(1)));
+    }
+
+    #[test]
+    fn resumed_players_restart_input_seqs() {
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut room = RoomTask::new(
+            RoomId::from("resume"),
+            simple_map(),
+            cfg("resume", 2),
+            cmd_rx,
+            Instant::now(),
+            1,
+        );
+        let join = |room: &mut RoomTask, tx: mpsc::Sender<Bytes>| {
+            let (response, _) = tokio::sync::oneshot::channel();
+            room.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(1),
+                username: "alice".to_string(),
+                role: JoinRole::Player,
+                tx,
+                response,
+            });
+        };
+        let (old_tx, _old_rx) = mpsc::channel::<Bytes>(1024);
+        join(&mut room, old_tx.clone());
+        room.handle_cmd(RoomCmd::Input {
+            player_id: PlayerId(1),
+            seq: 90,
+            input: PlayerInput {
+                key_right: true,
+                fire_seq: 30,
+                switch_seq: 2,
+                switch_weapon: Some(WeaponId::Machine),
+                ..PlayerInput::default()
+            },
+        });
+        room.handle_cmd(RoomCmd::Disconnect {
+            player_id: PlayerId(1),
+            tx: old_tx,
+        });
+
+        let (new_tx, _new_rx) = mpsc::channel::<Bytes>(1024);
+        join(&mut room, new_tx);
+        let resumed = room.player_store.player_by_id(PlayerId(1));
+        assert!(resumed.is_some_and(|player| !player.input.key_right && !player.fire_latched));
+        room.handle_cmd(RoomCmd::Input {
+            player_id: PlayerId(1),
+            seq: 1,
+            input: PlayerInput {
+                key_left: true,
+                fire_seq: 1,
+                ..PlayerInput::default()
+            },
+        });
+
+        let player = room
+            .player_store
+            .player_by_id(PlayerId(1))
+            .expect("resumed player should keep its slot");
+        assert!(player.input.key_left);
+        assert!(player.fire_latched);
+        assert_eq!(player.switch_latched, None);
+    }
+
+    #[test]
+    fn quad_item_and_respawn_events_follow_the_match() {
+        let mut bricks = vec![0; 30];
+        bricks[20..].fill(1);
+        let map = GameMap {
+            rows: 3,
+            cols: 10,
+            bricks,
+            respawns: vec![(1, 2), (1, 7)],
+            items: vec![MapItem {
+                kind: ItemKind::Quad,
+                row: 0,
+                col: 2,
+                active: true,
+                respawn_timer: 0,
+            }],
+            name: "floor".to_string(),
+        };
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut room = RoomTask::new(
+            RoomId::from("events"),
+            map,
+            cfg("events", 2),
+            cmd_rx,
+            Instant::now(),
+            7,
+        );
+        let mut outbound = Vec::new();
+        for id in [1, 2] {
+            let (tx, rx) = mpsc::channel::<Bytes>(4096);
+            outbound.push(rx);
+            let (response, _) = tokio::sync::oneshot::channel();
+            room.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(id),
+                username: format!("p{id}"),
+                role: JoinRole::Player,
+                tx,
+                response,
+            });
+        }
+        let spots = [spawn_position(1, 2), spawn_position(1, 7)];
+        for (state, (x, y)) in room.player_store.states_mut().iter_mut().zip(spots) {
+            state.x = x;
+            state.y = y;
+            state.spawn_protection = 0;
+        }
+        let mut tick = |room: &mut RoomTask| {
+            room.simulate_tick();
+            for rx in &mut outbound {
+                while rx.try_recv().is_ok() {}
+            }
+            room.scratch_events.to_vec()
+        };
+        let quad_start = |events: &[EffectEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, EffectEvent::QuadStart { player_id: 1 }))
+        };
+        let quad_expire = |events: &[EffectEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, EffectEvent::QuadExpire { player_id: 1 }))
+        };
+        let item_respawn = |events: &[EffectEvent]| {
+            events
+                .iter()
+                .any(|event| matches!(event, EffectEvent::ItemRespawn { index: 0 }))
+        };
+
+        // Pickup starts quad once the player lands on it, and the item respawns
+        // once its timer has run out.
+        let mut falling = 0;
+        while !quad_start(&tick(&mut room)) {
+            falling += 1;
+            assert!(falling < 60, "player never reached the quad");
+        }
+        assert!(!room.items[0].active);
+        room.player_store.states_mut()[0].quad_timer = 1;
+        assert!(quad_expire(&tick(&mut room)));
+        let mut elapsed = 1;
+        loop {
+            elapsed += 1;
+            if item_respawn(&tick(&mut room)) {
+                break;
+            }
+            assert!(elapsed < ItemKind::Quad.respawn_time());
+        }
+        assert_eq!(elapsed, ItemKind::Quad.respawn_time());
+        assert!(quad_start(&tick(&mut room)));
+
+        // Dying drops quad as well.
+        let states = room.player_store.states_mut();
+        states[0].health = 1;
+        states[0].armor = 0;
+        states[1].weapons[WeaponId::Rail as usize] = true;
+        states[1].ammo[WeaponId::Rail as usize] = 10;
+        states[1].ready_weapon(WeaponId::Rail as i32);
+        room.handle_cmd(RoomCmd::Input {
+            player_id: PlayerId(2),
+            seq: 1,
+            input: PlayerInput {
+                fire_seq: 1,
+                aim_angle: std::f32::consts::PI,
+                facing_left: true,
+                ..PlayerInput::default()
+            },
+        });
+        assert!(quad_expire(&tick(&mut room)));
+        assert!(room.player_store.states()[0].dead);
+
+        // The respawn event carries the spawn point the player was moved to.
+        let respawn = loop {
+            let events = tick(&mut room);
+            let respawn = events.into_iter().find_map(|event| match event {
+                EffectEvent::PlayerRespawn { player_id: 1, x, y } => Some((x, y)),
+                _ => None,
+            });
+            if let Some(respawn) = respawn {
+                break respawn;
+            }
+        };
+        assert!(spots.contains(&respawn));
+        let state = &room.player_store.states()[0];
+        assert!(!state.dead);
+        assert_eq!((state.x, state.y), respawn);
+    }
+
+    #[test]
+    fn spectators_get_snapshots_without_taking_player_slots() {
+        let (_cmd_tx, cmd_rx) = mpsc::channel(1);
+        let mut room = RoomTask::new(
+            RoomId::from("spec"),
+            simple_map(),
+            cfg("spec", 1),
+            cmd_rx,
+            Instant::now(),
+            1,
+        );
+        let join = |room: &mut RoomTask, id: u64, role: JoinRole| {
+            let (tx, rx) = mpsc::channel::<Bytes>(64);
+            let (response, mut response_rx) = tokio::sync::oneshot::channel();
+            room.handle_cmd(RoomCmd::Join {
+                player_id: PlayerId(id),
+                username: format!("p{id}"),
+                role,
+                tx,
+                response,
+            });
+            (response_rx.try_recv().map(|result| result.is_ok()), rx)
+        };
+
+        let (player_ok, _player_rx) = join(&mut room, 1, JoinRole::Player);
+        let (first_ok, mut spectator_rx) = join(&mut room, 2, JoinRole::Spectator);
+        let (second_ok, _) = join(&mut room, 3, JoinRole::Spectator);
+        let (third_ok, _) = join(&mut room, 4, JoinRole::Spectator);
+        assert_eq!(player_ok, Ok(true));
+        assert_eq!((first_ok, second_ok), (Ok(true), Ok(true)));
+        assert_eq!(third_ok, Ok(false));
+        assert_eq!(room.player_store.len(), 1);
+
+        for _ in 0..SNAPSHOT_INTERVAL_TICKS {
+            room.simulate_tick();
+        }
+        let mut got_snapshot = false;
+        while let Ok(payload) = spectator_rx.try_recv() {
+            got_snapshot |= payload.first() == Some(&binary_protocol::MSG_SNAPSHOT);
+        }
+        assert!(got_snapshot);
+
+        room.handle_cmd(RoomCmd::Leave {
+            player_id: PlayerId(1),
+        });
+        assert_eq!(room.status, RoomStatus::Running);
+        for id in [2, 3] {
+            room.handle_cmd(RoomCmd::Leave {
+                player_id: PlayerId(id),
+            });
+        }
+        assert_eq!(room.status, RoomStatus::Closing);
+    }
 }
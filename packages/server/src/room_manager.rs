@@ -8,11 +8,15 @@ use bytes::Bytes;
 use tokio::sync::{mpsc, Mutex, RwLock};
 use uuid::Uuid;
 
-use crate::binary::encode_join_rejected;
+use crate::binary::{encode_join_rejected, JoinRole};
+use crate::demo::Demo;
 use crate::map::GameMap;
+use crate::playback::PlaybackHandle;
 use crate::room::{PlayerId, RoomConfig, RoomHandle, RoomId};
+use crate::tick::TickMetricsSnapshot;
 
 pub const ROOM_MAX_PLAYERS_HARD_CAP: usize = 8;
+pub const ROOM_MAX_SPECTATORS_HARD_CAP: usize = 32;
 
 #[derive(Default)]
 pub struct RoomMetrics {
@@ -25,6 +29,8 @@ pub struct RoomManager {
     rooms: RwLock<HashMap<RoomId, Arc<RoomHandle>>>,
     names: RwLock<HashMap<String, RoomId>>,
     player_rooms: Mutex<HashMap<PlayerId, RoomId>>,
+    /// Demo playbacks by demo name; they count against `max_rooms`.
+    playbacks: Mutex<HashMap<String, Arc<PlaybackHandle>>>,
     pub metrics: RoomMetrics,
     server_started_at: Instant,
     max_rooms: usize,
@@ -58,6 +64,7 @@ impl RoomManager {
             rooms: RwLock::new(HashMap::new()),
             names: RwLock::new(HashMap::new()),
             player_rooms: Mutex::new(HashMap::new()),
+            playbacks: Mutex::new(HashMap::new()),
             metrics: RoomMetrics::default(),
             server_started_at,
             max_rooms,
@@ -65,52 +72,96 @@ impl RoomManager {
         }
     }
 
+    /// Returns the room called `name`. `create` supplies its config and map
+    /// and runs only if the room doesn't exist yet, so joining a running room
+    /// never depends on its creation settings.
     pub async fn get_or_create_room(
         &self,
-        config: RoomConfig,
-        map: GameMap,
+        name: &str,
+        create: impl FnOnce() -> Result<(RoomConfig, GameMap), RoomCreateError>,
     ) -> Result<Arc<RoomHandle>, RoomCreateError> {
-        if config.max_players == 0 || config.max_players > self.max_players_per_room {
-            return Err(RoomCreateError::InvalidMaxPlayers(format!(
-                "maxPlayers must be 1..={}",
-                self.max_players_per_room
-            )));
-        }
-
         let mut rooms = self.rooms.write().await;
         let mut names = self.names.write().await;
 
-        if let Some(room_id) = names.get(&config.name) {
+        if let Some(room_id) = names.get(name) {
             if let Some(room) = rooms.get(room_id) {
                 return Ok(Arc::clone(room));
             }
-            names.remove(&config.name);
+            names.remove(name);
         }
 
-        if rooms.len() >= self.max_rooms {
+        let (config, map) = create()?;
+        if config.max_players == 0 || config.max_players > self.max_players_per_room {
+            return Err(RoomCreateError::InvalidMaxPlayers(format!(
+                "maxPlayers must be 1..={}",
+                self.max_players_per_room
+            )));
+        }
+        if config.max_spectators > ROOM_MAX_SPECTATORS_HARD_CAP {
+            return Err(RoomCreateError::InvalidMaxPlayers(format!(
+                "maxSpectators must be 0..={ROOM_MAX_SPECTATORS_HARD_CAP}"
+            )));
+        }
+
+        if rooms.len() + self.playbacks.lock().await.len() >= self.max_rooms {
             return Err(RoomCreateError::Other("server room limit reached".to_string()));
         }
 
         let room_id = RoomId::from(Uuid::new_v4().simple().to_string());
         let room = RoomHandle::new(room_id.clone(), map, config.clone(), self.server_started_at);
         rooms.insert(room_id.clone(), Arc::clone(&room));
-        names.insert(config.name, room_id);
+        names.insert(name.to_string(), room_id);
         self.metrics
             .rooms_created_total
             .fetch_add(1, Ordering::Relaxed);
         Ok(room)
     }
 
+    /// Running playback of the named demo, if any.
+    pub async fn playback(&self, name: &str) -> Option<Arc<PlaybackHandle>> {
+        let playbacks = self.playbacks.lock().await;
+        playbacks
+            .get(name)
+            .filter(|playback| !playback.is_closed())
+            .cloned()
+    }
+
+    /// Starts streaming `demo`, or returns the playback another viewer
+    /// started for the same name in the meantime.
+    pub async fn start_playback(
+        &self,
+        name: String,
+        demo: Demo,
+        map: GameMap,
+    ) -> Result<Arc<PlaybackHandle>, RoomCreateError> {
+        let mut playbacks = self.playbacks.lock().await;
+        playbacks.retain(|_, playback| !playback.is_closed());
+        if let Some(playback) = playbacks.get(&name) {
+            return Ok(Arc::clone(playback));
+        }
+        if self.rooms.read().await.len() + playbacks.len() >= self.max_rooms {
+            return Err(RoomCreateError::Other("server room limit reached".to_string()));
+        }
+        let playback = PlaybackHandle::new(name.clone(), demo, map);
+        playbacks.insert(name, Arc::clone(&playback));
+        Ok(playback)
+    }
+
     pub async fn join_room(
         &self,
         player_id: PlayerId,
         username: String,
+        role: JoinRole,
         target_room: Arc<RoomHandle>,
         tx: mpsc::Sender<Bytes>,
     ) -> Result<JoinSuccess, Bytes> {
-        self.leave_player(player_id).await;
+        // Rejoining the same room keeps the player's state, e.g. when resuming.
+        let current_room = self.player_rooms.lock().await.get(&player_id).cloned();
+        if current_room.as_ref() != Some(target_room.id()) {
+            self.leave_player(player_id).await;
+        }
 
-        match target_room.join(player_id, username, tx).await {
+        match target_room.join(player_id, username, role, tx).await {
             Ok(room_state) => {
                 self.player_rooms
                     .lock()
@@ -139,6 +190,20 @@ impl RoomManager {
         }
     }
 
+    /// Tick timing for every room, by room name.
+    pub async fn room_tick_metrics(&self) -> Vec<(String, TickMetricsSnapshot)> {
+        let rooms = self.rooms.read().await;
+        let names = self.names.read().await;
+        names
+            .iter()
+            .filter_map(|(name, room_id)| {
+                rooms
+                    .get(room_id)
+                    .map(|room| (name.clone(), room.tick_metrics()))
+            })
+            .collect()
+    }
+
     #[cfg(test)]
     pub async fn current_players(&self) -> usize {
         self.player_rooms.lock().await.len()
@@ -153,9 +218,13 @@ mod tests {
     use bytes::Bytes;
     use tokio::sync::mpsc;
 
-    use super::RoomManager;
+    use super::{RoomCreateError, RoomManager};
+    use crate::binary::JoinRole;
+    use crate::constants::TICK_MILLIS;
     use crate::map::GameMap;
     use crate::room::{PlayerId, RoomConfig};
+    use crate::tick::MissedTickPolicy;
+    use physics_core::Ruleset;
 
     fn map() -> GameMap {
         GameMap {
@@ -172,7 +241,11 @@ mod tests {
         RoomConfig {
             name: name.to_string(),
             max_players: max,
-            tick_rate: 60,
+            max_spectators: 0,
+            tick_millis: TICK_MILLIS,
+            missed_tick_policy: MissedTickPolicy::default(),
+            rules: Ruleset::default(),
+            demo_dir: None,
         }
     }
 
@@ -180,27 +253,43 @@ mod tests {
     async fn join_until_full() {
         let manager = RoomManager::new(Instant::now(), 50, 8);
         let room = manager
-            .get_or_create_room(config("beta", 1), map())
+            .get_or_create_room("beta", || Ok((config("beta", 1), map())))
             .await
             .expect("room create");
         let (tx1, _rx1) = mpsc::channel::<Bytes>(4);
         let (tx2, _rx2) = mpsc::channel::<Bytes>(4);
 
         let first = manager
-            .join_room(PlayerId(1), "p1".to_string(), room.clone(), tx1)
+            .join_room(PlayerId(1), "p1".to_string(), JoinRole::Player, room.clone(), tx1)
             .await;
         assert!(first.is_ok());
         let second = manager
-            .join_room(PlayerId(2), "p2".to_string(), room, tx2)
+            .join_room(PlayerId(2), "p2".to_string(), JoinRole::Player, room, tx2)
             .await;
         assert!(second.is_err());
     }
 
+    #[tokio::test]
+    async fn existing_rooms_skip_creation_settings() {
+        let manager = RoomManager::new(Instant::now(), 50, 8);
+        let room = manager
+            .get_or_create_room("delta", || Ok((config("delta", 2), map())))
+            .await
+            .expect("room create");
+        let again = manager
+            .get_or_create_room("delta", || {
+                Err(RoomCreateError::Other("ruleset unavailable".to_string()))
+            })
+            .await
+            .expect("existing room");
+        assert!(Arc::ptr_eq(&room, &again));
+    }
+
     #[tokio::test]
     async fn concurrent_join_respects_capacity() {
         let manager = Arc::new(RoomManager::new(Instant::now(), 50, 8));
         let room = manager
-            .get_or_create_room(config("gamma", 2), map())
+            .get_or_create_room("gamma", || Ok((config("gamma", 2), map())))
             .await
             .expect("room create");
 
@@ -211,7 +300,7 @@ mod tests {
             tasks.push(tokio::spawn(async move {
                 let (tx, _rx) = mpsc::channel::<Bytes>(2);
                 manager
-                    .join_room(PlayerId(player), format!("p{player}"), room, tx)
+                    .join_room(PlayerId(player), format!("p{player}"), JoinRole::Player, room, tx)
                     .await
                     .is_ok()
             }));
@@ -230,12 +319,12 @@ mod tests {
     async fn disconnect_cleanup_reuses_leave_path() {
         let manager = RoomManager::new(Instant::now(), 50, 8);
         let room = manager
-            .get_or_create_room(config("epsilon", 2), map())
+            .get_or_create_room("epsilon", || Ok((config("epsilon", 2), map())))
             .await
             .expect("room create");
         let (tx, _rx) = mpsc::channel::<Bytes>(4);
         let joined = manager
-            .join_room(PlayerId(9), "p9".to_string(), room, tx)
+            .join_room(PlayerId(9), "p9".to_string(), JoinRole::Player, room, tx)
             .await;
         assert!(joined.is_ok());
 
@@ -247,11 +336,11 @@ mod tests {
     async fn moving_between_rooms_clears_old_membership() {
         let manager = RoomManager::new(Instant::now(), 50, 8);
         let room_a = manager
-            .get_or_create_room(config("a", 8), map())
+            .get_or_create_room("a", || Ok((config("a", 8), map())))
             .await
             .expect("room a");
         let room_b = manager
-            .get_or_create_room(config("b", 8), map())
+            .get_or_create_room("b", || Ok((config("b", 8), map())))
             .await
             .expect("room b");
 
@@ -261,6 +350,7 @@ mod tests {
             .join_room(
                 PlayerId(77),
                 "p77".to_string(),
+                JoinRole::Player,
                 Arc::clone(&room_a),
                 tx.clone(),
             )
@@ -269,7 +359,13 @@ mod tests {
         assert!(room_a.contains_player(PlayerId(77)).await);
 
         let joined_b = manager
-            .join_room(PlayerId(77), "p77".to_string(), Arc::clone(&room_b), tx)
+            .join_room(
+                PlayerId(77),
+                "p77".to_string(),
+                JoinRole::Player,
+                Arc::clone(&room_b),
+                tx,
+            )
             .await;
         assert!(joined_b.is_ok());
 
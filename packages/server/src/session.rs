@@ -0,0 +1,642 @@
+//! A player's session: claims the player id, dispatches `ClientMsg`s to rooms
+//! and playbacks, and keeps the slot for resuming once the connection drops.
+//! The connection itself is a `Transport`, so the same logic runs over
+//! WebRTC, WebSocket, UDP or in memory.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use physics_core::{Mutator, Ruleset};
+use serde::Deserialize;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{error, info, warn};
+
+use crate::binary::{encode_pong, encode_welcome, ClientMsg};
+use crate::constants::{
+    DEFAULT_MAP_NAME, DEFAULT_ROOM_ID, OUTBOUND_CHANNEL_CAPACITY, RESUME_GRACE_MS, TICK_MILLIS,
+};
+use crate::demo::Demo;
+use crate::game::WeaponId;
+use crate::map::GameMap;
+use crate::playback::PlaybackHandle;
+use crate::resume::ResumeSlots;
+use crate::room::{PlayerId, PlayerInput, RoomConfig, RoomHandle};
+use crate::room_manager::{RoomCreateError, RoomManager};
+use crate::tick::MissedTickPolicy;
+
+/// Server-wide settings and state shared by every session.
+pub struct AppState {
+    pub room_manager: Arc<RoomManager>,
+    pub next_player_id: AtomicU64,
+    pub map_dir: PathBuf,
+    pub ruleset_dir: PathBuf,
+    pub default_ruleset: Ruleset,
+    pub demo_dir: Option<PathBuf>,
+    pub started_at: Instant,
+    pub max_connections_per_ip: usize,
+    pub max_message_bytes: usize,
+    pub max_players_per_room: usize,
+    pub max_spectators_per_room: usize,
+    pub missed_tick_policy: MissedTickPolicy,
+    pub ip_connections: Mutex<HashMap<IpAddr, usize>>,
+    pub resume_slots: Mutex<ResumeSlots>,
+    pub game_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TicketClaims {
+    pub room_id: String,
+    pub session_id: String,
+    pub exp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportClosed;
+
+/// A connection carrying one session. `recv` must be cancel-safe: the session
+/// polls it alongside its outbound queue.
+pub trait Transport: Send {
+    /// The next client message, or `None` once the connection has closed.
+    fn recv(&mut self) -> impl Future<Output = Option<ClientMsg>> + Send;
+
+    fn send(&mut self, payload: Bytes) -> impl Future<Output = Result<(), TransportClosed>> + Send;
+
+    /// Transports with a reliable channel send the welcome on it.
+    fn send_welcome(
+        &mut self,
+        payload: Bytes,
+    ) -> impl Future<Output = Result<(), TransportClosed>> + Send {
+        self.send(payload)
+    }
+}
+
+/// Runs a session until the transport closes or the client is dropped.
+/// `resume` is a token from an earlier welcome.
+pub async fn run_session<T: Transport>(
+    state: &Arc<AppState>,
+    mut transport: T,
+    ticket: Option<TicketClaims>,
+    resume: Option<&str>,
+) {
+    let claimed_room_id = ticket.as_ref().map(|claims| claims.room_id.clone());
+    let session_id = ticket.map(|claims| claims.session_id);
+
+    let claim = {
+        let mut resume_slots = state.resume_slots.lock().await;
+        match resume_slots.claim(resume, session_id.as_deref(), Instant::now()) {
+            Some(claim) => {
+                info!(player_id = claim.player_id.0, "resuming player slot");
+                claim
+            }
+            None => {
+                let player_id = PlayerId(state.next_player_id.fetch_add(1, Ordering::Relaxed));
+                resume_slots.issue(player_id, session_id)
+            }
+        }
+    };
+    let player_id = claim.player_id;
+    let (outbound_tx, mut outbound_rx) = mpsc::channel::<Bytes>(OUTBOUND_CHANNEL_CAPACITY);
+    let mut current_room: Option<Arc<RoomHandle>> = None;
+    let mut watching: Option<Arc<PlaybackHandle>> = None;
+    let mut username = format!("player{}", player_id.0);
+    let client = Client {
+        player_id,
+        outbound_tx: &outbound_tx,
+        claimed_room_id: claimed_room_id.as_deref(),
+    };
+
+    let welcome = Bytes::from(encode_welcome(player_id.0, &claim.token));
+    if transport.send_welcome(welcome).await.is_ok() {
+        loop {
+            tokio::select! {
+                inbound = transport.recv() => {
+                    let Some(client_msg) = inbound else {
+                        break;
+                    };
+                    let keep_running = handle_client_msg(
+                        state,
+                        &mut current_room,
+                        &mut watching,
+                        &mut username,
+                        client,
+                        client_msg,
+                    )
+                    .await;
+                    if !keep_running {
+                        break;
+                    }
+                }
+                Some(payload) = outbound_rx.recv() => {
+                    if transport.send(payload).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Players keep their slot for a while in case the client comes back.
+    if let Some(room) = current_room.take() {
+        room.disconnect(player_id, outbound_tx.clone());
+    }
+    if let Some(playback) = watching.take() {
+        playback.unwatch(player_id);
+    }
+    state.resume_slots.lock().await.release(
+        &claim,
+        Duration::from_millis(RESUME_GRACE_MS),
+        Instant::now(),
+    );
+}
+
+/// Both ends of an in-process connection, for tests and embedded clients.
+pub struct MemoryTransport {
+    inbound: mpsc::Receiver<ClientMsg>,
+    outbound: mpsc::Sender<Bytes>,
+}
+
+/// The client end of a `MemoryTransport`. Dropping `tx` closes the session.
+pub struct MemoryClient {
+    pub tx: mpsc::Sender<ClientMsg>,
+    pub rx: mpsc::Receiver<Bytes>,
+}
+
+impl MemoryTransport {
+    pub fn pair(capacity: usize) -> (Self, MemoryClient) {
+        let (client_tx, inbound) = mpsc::channel(capacity);
+        let (outbound, client_rx) = mpsc::channel(capacity);
+        (
+            Self { inbound, outbound },
+            MemoryClient {
+                tx: client_tx,
+                rx: client_rx,
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    async fn recv(&mut self) -> Option<ClientMsg> {
+        self.inbound.recv().await
+    }
+
+    async fn send(&mut self, payload: Bytes) -> Result<(), TransportClosed> {
+        self.outbound
+            .send(payload)
+            .await
+            .map_err(|_| TransportClosed)
+    }
+}
+
+/// What a session knows about its client from the handshake on.
+#[derive(Clone, Copy)]
+struct Client<'a> {
+    player_id: PlayerId,
+    outbound_tx: &'a mpsc::Sender<Bytes>,
+    claimed_room_id: Option<&'a str>,
+}
+
+async fn handle_client_msg(
+    state: &Arc<AppState>,
+    current_room: &mut Option<Arc<RoomHandle>>,
+    watching: &mut Option<Arc<PlaybackHandle>>,
+    username: &mut String,
+    client: Client<'_>,
+    msg: ClientMsg,
+) -> bool {
+    let Client {
+        player_id,
+        outbound_tx,
+        claimed_room_id,
+    } = client;
+    match msg {
+        ClientMsg::Hello {
+            username: requested_name,
+        } => {
+            if current_room.is_some() {
+                info!(player_id = player_id.0, "ignoring hello after room join");
+                return true;
+            }
+            if !requested_name.is_empty() {
+                *username = requested_name;
+            }
+            true
+        }
+        ClientMsg::JoinRoom {
+            room_id,
+            map,
+            ruleset,
+            role,
+        } => {
+            if let Some(playback) = watching.take() {
+                playback.unwatch(player_id);
+            }
+            let room_ref = if let Some(claimed) = claimed_room_id {
+                if let Some(ref client_room) = room_id {
+                    if client_room != claimed {
+                        warn!(
+                            player_id = player_id.0,
+                            client_room_id = client_room,
+                            ticket_room_id = claimed,
+                            "JoinRoom: client room_id overridden by ticket"
+                        );
+                    }
+                }
+                claimed.to_string()
+            } else {
+                room_id.unwrap_or_else(|| DEFAULT_ROOM_ID.to_string())
+            };
+            // The map and ruleset only matter to whoever creates the room.
+            let create = || {
+                let map_name = map.unwrap_or_else(|| DEFAULT_MAP_NAME.to_string());
+                let game_map = load_map(&state.map_dir, &map_name)
+                    .ok_or_else(|| RoomCreateError::Other("room map unavailable".to_string()))?;
+                let rules = match ruleset {
+                    Some(spec) => load_ruleset(&state.ruleset_dir, &state.default_ruleset, &spec)
+                        .ok_or_else(|| {
+                        RoomCreateError::Other("ruleset unavailable".to_string())
+                    })?,
+                    None => state.default_ruleset.clone(),
+                };
+                let config = RoomConfig {
+                    name: room_ref.clone(),
+                    max_players: state.max_players_per_room,
+                    max_spectators: state.max_spectators_per_room,
+                    tick_millis: TICK_MILLIS,
+                    missed_tick_policy: state.missed_tick_policy,
+                    rules,
+                    demo_dir: state.demo_dir.clone(),
+                };
+                Ok((config, game_map))
+            };
+            let target_room = match state
+                .room_manager
+                .get_or_create_room(&room_ref, create)
+                .await
+            {
+                Ok(room) => room,
+                Err(err) => {
+                    warn!(
+                        player_id = player_id.0,
+                        room_ref,
+                        reason = %err,
+                        "join rejected: room create failed"
+                    );
+                    return true;
+                }
+            };
+
+            match state
+                .room_manager
+                .join_room(
+                    player_id,
+                    username.clone(),
+                    role,
+                    Arc::clone(&target_room),
+                    outbound_tx.clone(),
+                )
+                .await
+            {
+                Ok(success) => {
+                    if outbound_tx.try_send(success.room_state).is_err() {
+                        return false;
+                    }
+                    *current_room = Some(success.room);
+                    true
+                }
+                Err(rejection) => {
+                    let _ = outbound_tx.try_send(rejection);
+                    true
+                }
+            }
+        }
+        ClientMsg::Input {
+            seq,
+            key_up,
+            key_down,
+            key_left,
+            key_right,
+            mouse_down,
+            weapon_switch,
+            weapon_scroll,
+            aim_angle,
+            facing_left,
+            fire_seq,
+            switch_seq,
+            switch_weapon,
+            latched,
+        } => {
+            let Some(room) = current_room.as_ref() else {
+                return true;
+            };
+            let input = PlayerInput {
+                key_up,
+                key_down,
+                key_left,
+                key_right,
+                mouse_down,
+                weapon_switch: WeaponId::try_from(weapon_switch).ok(),
+                weapon_scroll: weapon_scroll as i8,
+                aim_angle,
+                facing_left,
+                fire_seq,
+                switch_seq,
+                switch_weapon: WeaponId::try_from(switch_weapon).ok(),
+                latched,
+            };
+            room.set_input(player_id, seq, input);
+            true
+        }
+        ClientMsg::WatchDemo { name } => {
+            if claimed_room_id.is_some() {
+                warn!(
+                    player_id = player_id.0,
+                    demo = name,
+                    "watch rejected: ticket is bound to a room"
+                );
+                return true;
+            }
+            let Some(demo_dir) = &state.demo_dir else {
+                warn!(
+                    player_id = player_id.0,
+                    demo = name,
+                    "watch rejected: demos disabled"
+                );
+                return true;
+            };
+            if current_room.take().is_some() {
+                state.room_manager.leave_player(player_id).await;
+            }
+            if let Some(playback) = watching.take() {
+                playback.unwatch(player_id);
+            }
+
+            let playback = match state.room_manager.playback(&name).await {
+                Some(playback) => playback,
+                None => {
+                    let Some(demo) = load_demo(demo_dir, &name) else {
+                        return true;
+                    };
+                    let Some(game_map) = load_map(&state.map_dir, &demo.header.map_name) else {
+                        warn!(
+                            player_id = player_id.0,
+                            demo = name,
+                            "watch rejected: demo map unavailable"
+                        );
+                        return true;
+                    };
+                    if let Err(err) = demo.check_map(&game_map) {
+                        warn!(
+                            player_id = player_id.0,
+                            demo = name,
+                            reason = %err,
+                            "watch rejected"
+                        );
+                        return true;
+                    }
+                    match state
+                        .room_manager
+                        .start_playback(name.clone(), demo, game_map)
+                        .await
+                    {
+                        Ok(playback) => playback,
+                        Err(_) => {
+                            warn!(
+                                player_id = player_id.0,
+                                demo = name,
+                                "watch rejected: playback create failed"
+                            );
+                            return true;
+                        }
+                    }
+                }
+            };
+            if playback.watch(player_id, outbound_tx.clone()).await {
+                info!(
+                    player_id = player_id.0,
+                    demo = playback.name(),
+                    "watching demo"
+                );
+                *watching = Some(playback);
+            }
+            true
+        }
+        ClientMsg::DemoControl { control } => {
+            if let Some(playback) = watching.as_ref() {
+                playback.control(player_id, control);
+            }
+            true
+        }
+        ClientMsg::Ping { client_time_ms } => {
+            let server_time_ms = state.started_at.elapsed().as_millis() as u64;
+            let _ = outbound_tx.try_send(Bytes::from(encode_pong(client_time_ms, server_time_ms)));
+            true
+        }
+    }
+}
+
+fn load_map(map_dir: &Path, map_name: &str) -> Option<GameMap> {
+    match GameMap::load(map_dir, map_name) {
+        Ok(map) => Some(map),
+        Err(primary_err) => {
+            error!("failed to load map '{map_name}': {primary_err}");
+            None
+        }
+    }
+}
+
+/// Resolve a `+`-separated ruleset spec such as `competitive+vampire` or
+/// `instagib+low-gravity`. Mutator names apply on top of the ruleset; the first
+/// part may instead name a ruleset file, otherwise `base` is used. A ruleset
+/// file wins over a mutator of the same name.
+pub fn load_ruleset(ruleset_dir: &Path, base: &Ruleset, spec: &str) -> Option<Ruleset> {
+    let mut parts = spec.split('+').peekable();
+    let names_file = |part: &&str| {
+        Mutator::from_name(part).is_none() || ruleset_dir.join(format!("{part}.rules")).is_file()
+    };
+    let rules = match parts.next_if(names_file) {
+        Some(name) => load_ruleset_file(ruleset_dir, name)?,
+        None => base.clone(),
+    };
+    let mut mutators = Vec::new();
+    for part in parts {
+        let Some(mutator) = Mutator::from_name(part) else {
+            error!("unknown mutator '{part}' in ruleset '{spec}'");
+            return None;
+        };
+        mutators.push(mutator);
+    }
+    Some(rules.with_mutators(&mutators))
+}
+
+/// Ruleset names come from clients, so only plain file stems are accepted.
+fn load_ruleset_file(ruleset_dir: &Path, name: &str) -> Option<Ruleset> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        error!("rejected ruleset name '{name}'");
+        return None;
+    }
+    let path = ruleset_dir.join(format!("{name}.rules"));
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => {
+            error!("failed to load ruleset '{name}': {err}");
+            return None;
+        }
+    };
+    match Ruleset::parse(&content) {
+        Ok(rules) => Some(rules),
+        Err(err) => {
+            error!("invalid ruleset '{name}': {err}");
+            None
+        }
+    }
+}
+
+/// Demo names come from clients, so only plain file stems are accepted.
+fn load_demo(demo_dir: &Path, name: &str) -> Option<Demo> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        error!("rejected demo name '{name}'");
+        return None;
+    }
+    match Demo::load(&demo_dir.join(format!("{name}.demo"))) {
+        Ok(demo) => Some(demo),
+        Err(err) => {
+            error!("failed to load demo '{name}': {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use bytes::Bytes;
+    use physics_core::Ruleset;
+    use tokio::sync::Mutex;
+
+    use super::{load_ruleset, run_session, AppState, MemoryClient, MemoryTransport};
+    use crate::binary::{ClientMsg, JoinRole};
+    use crate::resume::ResumeSlots;
+    use crate::room_manager::RoomManager;
+    use crate::tick::MissedTickPolicy;
+    use binary_protocol::{MSG_ROOM_STATE, MSG_WELCOME};
+
+    fn state() -> Arc<AppState> {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        Arc::new(AppState {
+            room_manager: Arc::new(RoomManager::new(Instant::now(), 4, 4)),
+            next_player_id: AtomicU64::new(1),
+            map_dir: manifest_dir.join("../../apps/client/public/maps"),
+            ruleset_dir: manifest_dir.join("rulesets"),
+            default_ruleset: Ruleset::default(),
+            demo_dir: None,
+            started_at: Instant::now(),
+            max_connections_per_ip: 4,
+            max_message_bytes: 65536,
+            max_players_per_room: 4,
+            max_spectators_per_room: 0,
+            missed_tick_policy: MissedTickPolicy::default(),
+            ip_connections: Mutex::new(HashMap::new()),
+            resume_slots: Mutex::new(ResumeSlots::default()),
+            game_secret: None,
+        })
+    }
+
+    fn connect(
+        state: &Arc<AppState>,
+        resume: Option<String>,
+    ) -> (MemoryClient, tokio::task::JoinHandle<()>) {
+        let (transport, client) = MemoryTransport::pair(64);
+        let state = Arc::clone(state);
+        let session = tokio::spawn(async move {
+            run_session(&state, transport, None, resume.as_deref()).await;
+        });
+        (client, session)
+    }
+
+    async fn recv_kind(client: &mut MemoryClient, kind: u8) -> Bytes {
+        loop {
+            match client.rx.recv().await {
+                Some(msg) if msg.first() == Some(&kind) => return msg,
+                Some(_) => {}
+                None => panic!("session closed before message {kind:#x}"),
+            }
+        }
+    }
+
+    /// Player id and resume token from a welcome.
+    fn read_welcome(msg: &[u8]) -> (u64, String) {
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&msg[1..9]);
+        let token = String::from_utf8_lossy(&msg[10..10 + msg[9] as usize]).into_owned();
+        (u64::from_le_bytes(id), token)
+    }
+
+    #[test]
+    fn shipped_rulesets_load() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("rulesets");
+        for name in ["competitive", "instagib"] {
+            assert!(
+                load_ruleset(&dir, &Ruleset::default(), name).is_some(),
+                "{name}"
+            );
+        }
+        let instagib = load_ruleset(&dir, &Ruleset::default(), "instagib+vampire")
+            .expect("instagib file with a mutator should load");
+        assert_eq!(instagib.spawn_weapon, 5);
+        assert!(instagib.vampire > 0.0);
+    }
+
+    #[tokio::test]
+    async fn sessions_join_rooms_and_resume_over_an_in_memory_transport() {
+        let state = state();
+        let (mut client, session) = connect(&state, None);
+        let (player_id, token) = read_welcome(&recv_kind(&mut client, MSG_WELCOME).await);
+
+        for msg in [
+            ClientMsg::Hello {
+                username: "alice".to_string(),
+            },
+            ClientMsg::JoinRoom {
+                room_id: Some("memory".to_string()),
+                map: None,
+                ruleset: None,
+                role: JoinRole::Player,
+            },
+        ] {
+            client
+                .tx
+                .send(msg)
+                .await
+                .expect("session should be running");
+        }
+        recv_kind(&mut client, MSG_ROOM_STATE).await;
+
+        // Closing the transport ends the session but keeps the slot.
+        drop(client.tx);
+        session.await.expect("session should end cleanly");
+        let (mut client, _session) = connect(&state, Some(token));
+        let (resumed_id, _) = read_welcome(&recv_kind(&mut client, MSG_WELCOME).await);
+        assert_eq!(resumed_id, player_id);
+    }
+}
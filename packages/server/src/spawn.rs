@@ -0,0 +1,152 @@
+use rand::Rng;
+
+use crate::constants::{PLAYER_HALF_H, SPAWN_OFFSET_X, TILE_H, TILE_W};
+use crate::map::GameMap;
+use crate::physics::PlayerState;
+use physics_core::constants::PLAYER_HALF_W;
+use physics_core::weapon::ray_trace;
+
+/// Enemies further away than this don't make a spawn point any safer.
+const SAFE_DISTANCE: f32 = 1200.0;
+/// Score multiplier for spawn points a living enemy can see.
+const VISIBLE_PENALTY: f32 = 0.5;
+
+/// Picks respawn points away from living enemies, out of their line of sight
+/// and not on the point used last.
+#[derive(Default)]
+pub struct SpawnSelector {
+    last_used: Option<usize>,
+}
+
+impl SpawnSelector {
+    /// Chooses randomly among the better half of the scored points so spawns
+    /// stay hard to predict. Points occupied by a living player are skipped
+    /// unless `allow_occupied`; if every point is ruled out any point will do.
+    pub fn select(
+        &mut self,
+        map: &GameMap,
+        players: &[PlayerState],
+        player_id: u64,
+        allow_occupied: bool,
+        rng: &mut impl Rng,
+    ) -> Option<(i32, i32)> {
+        let scored = |idx: usize| (idx, self.score(map, players, player_id, idx));
+        let mut candidates: Vec<(usize, f32)> = (0..map.respawns.len())
+            .filter(|idx| Some(*idx) != self.last_used)
+            .filter(|idx| {
+                let (row, col) = map.respawns[*idx];
+                let (x, y) = spawn_position(row, col);
+                allow_occupied || !is_occupied(players, player_id, x, y)
+            })
+            .map(scored)
+            .collect();
+        if candidates.is_empty() {
+            candidates = (0..map.respawns.len()).map(scored).collect();
+        }
+        if candidates.is_empty() {
+            return None;
+        }
+
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let pool = candidates.len().div_ceil(2);
+        let (idx, _) = candidates[rng.gen_range(0..pool)];
+        self.last_used = Some(idx);
+        Some(map.respawns[idx])
+    }
+
+    /// Distance to the nearest living enemy, halved when that enemy can see the point.
+    fn score(&self, map: &GameMap, players: &[PlayerState], player_id: u64, idx: usize) -> f32 {
+        let (row, col) = map.respawns[idx];
+        let (x, y) = spawn_position(row, col);
+        players
+            .iter()
+            .filter(|enemy| enemy.id != player_id && !enemy.dead)
+            .map(|enemy| {
+                let dx = x - enemy.x;
+                let dy = y - enemy.y;
+                let distance = (dx * dx + dy * dy).sqrt();
+                let trace = ray_trace(map, enemy.x, enemy.y, dy.atan2(dx), distance);
+                let visible = !trace.hit_wall;
+                let score = distance.min(SAFE_DISTANCE);
+                if visible {
+                    score * VISIBLE_PENALTY
+                } else {
+                    score
+                }
+            })
+            .fold(SAFE_DISTANCE, f32::min)
+    }
+}
+
+/// Player center for the respawn marker at `row`, `col`.
+pub fn spawn_position(row: i32, col: i32) -> (f32, f32) {
+    let x = col as f32 * TILE_W + SPAWN_OFFSET_X;
+    let y = row as f32 * TILE_H - PLAYER_HALF_H;
+    (x, y)
+}
+
+/// Whether a living player other than `player_id` overlaps a body at `x`, `y`.
+pub fn is_occupied(players: &[PlayerState], player_id: u64, x: f32, y: f32) -> bool {
+    players
+        .iter()
+        .any(|other| other.id != player_id && !other.dead && overlaps(other, x, y))
+}
+
+pub fn overlaps(player: &PlayerState, x: f32, y: f32) -> bool {
+    (player.x - x).abs() < PLAYER_HALF_W * 2.0 && (player.y - y).abs() < PLAYER_HALF_H * 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{spawn_position, SpawnSelector};
+    use crate::map::GameMap;
+    use crate::physics::PlayerState;
+
+    fn open_map(respawns: Vec<(i32, i32)>) -> GameMap {
+        GameMap {
+            rows: 4,
+            cols: 40,
+            bricks: vec![0; 160],
+            respawns,
+            items: Vec::new(),
+            name: "test".to_string(),
+        }
+    }
+
+    fn player_at_spawn(id: u64, row: i32, col: i32) -> PlayerState {
+        let mut player = PlayerState::new(id);
+        (player.x, player.y) = spawn_position(row, col);
+        player
+    }
+
+    #[test]
+    fn never_picks_the_point_next_to_an_enemy() {
+        let map = open_map(vec![(3, 1), (3, 20), (3, 38)]);
+        let players = [player_at_spawn(2, 3, 2)];
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+        for _ in 0..32 {
+            let mut spawns = SpawnSelector::default();
+            let spawn = spawns.select(&map, &players, 1, false, &mut rng);
+            assert_ne!(spawn, Some((3, 1)));
+        }
+    }
+
+    #[test]
+    fn avoids_last_used_and_occupied_points() {
+        let map = open_map(vec![(3, 1), (3, 20), (3, 38)]);
+        let players = [player_at_spawn(2, 3, 38)];
+        let mut spawns = SpawnSelector::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+
+        let first = spawns.select(&map, &players, 1, false, &mut rng);
+        let second = spawns.select(&map, &players, 1, false, &mut rng);
+
+        assert_ne!(first, second);
+        assert_ne!(first, Some((3, 38)));
+        assert_ne!(second, Some((3, 38)));
+    }
+}
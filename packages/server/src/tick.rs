@@ -0,0 +1,170 @@
+//! Fixed-timestep scheduling for room ticks, plus the per-room measurements
+//! of how long ticks take and how often the room falls behind.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::constants::MAX_CATCH_UP_TICKS;
+
+/// What a room does when it falls behind its tick schedule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTickPolicy {
+    /// Run late ticks back to back until caught up, dropping any beyond
+    /// `MAX_CATCH_UP_TICKS`. Simulated time stays in step with real time.
+    #[default]
+    Burst,
+    /// Drop the missed ticks and carry on at the next slot of the schedule.
+    Skip,
+    /// Restart the schedule from the late tick; the game runs slower instead.
+    Slow,
+}
+
+impl MissedTickPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "burst" => Some(Self::Burst),
+            "skip" => Some(Self::Skip),
+            "slow" => Some(Self::Slow),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Burst => "burst",
+            Self::Skip => "skip",
+            Self::Slow => "slow",
+        }
+    }
+}
+
+/// Deadlines for a fixed tick period, with `MissedTickPolicy` deciding what
+/// happens to the ticks a room was too slow to run on time.
+pub struct TickScheduler {
+    period: Duration,
+    next: Instant,
+    policy: MissedTickPolicy,
+}
+
+impl TickScheduler {
+    pub fn new(period: Duration, start: Instant, policy: MissedTickPolicy) -> Self {
+        Self {
+            period,
+            next: start + period,
+            policy,
+        }
+    }
+
+    /// When the next tick is due.
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+
+    /// Moves past the tick that was due at `deadline()`, given the time it
+    /// finished. Returns how many scheduled ticks were dropped.
+    pub fn advance(&mut self, now: Instant) -> u64 {
+        self.next += self.period;
+        if now <= self.next {
+            return 0;
+        }
+        // Ticks due at or before `now`, counting the one at `self.next`.
+        let overdue = 1 + ((now - self.next).as_nanos() / self.period.as_nanos().max(1)) as u64;
+        match self.policy {
+            MissedTickPolicy::Burst => {
+                let dropped = overdue.saturating_sub(MAX_CATCH_UP_TICKS);
+                self.next += self.period * dropped as u32;
+                dropped
+            }
+            MissedTickPolicy::Skip => {
+                self.next += self.period * overdue as u32;
+                overdue
+            }
+            MissedTickPolicy::Slow => {
+                self.next = now + self.period;
+                0
+            }
+        }
+    }
+}
+
+/// Counters a room updates every tick; readable from any thread.
+#[derive(Default)]
+pub struct TickMetrics {
+    ticks_total: AtomicU64,
+    overruns_total: AtomicU64,
+    dropped_ticks_total: AtomicU64,
+    last_tick_micros: AtomicU64,
+    max_tick_micros: AtomicU64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickMetricsSnapshot {
+    pub ticks_total: u64,
+    /// Ticks that took longer than the tick period.
+    pub overruns_total: u64,
+    /// Scheduled ticks that never ran because the room fell behind.
+    pub dropped_ticks_total: u64,
+    pub last_tick_micros: u64,
+    pub max_tick_micros: u64,
+}
+
+impl TickMetrics {
+    pub fn observe(&self, took: Duration, period: Duration, dropped: u64) {
+        let micros = took.as_micros() as u64;
+        self.ticks_total.fetch_add(1, Ordering::Relaxed);
+        if took > period {
+            self.overruns_total.fetch_add(1, Ordering::Relaxed);
+        }
+        self.dropped_ticks_total
+            .fetch_add(dropped, Ordering::Relaxed);
+        self.last_tick_micros.store(micros, Ordering::Relaxed);
+        self.max_tick_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> TickMetricsSnapshot {
+        TickMetricsSnapshot {
+            ticks_total: self.ticks_total.load(Ordering::Relaxed),
+            overruns_total: self.overruns_total.load(Ordering::Relaxed),
+            dropped_ticks_total: self.dropped_ticks_total.load(Ordering::Relaxed),
+            last_tick_micros: self.last_tick_micros.load(Ordering::Relaxed),
+            max_tick_micros: self.max_tick_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{MissedTickPolicy, TickScheduler};
+    use crate::constants::MAX_CATCH_UP_TICKS;
+
+    #[test]
+    fn policies_differ_only_once_a_room_falls_behind() {
+        let period = Duration::from_millis(16);
+        let start = Instant::now();
+        let late = start + period * 10 + Duration::from_millis(5);
+        let deadlines = |policy| {
+            let mut scheduler = TickScheduler::new(period, start, policy);
+            assert_eq!(scheduler.advance(start + period), 0);
+            assert_eq!(scheduler.deadline(), start + period * 2);
+            let dropped = scheduler.advance(late);
+            (dropped, scheduler.deadline())
+        };
+
+        // Eight ticks are overdue at `late`; burst runs a bounded number of them.
+        let overdue = 8;
+        assert_eq!(
+            deadlines(MissedTickPolicy::Burst),
+            (
+                overdue - MAX_CATCH_UP_TICKS,
+                start + period * (3 + (overdue - MAX_CATCH_UP_TICKS) as u32)
+            )
+        );
+        assert_eq!(
+            deadlines(MissedTickPolicy::Skip),
+            (overdue, start + period * 11)
+        );
+        assert_eq!(deadlines(MissedTickPolicy::Slow), (0, late + period));
+    }
+}
@@ -0,0 +1,257 @@
+//! Framing for the native UDP transport. Every datagram starts with a kind
+//! byte; `UDP_DATA` carries the regular binary protocol unchanged.
+//!
+//! A peer sends `HELLO`, gets back a `COOKIE` bound to its address, and
+//! echoes it in `CONNECT` (with its game ticket and resume token). Only then
+//! is a session started, so spoofed source addresses can't allocate one.
+//! `DATA` and `CLOSE` in both directions carry the cookie's session tag, so
+//! spoofing a peer's address alone can't feed input to or end its session.
+
+use std::net::SocketAddr;
+
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+
+use crate::constants::UDP_COOKIE_TTL_MS;
+
+pub const UDP_HELLO: u8 = 0;
+pub const UDP_COOKIE: u8 = 1;
+pub const UDP_CONNECT: u8 = 2;
+pub const UDP_DATA: u8 = 3;
+pub const UDP_CLOSE: u8 = 4;
+
+const MAC_LEN: usize = 32;
+const COOKIE_LEN: usize = 8 + MAC_LEN;
+pub const SESSION_TAG_LEN: usize = 8;
+/// `HELLO` is padded to the size of the reply so the handshake can't be used
+/// to amplify traffic towards a spoofed address.
+pub const UDP_HELLO_LEN: usize = 1 + COOKIE_LEN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    pub expires_ms: u64,
+    pub mac: [u8; MAC_LEN],
+}
+
+pub type SessionTag = [u8; SESSION_TAG_LEN];
+
+impl Cookie {
+    /// Leading bytes of the MAC. Only the peer the cookie was sent to knows
+    /// them, so they identify its session on every later packet.
+    pub fn session_tag(&self) -> SessionTag {
+        let mut tag = [0; SESSION_TAG_LEN];
+        tag.copy_from_slice(&self.mac[..SESSION_TAG_LEN]);
+        tag
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum UdpPacket<'a> {
+    Hello,
+    Cookie(Cookie),
+    Connect {
+        cookie: Cookie,
+        ticket: Option<&'a str>,
+        resume: Option<&'a str>,
+    },
+    Data {
+        tag: SessionTag,
+        payload: &'a [u8],
+    },
+    Close {
+        tag: SessionTag,
+    },
+}
+
+pub fn decode_udp_packet(data: &[u8]) -> Option<UdpPacket<'_>> {
+    let (&kind, rest) = data.split_first()?;
+    match kind {
+        UDP_HELLO if data.len() >= UDP_HELLO_LEN => Some(UdpPacket::Hello),
+        UDP_COOKIE => Some(UdpPacket::Cookie(read_cookie(rest)?)),
+        UDP_CONNECT => {
+            let cookie = read_cookie(rest)?;
+            let rest = &rest[COOKIE_LEN..];
+            let ticket_len = u16::from_le_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+            let ticket = std::str::from_utf8(rest.get(2..2 + ticket_len)?).ok()?;
+            let rest = &rest[2 + ticket_len..];
+            let resume_len = *rest.first()? as usize;
+            let resume = std::str::from_utf8(rest.get(1..1 + resume_len)?).ok()?;
+            Some(UdpPacket::Connect {
+                cookie,
+                ticket: Some(ticket).filter(|ticket| !ticket.is_empty()),
+                resume: Some(resume).filter(|resume| !resume.is_empty()),
+            })
+        }
+        UDP_DATA => Some(UdpPacket::Data {
+            tag: read_tag(rest)?,
+            payload: &rest[SESSION_TAG_LEN..],
+        }),
+        UDP_CLOSE => Some(UdpPacket::Close {
+            tag: read_tag(rest)?,
+        }),
+        _ => None,
+    }
+}
+
+pub fn encode_udp_hello() -> Vec<u8> {
+    let mut out = vec![0; UDP_HELLO_LEN];
+    out[0] = UDP_HELLO;
+    out
+}
+
+pub fn encode_udp_cookie(cookie: &Cookie) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + COOKIE_LEN);
+    out.push(UDP_COOKIE);
+    write_cookie(&mut out, cookie);
+    out
+}
+
+/// Tickets longer than `u16::MAX` bytes and resume tokens longer than
+/// `u8::MAX` are sent empty; neither is valid anyway.
+pub fn encode_udp_connect(cookie: &Cookie, ticket: Option<&str>, resume: Option<&str>) -> Vec<u8> {
+    let ticket = ticket
+        .filter(|ticket| ticket.len() <= u16::MAX as usize)
+        .unwrap_or("");
+    let resume = resume
+        .filter(|resume| resume.len() <= u8::MAX as usize)
+        .unwrap_or("");
+    let mut out = Vec::with_capacity(1 + COOKIE_LEN + 3 + ticket.len() + resume.len());
+    out.push(UDP_CONNECT);
+    write_cookie(&mut out, cookie);
+    out.extend_from_slice(&(ticket.len() as u16).to_le_bytes());
+    out.extend_from_slice(ticket.as_bytes());
+    out.push(resume.len() as u8);
+    out.extend_from_slice(resume.as_bytes());
+    out
+}
+
+pub fn encode_udp_data(tag: &SessionTag, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + SESSION_TAG_LEN + payload.len());
+    out.push(UDP_DATA);
+    out.extend_from_slice(tag);
+    out.extend_from_slice(payload);
+    out
+}
+
+pub fn encode_udp_close(tag: &SessionTag) -> Vec<u8> {
+    let mut out = Vec::with_capacity(1 + SESSION_TAG_LEN);
+    out.push(UDP_CLOSE);
+    out.extend_from_slice(tag);
+    out
+}
+
+fn read_tag(data: &[u8]) -> Option<SessionTag> {
+    data.get(..SESSION_TAG_LEN)?.try_into().ok()
+}
+
+fn read_cookie(data: &[u8]) -> Option<Cookie> {
+    let bytes = data.get(..COOKIE_LEN)?;
+    let expires_ms = u64::from_le_bytes(bytes[..8].try_into().ok()?);
+    let mac = bytes[8..].try_into().ok()?;
+    Some(Cookie { expires_ms, mac })
+}
+
+fn write_cookie(out: &mut Vec<u8>, cookie: &Cookie) {
+    out.extend_from_slice(&cookie.expires_ms.to_le_bytes());
+    out.extend_from_slice(&cookie.mac);
+}
+
+/// Signs handshake cookies. The key lives only in memory, so cookies from a
+/// previous server process are rejected.
+pub struct CookieKey {
+    keyed: Hmac<Sha256>,
+}
+
+impl CookieKey {
+    pub fn random() -> Self {
+        let mut key = [0u8; 64];
+        rand::thread_rng().fill(&mut key);
+        Self {
+            keyed: <Hmac<Sha256> as KeyInit>::new(&key.into()),
+        }
+    }
+
+    pub fn issue(&self, peer: SocketAddr, now_ms: u64) -> Cookie {
+        let expires_ms = now_ms + UDP_COOKIE_TTL_MS;
+        let mac = self.mac(peer, expires_ms).finalize().into_bytes().into();
+        Cookie { expires_ms, mac }
+    }
+
+    pub fn verify(&self, peer: SocketAddr, cookie: &Cookie, now_ms: u64) -> bool {
+        cookie.expires_ms >= now_ms
+            && self
+                .mac(peer, cookie.expires_ms)
+                .verify_slice(&cookie.mac)
+                .is_ok()
+    }
+
+    fn mac(&self, peer: SocketAddr, expires_ms: u64) -> Hmac<Sha256> {
+        let mut mac = self.keyed.clone();
+        mac.update(peer.to_string().as_bytes());
+        mac.update(&expires_ms.to_le_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::{
+        decode_udp_packet, encode_udp_close, encode_udp_connect, encode_udp_data, encode_udp_hello,
+        CookieKey, UdpPacket,
+    };
+    use crate::constants::UDP_COOKIE_TTL_MS;
+
+    #[test]
+    fn cookies_only_connect_the_address_they_were_issued_to() {
+        let key = CookieKey::random();
+        let peer: SocketAddr = "10.0.0.1:4000".parse().expect("valid address");
+        let other: SocketAddr = "10.0.0.2:4000".parse().expect("valid address");
+        assert_eq!(
+            decode_udp_packet(&encode_udp_hello()),
+            Some(UdpPacket::Hello)
+        );
+        assert_eq!(decode_udp_packet(&encode_udp_hello()[..8]), None);
+
+        let cookie = key.issue(peer, 1_000);
+        let packet = encode_udp_connect(&cookie, Some("ticket"), None);
+        let Some(UdpPacket::Connect {
+            cookie: echoed,
+            ticket,
+            resume,
+        }) = decode_udp_packet(&packet)
+        else {
+            panic!("connect should decode");
+        };
+        assert_eq!((echoed, ticket, resume), (cookie, Some("ticket"), None));
+
+        assert!(key.verify(peer, &echoed, 1_000 + UDP_COOKIE_TTL_MS));
+        assert!(!key.verify(other, &echoed, 1_000));
+        assert!(!key.verify(peer, &echoed, 1_001 + UDP_COOKIE_TTL_MS));
+        assert!(!CookieKey::random().verify(peer, &echoed, 1_000));
+    }
+
+    #[test]
+    fn data_and_close_carry_the_session_tag() {
+        let key = CookieKey::random();
+        let peer: SocketAddr = "10.0.0.1:4000".parse().expect("valid address");
+        let tag = key.issue(peer, 1_000).session_tag();
+        assert_ne!(tag, key.issue(peer, 1_001).session_tag());
+
+        assert_eq!(
+            decode_udp_packet(&encode_udp_data(&tag, b"input")),
+            Some(UdpPacket::Data {
+                tag,
+                payload: b"input"
+            })
+        );
+        assert_eq!(
+            decode_udp_packet(&encode_udp_close(&tag)),
+            Some(UdpPacket::Close { tag })
+        );
+        assert_eq!(decode_udp_packet(&encode_udp_close(&tag)[..4]), None);
+    }
+}